@@ -1,99 +1,100 @@
 extern crate proc_macro;
 use proc_macro::{TokenStream};
-use proc_macro2::{Span};
+use proc_macro2::{Span, TokenStream as TokenStream2};
 use quote::{quote};
-use syn::{parse_macro_input, ItemImpl, ImplItem, ImplItemMethod, ItemTrait, TraitItem, Ident, Block, Visibility, VisPublic, token::Pub};
-//use syn::{ReturnType};
+use syn::{parse_quote, ItemImpl, ImplItem, ImplItemMethod, ItemTrait, TraitItem, TraitItemMethod, Ident, Block, Visibility, VisPublic, token::Pub};
+use syn::{Error, FnArg, GenericArgument, Lit, Meta, NestedMeta, Pat, PathArguments, ReturnType, Signature, Type};
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
 
 /// a macro to generate an empty blueprint just to get the stub functions for inter-blueprint calls
 /// avoids manually specifying an ABI, and allows a concise definition using trait syntax
+///
+/// methods (`&self` or `&mut self`) become stubs calling the component they are converted from (ie. `let c: Trait = address.into();`)
+/// associated functions (ie. constructors) call the blueprint in another package, so they need its address:
+/// `#[blueprint_stub(package = "01...")]`
+///
+/// arguments and returns can be any owned type that can be passed between components (`Decimal`, `Bucket`, `BucketRef`,
+/// `Vec<Bucket>`, tuples, ...).  Anything else is rejected at compile time pointing at the offending part of the signature
 #[proc_macro_attribute]
-pub fn blueprint_stub(_metadata: TokenStream, input: TokenStream) -> TokenStream {
+pub fn blueprint_stub(metadata: TokenStream, input: TokenStream) -> TokenStream {
+    match expand_blueprint_stub(metadata.into(), input.into()) {
+        Ok(output) => output.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand_blueprint_stub(metadata: TokenStream2, input: TokenStream2) -> syn::Result<TokenStream2> {
+    let package = parse_package(metadata)?;
     // parse the trait
-    let mut input = parse_macro_input!(input as ItemTrait);
+    let input: ItemTrait = syn::parse2(input)?;
     // grap the identifier
     let ident = input.ident.clone();
+    if !input.generics.params.is_empty() || input.generics.where_clause.is_some() {
+        return Err(Error::new_spanned(&input.generics, "blueprint_stub traits cannot be generic"));
+    }
     // create the skeleton for impl to be generated
-    let the_impl = TokenStream::from(quote! {
+    let mut the_impl: ItemImpl = parse_quote! {
         #[allow(unused)]
         impl #ident {
 
         }
-    });
-    let mut the_impl = parse_macro_input!(the_impl as ItemImpl);
+    };
+    // associated functions are generated outside of the blueprint since they call another package
+    let mut functions: Vec<TokenStream2> = Vec::new();
     // iterate over the methods creating the implementation and filling the impl items
-    // let decimal_ident = Ident::new(&format!("Decimal"), Span::call_site()); // used to find Decimal type
-    let items = &mut input.items;
-    for i in 0..items.len() {
-        match items[i] {
-            TraitItem::Method(ref mut method) => {
-                //method.semi_token = None;
-                //method.sig.ident =  Ident::new(&format!("{}_{}", ident, i), Span::call_site());
-                let empty_block = TokenStream::from(quote! {
-                    {
-                        panic!("only exists for stub, do not call");
-                    }
-                });
-                /*
-                let block = match method.sig.output {
-                    ReturnType::Default => { empty_block },
-                    ReturnType::Type(_, ref box_type) => {
-                        let is_decimal: bool = match **box_type {
-                            syn::Type::Path(ref type_path) => {
-                                type_path.path.is_ident(&decimal_ident)
-                            },
-                            _ => false
-                        };
-                        if is_decimal {
-                            TokenStream::from(quote! {
-                                { // block returning the default for Decimal
-                                    0.into()
-                                    //let result: #box_type = Default::default();
-                                    //result
-                                }
-                            })
-                        } else {
-                            TokenStream::from(quote! {
-                                { // block returning the default for the type
-                                    let result: #box_type = Default::default();
-                                    result
-                                }
-                            })
+    for item in input.items.iter() {
+        let method = match item {
+            TraitItem::Method(method) => method,
+            _ => return Err(Error::new_spanned(item, "blueprint_stub traits can only contain methods and functions")),
+        };
+        check_method(method)?;
+        if method.sig.receiver().is_none() {
+            let package = package.as_ref().ok_or_else(|| Error::new_spanned(&method.sig,
+                "associated functions need the address of the package to call: #[blueprint_stub(package = \"...\")]"))?;
+            functions.push(function_stub(&ident, package, &method.sig));
+            continue;
+        }
+        let block: Block = parse_quote! {
+            {
+                panic!("only exists for stub, do not call");
+            }
+        };
+        // now update the vec of ImplItem::Method from the TraitItem::Method
+        the_impl.items.push(
+            ImplItem::Method(ImplItemMethod {
+                attrs: the_impl.attrs.clone(), // copy the #[allow(unused)] to each method
+                vis: Visibility::Public(
+                    VisPublic{
+                        pub_token: Pub{
+                            span: Span::call_site()
                         }
                     }
-                };
-                let block = parse_macro_input!(block as Block);
-                */
-                let block = parse_macro_input!(empty_block as Block);
-                method.default = Some(block.clone());
-                // now update the vec of ImplItem::Method from the TraitItem::Method 
-                the_impl.items.push(
-                    ImplItem::Method(ImplItemMethod {
-                        attrs: the_impl.attrs.clone(), // copy the #[allow(unused)] to each method
-                        vis: Visibility::Public(
-                            VisPublic{
-                                pub_token: Pub{
-                                    span: Span::call_site()
-                                }
-                            }
-                        ),
-                        defaultness: None,
-                        sig: method.sig.clone(),
-                        block: block
-                    }
-                )
+                ),
+                defaultness: None,
+                sig: method.sig.clone(),
+                block
+            }
+        )
 
-                );
-            },
-            _ => {}
-        }
+        );
     }
 
     // create the final output
     // a blueprint! with the geenerated impl, but all inside a module so the code is not callable
-    // then reexport only the stubs
+    // then reexport only the stubs, and add the functions calling the other package
     let mod_name = Ident::new(&format!("internal_{}", ident), Span::call_site());
-    TokenStream::from(quote!{
+    let functions_impl = if functions.is_empty() {
+        quote!{}
+    } else {
+        quote!{
+            #[allow(unused)]
+            impl #ident {
+                #(#functions)*
+            }
+        }
+    };
+    Ok(quote!{
         mod #mod_name {
         use super::*;
         blueprint! {
@@ -103,5 +104,425 @@ pub fn blueprint_stub(_metadata: TokenStream, input: TokenStream) -> TokenStream
         }
         // reexport the stub only
         pub use #mod_name::#ident;
+        #functions_impl
     })
-}
\ No newline at end of file
+}
+
+// parse the optional `package = "..."` argument
+fn parse_package(metadata: TokenStream2) -> syn::Result<Option<String>> {
+    let args = Punctuated::<NestedMeta, syn::Token![,]>::parse_terminated.parse2(metadata)?;
+    let mut package = None;
+    for arg in args.iter() {
+        match arg {
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("package") => {
+                match &nv.lit {
+                    Lit::Str(s) => package = Some(s.value()),
+                    lit => return Err(Error::new_spanned(lit, "package must be a string with the package address")),
+                }
+            },
+            _ => return Err(Error::new_spanned(arg, "unknown argument, only `package = \"...\"` is supported")),
+        }
+    }
+    Ok(package)
+}
+
+// stub for an associated function, calling the blueprint of the same name in `package`
+fn function_stub(ident: &Ident, package: &str, sig: &Signature) -> TokenStream2 {
+    let blueprint_name = ident.to_string();
+    let function_name = sig.ident.to_string();
+    let args = sig.inputs.iter().map(|arg| match arg {
+        FnArg::Typed(pat_type) => pat_type.pat.clone(),
+        FnArg::Receiver(_) => unreachable!("functions have no receiver"),
+    });
+    let output = match &sig.output {
+        ReturnType::Default => quote!{ () },
+        ReturnType::Type(_, ty) => quote!{ #ty },
+    };
+    // the arguments are encoded before any local is bound, the locals are prefixed so they can't shadow an argument
+    quote!{
+        pub #sig {
+            let __scrypto_args = vec![#(::scrypto::buffer::scrypto_encode(&#args)),*];
+            let __scrypto_package = <::scrypto::types::Address as ::core::str::FromStr>::from_str(#package).unwrap();
+            let __scrypto_rtn = ::scrypto::core::call_function(__scrypto_package, #blueprint_name, #function_name, __scrypto_args);
+            let __scrypto_result: #output = ::scrypto::buffer::scrypto_decode(&__scrypto_rtn).unwrap();
+            __scrypto_result
+        }
+    }
+}
+
+// only signatures that can be called on another component are supported
+fn check_method(method: &TraitItemMethod) -> syn::Result<()> {
+    if let Some(default) = &method.default {
+        return Err(Error::new_spanned(default, "stub methods cannot have a body"));
+    }
+    let sig = &method.sig;
+    if let Some(constness) = &sig.constness {
+        return Err(Error::new_spanned(constness, "const is not supported"));
+    }
+    if let Some(asyncness) = &sig.asyncness {
+        return Err(Error::new_spanned(asyncness, "async is not supported"));
+    }
+    if let Some(unsafety) = &sig.unsafety {
+        return Err(Error::new_spanned(unsafety, "unsafe is not supported"));
+    }
+    if let Some(abi) = &sig.abi {
+        return Err(Error::new_spanned(abi, "extern is not supported"));
+    }
+    if let Some(variadic) = &sig.variadic {
+        return Err(Error::new_spanned(variadic, "variadic arguments are not supported"));
+    }
+    if !sig.generics.params.is_empty() || sig.generics.where_clause.is_some() {
+        return Err(Error::new_spanned(&sig.generics, "generic methods are not supported"));
+    }
+    for arg in sig.inputs.iter() {
+        match arg {
+            FnArg::Receiver(receiver) => {
+                if receiver.reference.is_none() {
+                    return Err(Error::new_spanned(receiver, "methods must take `&self` or `&mut self`"));
+                }
+            },
+            FnArg::Typed(pat_type) => {
+                match &*pat_type.pat {
+                    Pat::Ident(pat_ident) if pat_ident.by_ref.is_none() && pat_ident.subpat.is_none() => {},
+                    pat => return Err(Error::new_spanned(pat, "arguments must be plain identifiers")),
+                }
+                check_type(&pat_type.ty)?;
+            },
+        }
+    }
+    if let ReturnType::Type(_, ty) = &sig.output {
+        check_type(ty)?;
+    }
+    Ok(())
+}
+
+// owned paths (with owned generic arguments), tuples and arrays can be encoded for a call
+fn check_type(ty: &Type) -> syn::Result<()> {
+    match ty {
+        Type::Path(type_path) => {
+            if type_path.qself.is_some() {
+                return Err(Error::new_spanned(ty, "qualified types are not supported"));
+            }
+            if type_path.path.is_ident("Self") {
+                return Err(Error::new_spanned(ty, "`Self` is not supported, use the concrete type (ie. `Component`)"));
+            }
+            for segment in type_path.path.segments.iter() {
+                match &segment.arguments {
+                    PathArguments::None => {},
+                    PathArguments::AngleBracketed(args) => {
+                        for arg in args.args.iter() {
+                            match arg {
+                                GenericArgument::Type(ty) => check_type(ty)?,
+                                arg => return Err(Error::new_spanned(arg, "only type arguments are supported")),
+                            }
+                        }
+                    },
+                    arguments => return Err(Error::new_spanned(arguments, "function types are not supported")),
+                }
+            }
+            Ok(())
+        },
+        Type::Tuple(tuple) => tuple.elems.iter().try_for_each(check_type),
+        Type::Array(array) => check_type(&array.elem),
+        Type::Paren(paren) => check_type(&paren.elem),
+        Type::Group(group) => check_type(&group.elem),
+        Type::Reference(_) => Err(Error::new_spanned(ty, "references cannot be passed to another component, use an owned type (ie. `BucketRef` instead of `&Bucket`)")),
+        _ => Err(Error::new_spanned(ty, "unsupported type for a blueprint stub")),
+    }
+}
+
+/// a companion to `blueprint_stub` which generates the ABI JSON of a blueprint in another package from a trait
+/// and passes it to `import!`, so the stubs don't depend on hand maintained JSON
+/// `#[blueprint_import(package = "01...")]`
+///
+/// the JSON is also available as `<Trait>::ABI`
+#[proc_macro_attribute]
+pub fn blueprint_import(metadata: TokenStream, input: TokenStream) -> TokenStream {
+    match expand_blueprint_import(metadata.into(), input.into()) {
+        Ok(output) => output.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand_blueprint_import(metadata: TokenStream2, input: TokenStream2) -> syn::Result<TokenStream2> {
+    let package = parse_package(metadata)?;
+    let input: ItemTrait = syn::parse2(input)?;
+    let package = package.ok_or_else(|| Error::new_spanned(&input.ident,
+        "blueprint_import needs the address of the package to import: #[blueprint_import(package = \"...\")]"))?;
+    let ident = input.ident.clone();
+    let abi = generate_abi(&package, &input)?;
+    Ok(quote!{
+        import! { #abi }
+        #[allow(unused)]
+        impl #ident {
+            pub const ABI: &'static str = #abi;
+        }
+    })
+}
+
+// the ABI JSON as `import!` expects it, formatted like `resim export-abi`
+fn generate_abi(package: &str, input: &ItemTrait) -> syn::Result<String> {
+    if !input.generics.params.is_empty() || input.generics.where_clause.is_some() {
+        return Err(Error::new_spanned(&input.generics, "blueprint_stub traits cannot be generic"));
+    }
+    let mut functions = Vec::new();
+    let mut methods = Vec::new();
+    for item in input.items.iter() {
+        let method = match item {
+            TraitItem::Method(method) => method,
+            _ => return Err(Error::new_spanned(item, "blueprint_stub traits can only contain methods and functions")),
+        };
+        check_method(method)?;
+        let sig = &method.sig;
+        let mut inputs = Vec::new();
+        for arg in sig.inputs.iter() {
+            if let FnArg::Typed(pat_type) = arg {
+                inputs.push(abi_type(&pat_type.ty)?);
+            }
+        }
+        let output = match &sig.output {
+            ReturnType::Default => Json::object(vec![("type", Json::string("Unit"))]),
+            ReturnType::Type(_, ty) => abi_type(ty)?,
+        };
+        let name = ("name", Json::string(&sig.ident.to_string()));
+        match sig.receiver() {
+            None => functions.push(Json::object(vec![name, ("inputs", Json::Array(inputs)), ("output", output)])),
+            Some(FnArg::Receiver(receiver)) => {
+                let mutability = if receiver.mutability.is_some() { "Mutable" } else { "Immutable" };
+                methods.push(Json::object(vec![name, ("mutability", Json::string(mutability)), ("inputs", Json::Array(inputs)), ("output", output)]));
+            },
+            Some(receiver) => return Err(Error::new_spanned(receiver, "methods must take `&self` or `&mut self`")),
+        }
+    }
+    let abi = Json::object(vec![
+        ("package", Json::string(package)),
+        ("name", Json::string(&input.ident.to_string())),
+        ("functions", Json::Array(functions)),
+        ("methods", Json::Array(methods)),
+    ]);
+    Ok(abi.to_pretty_string())
+}
+
+// the sbor type description of a Rust type
+fn abi_type(ty: &Type) -> syn::Result<Json> {
+    let simple = |name: &str| Json::object(vec![("type", Json::string(name))]);
+    let custom = |name: &str| Json::object(vec![("type", Json::string("Custom")), ("name", Json::string(name)), ("generics", Json::Array(vec![]))]);
+    match ty {
+        Type::Path(type_path) if type_path.qself.is_none() => {
+            let segment = type_path.path.segments.last().unwrap();
+            let generics: Vec<&Type> = match &segment.arguments {
+                PathArguments::AngleBracketed(args) => args.args.iter().filter_map(|arg| match arg {
+                    GenericArgument::Type(ty) => Some(ty),
+                    _ => None,
+                }).collect(),
+                _ => Vec::new(),
+            };
+            let name = segment.ident.to_string();
+            match (name.as_str(), generics.as_slice()) {
+                ("bool", []) => Ok(simple("Bool")),
+                ("i8", []) => Ok(simple("I8")),
+                ("i16", []) => Ok(simple("I16")),
+                ("i32", []) => Ok(simple("I32")),
+                ("i64", []) => Ok(simple("I64")),
+                ("i128", []) => Ok(simple("I128")),
+                ("u8", []) => Ok(simple("U8")),
+                ("u16", []) => Ok(simple("U16")),
+                ("u32", []) => Ok(simple("U32")),
+                ("u64", []) => Ok(simple("U64")),
+                ("u128", []) => Ok(simple("U128")),
+                ("String", []) => Ok(simple("String")),
+                ("Decimal", []) | ("BigDecimal", []) | ("Address", []) | ("H256", []) => Ok(custom(&format!("scrypto::types::{}", name))),
+                ("Component", []) | ("Package", []) => Ok(custom(&format!("scrypto::core::{}", name))),
+                ("Bucket", []) | ("BucketRef", []) | ("Vault", []) | ("ResourceDef", []) => Ok(custom(&format!("scrypto::resource::{}", name))),
+                ("Vec", [element]) => Ok(Json::object(vec![("type", Json::string("Vec")), ("element", abi_type(element)?)])),
+                ("Option", [value]) => Ok(Json::object(vec![("type", Json::string("Option")), ("value", abi_type(value)?)])),
+                _ => Err(Error::new_spanned(ty, "no ABI description for this type")),
+            }
+        },
+        Type::Tuple(tuple) if tuple.elems.is_empty() => Ok(simple("Unit")),
+        Type::Tuple(tuple) => {
+            let elements = tuple.elems.iter().map(abi_type).collect::<syn::Result<Vec<Json>>>()?;
+            Ok(Json::object(vec![("type", Json::string("Tuple")), ("elements", Json::Array(elements))]))
+        },
+        Type::Paren(paren) => abi_type(&paren.elem),
+        Type::Group(group) => abi_type(&group.elem),
+        _ => Err(Error::new_spanned(ty, "no ABI description for this type")),
+    }
+}
+
+// just enough JSON to write an ABI, without pulling in serde for a proc macro
+enum Json {
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn string(s: &str) -> Json {
+        Json::String(s.to_string())
+    }
+
+    fn object(fields: Vec<(&str, Json)>) -> Json {
+        Json::Object(fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    }
+
+    fn to_pretty_string(&self) -> String {
+        let mut out = String::new();
+        self.write(&mut out, 0);
+        out
+    }
+
+    fn write(&self, out: &mut String, indent: usize) {
+        let pad = |out: &mut String, indent: usize| out.push_str(&"  ".repeat(indent));
+        match self {
+            // names are Rust identifiers and addresses are hex, so nothing needs escaping
+            Json::String(s) => { out.push('"'); out.push_str(s); out.push('"'); },
+            Json::Array(items) if items.is_empty() => out.push_str("[]"),
+            Json::Array(items) => {
+                out.push_str("[\n");
+                for (i, item) in items.iter().enumerate() {
+                    pad(out, indent + 1);
+                    item.write(out, indent + 1);
+                    out.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+                }
+                pad(out, indent);
+                out.push(']');
+            },
+            Json::Object(fields) => {
+                out.push_str("{\n");
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    pad(out, indent + 1);
+                    out.push('"');
+                    out.push_str(key);
+                    out.push_str("\": ");
+                    value.write(out, indent + 1);
+                    out.push_str(if i + 1 < fields.len() { ",\n" } else { "\n" });
+                }
+                pad(out, indent);
+                out.push('}');
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn expand(metadata: TokenStream2, input: TokenStream2) -> Result<String, String> {
+        expand_blueprint_stub(metadata, input)
+            .map(|output| output.to_string())
+            .map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn test_methods_with_buckets_and_tuples() {
+        let output = expand(quote!{}, quote!{
+            pub trait Pool {
+                fn deposit(&mut self, tokens: Vec<Bucket>, auth: BucketRef) -> (Bucket, Vec<Bucket>);
+                fn get_price(&self) -> Decimal;
+            }
+        }).unwrap();
+        assert!(output.contains("blueprint !"));
+        assert!(output.contains("pub fn deposit (& mut self , tokens : Vec < Bucket > , auth : BucketRef) -> (Bucket , Vec < Bucket >)"));
+        assert!(output.contains("pub use internal_Pool :: Pool"));
+    }
+
+    #[test]
+    fn test_functions_call_the_package() {
+        let output = expand(quote!{ package = "01abcd" }, quote!{
+            pub trait Pool {
+                fn new(tokens: Bucket, fee: Decimal) -> (Component, Bucket);
+                fn get_price(&self) -> Decimal;
+            }
+        }).unwrap();
+        assert!(output.contains("call_function (__scrypto_package , \"Pool\" , \"new\""));
+        assert!(output.contains("\"01abcd\""));
+        // the function is not part of the hidden blueprint
+        assert_eq!(output.matches("fn new").count(), 1);
+    }
+
+    #[test]
+    fn test_function_arguments_are_not_shadowed() {
+        let output = expand(quote!{ package = "01abcd" }, quote!{
+            pub trait Pool {
+                fn new(package: Address, rtn: Vec<u8>) -> Component;
+            }
+        }).unwrap();
+        let encoded = output.find("scrypto_encode (& package)").unwrap();
+        assert!(encoded < output.find("let __scrypto_package").unwrap());
+        assert!(output.contains("scrypto_encode (& rtn)"));
+        assert!(!output.contains("let package"));
+        assert!(!output.contains("let rtn"));
+    }
+
+    #[test]
+    fn test_functions_need_the_package() {
+        let err = expand(quote!{}, quote!{
+            pub trait Pool {
+                fn new(tokens: Bucket) -> Component;
+            }
+        }).unwrap_err();
+        assert!(err.contains("package"));
+    }
+
+    #[test]
+    fn test_unsupported_signatures() {
+        let cases = vec![
+            (quote!{ fn f(&self, b: &Bucket); }, "references"),
+            (quote!{ fn f(self); }, "&self"),
+            (quote!{ fn f<T>(&self, t: T); }, "generic"),
+            (quote!{ fn f(&self) -> Self; }, "Self"),
+            (quote!{ fn f(&self, (a, b): (u32, u32)); }, "identifiers"),
+            (quote!{ fn f(&self, f: Box<dyn Fn()>); }, "unsupported type"),
+            (quote!{ async fn f(&self); }, "async"),
+            (quote!{ fn f(&self) {} }, "body"),
+            (quote!{ const X: u32; }, "only contain methods"),
+        ];
+        for (item, expected) in cases {
+            let err = expand(quote!{}, quote!{ pub trait Pool { #item } }).unwrap_err();
+            assert!(err.contains(expected), "expected error containing {:?} but got {:?}", expected, err);
+        }
+        let err = expand(quote!{ address = "01" }, quote!{ pub trait Pool {} }).unwrap_err();
+        assert!(err.contains("unknown argument"));
+    }
+
+    #[test]
+    fn test_abi_matches_checked_in_json() {
+        // the Staking blueprint from other/no-loss-lottery, as imported by the lottery
+        let input: ItemTrait = parse_quote! {
+            pub trait Staking {
+                fn new(rewards: Bucket, rate: Decimal, end_epoch: u64) -> (Component, Bucket);
+                fn top_up(&mut self, rewards: Bucket, auth: BucketRef);
+                fn extend(&mut self, end_epoch: u64, auth: BucketRef);
+                fn new_user(&mut self) -> Bucket;
+                fn stake(&mut self, staking: Bucket, auth: BucketRef);
+                fn withdraw(&mut self, auth: BucketRef) -> Bucket;
+                fn unstake(&mut self, amount: Decimal, auth: BucketRef) -> Bucket;
+                fn claim_rewards(&mut self, auth: BucketRef) -> Bucket;
+                fn compound(&mut self, auth: BucketRef);
+                fn get_pools(&self) -> (Decimal, Decimal);
+                fn get_staking_data(&self, id: u128) -> (Decimal, Decimal, Decimal);
+            }
+        };
+        let abi = generate_abi("013fa22e238526e9c82376d2b4679a845364243bf970e5f783d13f", &input).unwrap();
+        assert_eq!(abi, include_str!("../tests/staking_abi.json").trim_end());
+    }
+
+    #[test]
+    fn test_import_needs_the_package() {
+        let err = expand_blueprint_import(quote!{}, quote!{ pub trait Staking {} }).unwrap_err();
+        assert!(err.to_string().contains("package"));
+    }
+
+    #[test]
+    fn test_abi_unknown_type() {
+        let input: ItemTrait = parse_quote! {
+            pub trait Staking {
+                fn f(&self, data: MyStruct);
+            }
+        };
+        let err = generate_abi("01", &input).unwrap_err();
+        assert!(err.to_string().contains("no ABI description"));
+    }
+}
@@ -16,6 +16,12 @@ blueprint! {
         pub fn get_return_amount(&self, continuous_amount: Decimal, _reserve_amount: Decimal, _supply_amount: Decimal) -> Decimal {
             continuous_amount
         }
+        pub fn get_collateral_amount(&self, continuous_amount: Decimal, _reserve_amount: Decimal, _supply_amount: Decimal) -> Decimal {
+            continuous_amount
+        }
+        pub fn get_burn_amount(&self, return_amount: Decimal, _reserve_amount: Decimal, _supply_amount: Decimal) -> Decimal {
+            return_amount
+        }
         pub fn get_initial_supply(&self, collateral_amount: Decimal) -> Decimal {
             collateral_amount
         }
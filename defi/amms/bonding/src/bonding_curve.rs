@@ -8,6 +8,9 @@ use bonding_macros::blueprint_stub; // fancy proc macro that generates a "hidden
 pub trait BondingCurve {
     fn get_mint_amount(&self, collateral_amount: Decimal, reserve_amount: Decimal, supply_amount: Decimal) -> Decimal;
     fn get_return_amount(&self, continuous_amount: Decimal, reserve_amount: Decimal, supply_amount: Decimal) -> Decimal;
+    // inverses of the above: the collateral needed to mint exactly continuous_amount, and the continuous amount to burn to get exactly return_amount
+    fn get_collateral_amount(&self, continuous_amount: Decimal, reserve_amount: Decimal, supply_amount: Decimal) -> Decimal;
+    fn get_burn_amount(&self, return_amount: Decimal, reserve_amount: Decimal, supply_amount: Decimal) -> Decimal;
     fn get_initial_supply(&self, collateral_amount: Decimal) -> Decimal;
     fn get_price(&self, reserve_amount: Decimal, supply_amount: Decimal) -> Decimal;
 }
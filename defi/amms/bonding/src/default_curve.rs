@@ -45,6 +45,20 @@ fn calculate_curve_return(c: Number, r: Number, s: Number, reserve_ratio_n: u32,
     result
 }
 
+fn calculate_curve_collateral(m: Number, r: Number, s: Number, reserve_ratio_n: u32, reserve_ratio_d: u32) -> Number {
+    // inverse of calculate_curve_mint, the collateral needed to mint exactly m
+    // (1 + c / r) ^ rr = (s + m) / s
+    // c = r * ((s + m) / s) ^ (1/rr) - r
+    scaled_power(&r, &(&s + m), &s, reserve_ratio_d, reserve_ratio_n) - r
+}
+
+fn calculate_curve_burn(t: Number, r: Number, s: Number, reserve_ratio_n: u32, reserve_ratio_d: u32) -> Number {
+    // inverse of calculate_curve_return, the amount to burn to return exactly t
+    // (1 - c / s) ^ (1/rr) = (r - t) / r
+    // c = s - s * ((r - t) / r) ^ rr
+    &s - scaled_power(&s, &(&r - t), &r, reserve_ratio_n, reserve_ratio_d)
+}
+
 fn get_initial_supply(collateral_amount: Decimal, reserve_ratio_n: u32, reserve_ratio_d: u32, precision_bits: u16) -> Decimal {
     assert!(!collateral_amount.is_negative());
     assert!(reserve_ratio_d != 0);
@@ -70,6 +84,11 @@ fn get_mint_amount(collateral_amount: Decimal, reserve_amount: Decimal, supply_a
         return Decimal::zero();
     }
 
+    #[cfg(feature = "fixed_point")]
+    if let Some(result) = crate::fixed_point::curve_mint(collateral_amount.0 as u128, reserve_amount.0 as u128, supply_amount.0 as u128, reserve_ratio_n, reserve_ratio_d) {
+        return Decimal(result as i128);
+    } // else out of range for the fast path, so fall back to arbitrary precision
+
     let c = number_from_decimal(collateral_amount, precision_bits);
     let r = number_from_decimal(reserve_amount, precision_bits);
     let s = number_from_decimal(supply_amount, precision_bits);
@@ -91,6 +110,11 @@ fn get_return_amount(continuous_amount: Decimal, reserve_amount: Decimal, supply
         return Decimal::zero();
     }
 
+    #[cfg(feature = "fixed_point")]
+    if let Some(result) = crate::fixed_point::curve_return(continuous_amount.0 as u128, reserve_amount.0 as u128, supply_amount.0 as u128, reserve_ratio_n, reserve_ratio_d) {
+        return Decimal(result as i128);
+    } // else out of range for the fast path, so fall back to arbitrary precision
+
     let c = number_from_decimal(continuous_amount, precision_bits);
     let r = number_from_decimal(reserve_amount, precision_bits);
     let s = number_from_decimal(supply_amount, precision_bits);
@@ -102,6 +126,52 @@ fn get_return_amount(continuous_amount: Decimal, reserve_amount: Decimal, supply
     decimal_from_number(result, precision_bits).unwrap()
 }
 
+fn get_collateral_amount(continuous_amount: Decimal, reserve_amount: Decimal, supply_amount: Decimal, reserve_ratio_n: u32, reserve_ratio_d: u32, precision_bits: u16) -> Decimal {
+    assert!(!continuous_amount.is_negative());
+    assert!(!reserve_amount.is_negative());
+    assert!(!supply_amount.is_negative());
+    assert!(reserve_ratio_n != 0);
+    assert!(reserve_ratio_d != 0);
+
+    if continuous_amount.is_zero() {
+        return Decimal::zero();
+    }
+
+    let m = number_from_decimal(continuous_amount, precision_bits);
+    let r = number_from_decimal(reserve_amount, precision_bits);
+    let s = number_from_decimal(supply_amount, precision_bits);
+
+    let result = calculate_curve_collateral(m, r, s, reserve_ratio_n, reserve_ratio_d);
+
+    assert!(result >= Number::zero(), "Calculated negative collateral amount");
+
+    // round up so the curve is never paid less than it mints
+    decimal_from_number_ceil(result, precision_bits).unwrap()
+}
+
+fn get_burn_amount(return_amount: Decimal, reserve_amount: Decimal, supply_amount: Decimal, reserve_ratio_n: u32, reserve_ratio_d: u32, precision_bits: u16) -> Decimal {
+    assert!(!return_amount.is_negative());
+    assert!(!reserve_amount.is_negative());
+    assert!(!supply_amount.is_negative());
+    assert!(reserve_ratio_d != 0);
+    assert!(return_amount <= reserve_amount, "Cannot return more than the reserve");
+
+    if return_amount.is_zero() {
+        return Decimal::zero();
+    }
+
+    let t = number_from_decimal(return_amount, precision_bits);
+    let r = number_from_decimal(reserve_amount, precision_bits);
+    let s = number_from_decimal(supply_amount, precision_bits);
+
+    let result = calculate_curve_burn(t, r, s, reserve_ratio_n, reserve_ratio_d);
+
+    assert!(result >= Number::zero(), "Calculated negative burn amount");
+
+    // round up so the curve never returns more than is burned
+    decimal_from_number_ceil(result, precision_bits).unwrap()
+}
+
 
 blueprint! {
     struct RatioBondingCurve {
@@ -146,6 +216,18 @@ blueprint! {
             get_return_amount(continuous_amount, reserve_amount, supply_amount, self.reserve_ratio_n, self.reserve_ratio_d, self.precision_bits)
         }
 
+        pub fn get_collateral_amount(&self, continuous_amount: Decimal, reserve_amount: Decimal, supply_amount: Decimal) -> Decimal {
+            debug!("RatioBondingCurve::get_collateral_amount called with:\ncontinuous_amount: {}\nreserve_amount: {}\nsupply_amount: {}", 
+                continuous_amount, reserve_amount, supply_amount);
+            get_collateral_amount(continuous_amount, reserve_amount, supply_amount, self.reserve_ratio_n, self.reserve_ratio_d, self.precision_bits)
+        }
+
+        pub fn get_burn_amount(&self, return_amount: Decimal, reserve_amount: Decimal, supply_amount: Decimal) -> Decimal {
+            debug!("RatioBondingCurve::get_burn_amount called with:\nreturn_amount: {}\nreserve_amount: {}\nsupply_amount: {}", 
+                return_amount, reserve_amount, supply_amount);
+            get_burn_amount(return_amount, reserve_amount, supply_amount, self.reserve_ratio_n, self.reserve_ratio_d, self.precision_bits)
+        }
+
         pub fn get_price(&self, reserve_amount: Decimal, supply_amount: Decimal) -> Decimal {
             let r = number_from_decimal(reserve_amount, self.precision_bits);
             let s = number_from_decimal(supply_amount, self.precision_bits);
@@ -163,6 +245,8 @@ blueprint! {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::lcg::Lcg;
+
     #[test]
     fn test_1_buy() {
         let precision_bits = 384;
@@ -228,4 +312,79 @@ mod test {
         assert_eq!(to_return, expected);
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_4_inverse() {
+        let precision_bits = 384;
+        let reserve_ratio_n = 1;
+        let reserve_ratio_d = 5;
+        // same numbers as test_1_buy, but asking for the exact amount minted
+        let continuous_amount = Decimal(299401793723844635041i128);
+        let collateral = get_collateral_amount(continuous_amount, 60000.into(), 300000.into(), reserve_ratio_n, reserve_ratio_d, precision_bits);
+        let tolerance = Decimal(10i128); // the forward result was rounded, so allow a few units in the last place
+        assert!(collateral >= Decimal::from(300) - tolerance && collateral <= Decimal::from(300) + tolerance);
+        assert!(get_mint_amount(collateral, 60000.into(), 300000.into(), reserve_ratio_n, reserve_ratio_d, precision_bits) >= continuous_amount);
+
+        // same numbers as test_3_sell, but asking for the exact amount returned
+        let supply_amount = Decimal(300000000000000000000000i128 + 299401793723844635041i128 + 693997438220660073726i128);
+        let return_amount = Decimal(101263817029251588263i128);
+        let burn = get_burn_amount(return_amount, 61000.into(), supply_amount, reserve_ratio_n, reserve_ratio_d, precision_bits);
+        assert!(burn >= Decimal::from(100) - tolerance && burn <= Decimal::from(100) + tolerance);
+        assert!(get_return_amount(burn, 61000.into(), supply_amount, reserve_ratio_n, reserve_ratio_d, precision_bits) >= return_amount);
+        assert!(get_return_amount(burn - Decimal(1i128), 61000.into(), supply_amount, reserve_ratio_n, reserve_ratio_d, precision_bits) <= return_amount);
+    }
+
+    // differential tests of the fixed_point fast path against the arbitrary precision implementation
+
+    fn precise_mint(c: Decimal, r: Decimal, s: Decimal, reserve_ratio_n: u32, reserve_ratio_d: u32) -> Decimal {
+        let precision_bits = 384;
+        let result = calculate_curve_mint(number_from_decimal(c, precision_bits), number_from_decimal(r, precision_bits), number_from_decimal(s, precision_bits), reserve_ratio_n, reserve_ratio_d);
+        decimal_from_number_floor(result, precision_bits).unwrap()
+    }
+
+    fn precise_return(c: Decimal, r: Decimal, s: Decimal, reserve_ratio_n: u32, reserve_ratio_d: u32) -> Decimal {
+        let precision_bits = 384;
+        let result = calculate_curve_return(number_from_decimal(c, precision_bits), number_from_decimal(r, precision_bits), number_from_decimal(s, precision_bits), reserve_ratio_n, reserve_ratio_d);
+        decimal_from_number_floor(result, precision_bits).unwrap()
+    }
+
+    // the bound stated in fixed_point: never more than exact, and less by at most 2e-15 of `scale` + 2e-18
+    fn assert_within_bound(fast: Decimal, exact: Decimal, scale: Decimal) {
+        assert!(fast <= exact, "fast path {} is more than the exact {}", fast, exact);
+        let bound = scale.0 / 500_000_000_000_000 + 2;
+        assert!(exact.0 - fast.0 <= bound, "fast path {} is too far from the exact {}", fast, exact);
+    }
+
+    #[test]
+    fn test_5_fixed_point_differential() {
+        let e18 = 1_000_000_000_000_000_000i128;
+        let mut rng = Lcg(7);
+        for i in 0..500 {
+            let reserve_ratio_d = 1 + rng.next(10) as u32;
+            let reserve_ratio_n = 1 + rng.next(reserve_ratio_d as i128) as u32;
+            // mix tiny, normal and huge reserve, supply and trade sizes
+            let r = 1 + rng.next([1_000, e18, 1_000_000_000 * e18][i % 3]);
+            let s = 1 + rng.next([1_000_000_000 * e18, e18, 1_000][(i / 3) % 3]);
+            let c = [rng.next(r), rng.next(10 * r), rng.next(1_000_000 * r), rng.next(1_000)][(i / 9) % 4];
+            let (c, r, s) = (Decimal(c), Decimal(r), Decimal(s));
+
+            let fast = crate::fixed_point::curve_mint(c.0 as u128, r.0 as u128, s.0 as u128, reserve_ratio_n, reserve_ratio_d).unwrap();
+            let exact = precise_mint(c, r, s, reserve_ratio_n, reserve_ratio_d);
+            assert_within_bound(Decimal(fast as i128), exact, s + exact);
+
+            let sold = Decimal(if i % 5 == 0 { s.0 } else { rng.next(s.0) });
+            let fast = crate::fixed_point::curve_return(sold.0 as u128, r.0 as u128, s.0 as u128, reserve_ratio_n, reserve_ratio_d).unwrap();
+            let exact = precise_return(sold, r, s, reserve_ratio_n, reserve_ratio_d);
+            assert_within_bound(Decimal(fast as i128), exact, r);
+        }
+    }
+
+    #[test]
+    fn test_6_fixed_point_out_of_range() {
+        // reserve ratios above 1 are left to the arbitrary precision implementation
+        assert_eq!(crate::fixed_point::curve_mint(1, 1, 1, 2, 1), None);
+        assert_eq!(crate::fixed_point::curve_return(1, 1, 1, 2, 1), None);
+        // as is selling more than the supply
+        assert_eq!(crate::fixed_point::curve_return(2, 1, 1, 1, 5), None);
+    }
+
+}
@@ -0,0 +1,145 @@
+// Fixed point (unsigned Q64.64 in a u128) math for a fast path of the RatioBondingCurve formulas
+//
+// Powers with a fractional exponent are calculated as x^e = 2^(e * log2(x)):
+// * log2 uses the repeated squaring method, one iteration per fractional bit
+// * 2^f for the fractional part of the exponent uses a Taylor series of e^(f * ln2)
+//
+// Everything is done with u128/i128 and checked arithmetic.  Instead of panicking on overflow the functions return None
+// so the caller can fall back to the arbitrary precision implementation in `number`.
+//
+// Error bound (checked by the differential tests in default_curve against the arbitrary precision implementation):
+// for reserve ratios in (0, 1] the amount returned by `curve_mint` or `curve_return` is never more than the exact value,
+// and is less by at most 2e-15 of the new supply (for mint) or the reserve (for return), plus 2e-18 (two units of a Decimal)
+
+const FRACTION_BITS: u32 = 64;
+const ONE: u128 = 1 << FRACTION_BITS;
+const LN_2: u128 = 0xB17217F7D1CF79AB; // ln(2) in Q64.64
+const TAYLOR_TERMS: u128 = 24; // (ln2)^24 / 24! < 2^-64
+
+// (a * b) >> 64 without intermediate overflow
+fn mul(a: u128, b: u128) -> Option<u128> {
+    let mask = ONE - 1;
+    let (a_hi, a_lo) = (a >> FRACTION_BITS, a & mask);
+    let (b_hi, b_lo) = (b >> FRACTION_BITS, b & mask);
+    (a_hi * b_hi).checked_mul(ONE)?
+        .checked_add(a_hi * b_lo)?
+        .checked_add(a_lo * b_hi)?
+        .checked_add((a_lo * b_lo) >> FRACTION_BITS)
+}
+
+// (a << 64) / b without intermediate overflow, by long division for the fractional bits
+fn div(a: u128, b: u128) -> Option<u128> {
+    if b == 0 {
+        return None;
+    }
+    let whole = a / b;
+    if whole >= ONE {
+        return None;
+    }
+    let mut remainder = a % b;
+    let mut fraction = 0u128;
+    for _ in 0..FRACTION_BITS {
+        // remainder < b so this can only overflow when b >= 2^127, compare before shifting instead
+        let carry = remainder >> 127;
+        remainder <<= 1;
+        fraction <<= 1;
+        if carry == 1 || remainder >= b {
+            remainder = remainder.wrapping_sub(b);
+            fraction |= 1;
+        }
+    }
+    Some((whole << FRACTION_BITS) | fraction)
+}
+
+// log2(x) as signed Q64.64, x > 0
+fn log2(x: u128) -> i128 {
+    assert!(x > 0, "log2 of zero");
+    let msb = 127 - x.leading_zeros() as i128;
+    let whole = msb - FRACTION_BITS as i128;
+    // normalize into [1, 2)
+    let mut y = if whole >= 0 { x >> whole } else { x << -whole };
+    let mut result = whole << FRACTION_BITS;
+    let mut bit = (ONE >> 1) as i128;
+    while bit > 0 {
+        // y < 2 so y * y < 4 fits
+        y = mul(y, y).unwrap();
+        if y >= 2 * ONE {
+            y >>= 1;
+            result += bit;
+        }
+        bit >>= 1;
+    }
+    result
+}
+
+// 2^x for signed Q64.64 x
+fn exp2(x: i128) -> Option<u128> {
+    let whole = x >> FRACTION_BITS; // floor
+    let fraction = (x - (whole << FRACTION_BITS)) as u128; // [0, 1)
+
+    // 2^fraction = e^(fraction * ln2), fraction * ln2 < 0.7 so the series converges quickly
+    let y = mul(fraction, LN_2)?;
+    let mut term = ONE;
+    let mut sum = ONE;
+    for k in 1..=TAYLOR_TERMS {
+        term = mul(term, y)? / k;
+        sum += term;
+    }
+
+    if whole >= 0 {
+        if whole >= (127 - FRACTION_BITS as i128) {
+            return None;
+        }
+        sum.checked_mul(1 << whole)
+    } else if whole <= -128 {
+        Some(0)
+    } else {
+        Some(sum >> -whole)
+    }
+}
+
+// (base_n / base_d) ^ (exp_n / exp_d) as Q64.64
+fn pow(base_n: u128, base_d: u128, exp_n: u32, exp_d: u32) -> Option<u128> {
+    if exp_d == 0 {
+        return None;
+    }
+    if base_n == 0 {
+        return Some(if exp_n == 0 { ONE } else { 0 });
+    }
+    let base = div(base_n, base_d)?;
+    if base == 0 {
+        return None; // too small to represent, leave it to the precise implementation
+    }
+    let exponent = log2(base).checked_mul(exp_n as i128)? / exp_d as i128;
+    exp2(exponent)
+}
+
+/// s * ((1 + c / r) ^ rr - 1) with all amounts in raw Decimal units (1e-18), rr = reserve_ratio_n / reserve_ratio_d
+pub fn curve_mint(c: u128, r: u128, s: u128, reserve_ratio_n: u32, reserve_ratio_d: u32) -> Option<u128> {
+    if reserve_ratio_n > reserve_ratio_d {
+        return None; // outside the range the error bound holds for
+    }
+    let growth = pow(r.checked_add(c)?, r, reserve_ratio_n, reserve_ratio_d)?;
+    let result = mul(s, growth.checked_sub(ONE)?)?;
+    // the error is in both directions, so remove the bound to never mint more than the exact amount
+    Some(result.saturating_sub(error_margin(mul(s, growth)?)))
+}
+
+/// r * (1 - (1 - c / s) ^ (1 / rr)) with all amounts in raw Decimal units (1e-18), rr = reserve_ratio_n / reserve_ratio_d
+pub fn curve_return(c: u128, r: u128, s: u128, reserve_ratio_n: u32, reserve_ratio_d: u32) -> Option<u128> {
+    if c > s || reserve_ratio_n > reserve_ratio_d || reserve_ratio_n == 0 {
+        return None; // outside the range the error bound holds for
+    }
+    if c == s {
+        return Some(r); // selling the whole supply returns the whole reserve
+    }
+    let remaining = pow(s - c, s, reserve_ratio_d, reserve_ratio_n)?;
+    let result = mul(r, ONE.checked_sub(remaining)?)?;
+    // the error is in both directions, so remove the bound to never return more than the exact amount
+    Some(result.saturating_sub(error_margin(r)))
+}
+
+// over the worst case error of the pow approximation, in raw Decimal units, for a result scaled by `amount`
+fn error_margin(amount: u128) -> u128 {
+    amount / 1_000_000_000_000_000 + 1 // 1e-15 of the amount
+}
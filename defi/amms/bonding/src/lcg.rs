@@ -0,0 +1,26 @@
+// Small deterministic generator so the property and differential tests of the curves are repeatable
+// without extra dependencies
+
+use scrypto::prelude::{Decimal};
+
+pub struct Lcg(pub u64);
+
+impl Lcg {
+    fn step(&mut self) -> u64 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        self.0
+    }
+
+    // a whole part below max_whole with any 18 digits fraction
+    pub fn next_decimal(&mut self, max_whole: u64) -> Decimal {
+        let whole = (self.step() >> 33) % max_whole;
+        let frac = (self.step() >> 4) % 1_000_000_000_000_000_000;
+        Decimal(whole as i128 * 1_000_000_000_000_000_000i128 + frac as i128)
+    }
+
+    // a number below max, which can be bigger than u64
+    pub fn next(&mut self, max: i128) -> i128 {
+        let hi = (self.step() >> 11) as i128;
+        (hi * 1_000_003 + (self.step() >> 40) as i128) % max
+    }
+}
@@ -4,13 +4,26 @@
 //! bonding curve defined by a plugable second component.  It will instantiate a component from the `RatioBondingCurve`
 //! blueprint if no other curve component is provided.
 //! 
+//! A buy fee and a sell fee (fractions of the RESERVE amount) can be configured with `new_with_fees`.  They are paid into a
+//! beneficiary vault that the returned owner badge can empty with `withdraw_fees`, and are included in the buy and sell quotes.
+//! 
+//! Besides the exact input `buy` and `sell` there are exact output `buy_exact` and `sell_exact` (with a maximum to spend), which use the
+//! inverse functions every curve implements (`get_collateral_amount` and `get_burn_amount`).
+//! 
 //! There is also an extrememly simple `BasicBondingCurve` blueprint (more like a flat line) which could be used to implement
 //! a simple "wrapped" or "virtual" token from another.  Or it's a a good template for making your own curve component.
 //! 
+//! For other curve shapes there is `PolynomialBondingCurve` (price = slope * supply ^ exponent) and `SigmoidBondingCurve` (an S-curve
+//! whose price approaches but never exceeds a cap).  Both compute their closed form integrals with the arbitrary precision number implementation and round in favor of the curve.
+//! 
 //! Some useful utilities are included too.  An internal crate is used to provide the `blueprint_stub` macro which
-//! automates creating stub functions from a trait so calling another component is ergonomic.  Also included and
+//! automates creating stub functions from a trait so calling another component is ergonomic (its companion `blueprint_import`
+//! generates the ABI JSON for `import!` from the same kind of trait).  Also included and
 //! used for the `RatioBondingCurve` is a reusable arbitrary precision number implmentation that converts to/from Decimal
 //! It is precise but not yet optimized.  Bounded (BigInt) or unbounded (BigRational) precision is configurable with a feature flag.
+//! For large trades the `fixed_point` feature switches `RatioBondingCurve` to a much cheaper fixed point log/exp implementation.
+//! It never returns more than the exact amount and is within 2e-15 (relative to the supply or reserve) of it, which is checked by
+//! differential tests against the arbitrary precision implementation.
 //! 
 //! # Bonuses:
 //! 
@@ -46,8 +59,14 @@
 //! 
 mod basic_curve; // a simple flat "curve" 1:1 implementation as a reference
 mod default_curve; // a complete non-production bonding curve implementation parametrizable by "curve weight" aka "reserve ratio".  Max precision within Decimal. (ie. precise, but unoptimized)
-mod number; // arbitrary precision math used in default_curve
+mod polynomial_curve; // price = slope * supply ^ exponent
+mod sigmoid_curve; // S-curve price capped at a maximum
+mod number; // arbitrary precision math used in the curves
+#[cfg(any(feature = "fixed_point", test))]
+mod fixed_point; // fast fixed point math for default_curve, with bounded error
 mod bonding_curve; // the trait for cross-blueprint calls for plugable curve math
+#[cfg(test)]
+mod lcg; // deterministic random numbers for the curve tests
 
 use scrypto::prelude::*;
 use scrypto_statictypes::prelude::*; // Use https://github.com/devmannic/scrypto_statictypes
@@ -62,6 +81,10 @@ blueprint! {
         continuous: VaultOf<CONTINUOUS>,
         continuous_auth: VaultOf<AUTH>,
         bonding_curve: Component, // plugable, does the math
+        buy_fee: Decimal, // fraction of the RESERVE paid in that goes to fees instead of the curve
+        sell_fee: Decimal, // fraction of the RESERVE returned by the curve that goes to fees instead of the seller
+        fees: VaultOf<RESERVE>, // beneficiary vault for the collected fees
+        owner_badge: ResourceDef, // may withdraw the collected fees
     }
 
     impl BondingAMM {
@@ -75,10 +98,30 @@ blueprint! {
             BondingAMM::new(initial_reserve, continuous_name, continuous_symbol, Some(bonding_curve.into()))
         }
 
+        // Convenient Constructor with specified curve and fees - also makes it easy to call from integration tests
+        pub fn new_with_curve_and_fees(initial_reserve: BucketOf<RESERVE>, continuous_name: String, continuous_symbol: String, bonding_curve: Address, buy_fee: Decimal, sell_fee: Decimal) -> (Component, BucketOf<CONTINUOUS>, Bucket) {
+            BondingAMM::new_with_fees(initial_reserve, continuous_name, continuous_symbol, Some(bonding_curve.into()), buy_fee, sell_fee)
+        }
+
         // Main constructor easily called from other blueprints
         pub fn new(initial_reserve: BucketOf<RESERVE>, continuous_name: String, continuous_symbol: String, bonding_curve: Option<Component>) -> (Component, BucketOf<CONTINUOUS>) {
+            // without fees there is never anything to withdraw, so no owner badge is created
+            let (component, continuous, _) = BondingAMM::build(initial_reserve, continuous_name, continuous_symbol, bonding_curve, Decimal::zero(), Decimal::zero(), false);
+            (component, continuous)
+        }
+
+        // Main constructor with fees easily called from other blueprints
+        // buy_fee and sell_fee are fractions (ie. 0.01 is 1%) of the RESERVE amount, paid into a beneficiary vault which the returned owner badge can withdraw
+        pub fn new_with_fees(initial_reserve: BucketOf<RESERVE>, continuous_name: String, continuous_symbol: String, bonding_curve: Option<Component>, buy_fee: Decimal, sell_fee: Decimal) -> (Component, BucketOf<CONTINUOUS>, Bucket) {
+            let (component, continuous, owner_badge) = BondingAMM::build(initial_reserve, continuous_name, continuous_symbol, bonding_curve, buy_fee, sell_fee, true);
+            (component, continuous, owner_badge.unwrap())
+        }
+
+        fn build(initial_reserve: BucketOf<RESERVE>, continuous_name: String, continuous_symbol: String, bonding_curve: Option<Component>, buy_fee: Decimal, sell_fee: Decimal, with_owner_badge: bool) -> (Component, BucketOf<CONTINUOUS>, Option<Bucket>) {
             // initial_reserve cannot be empty
             assert!(!initial_reserve.is_empty());
+            assert!(!buy_fee.is_negative() && buy_fee < 1.into(), "buy_fee must be in the range [0, 1)");
+            assert!(!sell_fee.is_negative() && sell_fee < 1.into(), "sell_fee must be in the range [0, 1)");
 
             // get the curve Component for the math, or create a default_curve with ratio 1:5 and 384 bit precision (which is plenty for the 1e-18 precision of a Decimal)
             // the 384 bit precision is not needed and ignored when built with feature=use_rationals
@@ -106,15 +149,32 @@ blueprint! {
                 continuous_def.mint(initial_supply, minter).into()
             );
 
+            // setup the owner badge, or reuse the (never released) continuous_auth definition so no one can withdraw the always empty fees vault
+            let owner_badge = if with_owner_badge {
+                Some(ResourceBuilder::new_fungible(DIVISIBILITY_NONE)
+                    .metadata("name", "BondingAMM Owner Badge")
+                    .initial_supply_fungible(1))
+            } else {
+                None
+            };
+            let owner_badge_def = match &owner_badge {
+                Some(badge) => badge.resource_def(),
+                None => continuous_auth.resource_def(),
+            };
+
             // store and instantiate
             let component = Self {
+                fees: VaultOf::with_bucket(initial_reserve.take(0)),
                 reserve: VaultOf::with_bucket(initial_reserve),
                 continuous: VaultOf::with_bucket(continuous.take(0)),
                 continuous_auth: VaultOf::with_bucket(continuous_auth),
                 bonding_curve,
+                buy_fee,
+                sell_fee,
+                owner_badge: owner_badge_def,
             }.instantiate();
 
-            (component, continuous)
+            (component, continuous, owner_badge)
         }
 
         pub fn buy(&mut self, collateral: BucketOf<RESERVE>, minimum_to_receive: Decimal) -> (BucketOf<CONTINUOUS>, BucketOf<RESERVE>) {
@@ -143,7 +203,9 @@ blueprint! {
                         collateral);
             }
 
-            // keep the collateral
+            // keep the fee and the collateral
+            let fee = self.get_buy_fee_amount(collateral.amount());
+            self.fees.put(collateral.take(fee));
             self.reserve.put(collateral);
 
             // mint for return
@@ -172,7 +234,7 @@ blueprint! {
                         continuous);
             }
             // calculate the amount to return
-            let return_amount = self.get_sell_quote_amount(continuous.amount());
+            let (return_amount, fee) = self.get_sell_amounts(continuous.amount());
 
             debug!("will return RESERVE amount: {}", return_amount);
 
@@ -189,11 +251,118 @@ blueprint! {
                 continuous.burn_with_auth(burner)
             );
 
+            // keep the fee
+            self.fees.put(self.reserve.take(fee));
+
             // return from reserve vault, and empty CONTINUOUS bucket
             (self.reserve.take(return_amount),
              self.continuous.take(0))
         }
 
+        pub fn buy_exact(&mut self, collateral: BucketOf<RESERVE>, continuous_wanted: Decimal, max_reserve: Decimal) -> (BucketOf<CONTINUOUS>, BucketOf<RESERVE>) {
+            // mint exactly continuous_wanted CONTINUOUS and return it with the unused RESERVE
+            assert!(!continuous_wanted.is_negative());
+            assert!(!max_reserve.is_negative());
+
+            debug!("buy_exact CONTINUOUS amount: {} with RESERVE amount: {}", continuous_wanted, collateral.amount());
+
+            if continuous_wanted.is_zero() { // fast path, don't panic to allow better composability within a single transaction
+                return (self.continuous.take(0),
+                        collateral);
+            }
+
+            // calculate the amount needed (including the fee)
+            let (reserve_amount, fee) = self.get_buy_exact_amounts(continuous_wanted);
+
+            debug!("will take RESERVE amount: {}", reserve_amount + fee);
+
+            if reserve_amount + fee > max_reserve || reserve_amount + fee > collateral.amount() { // too expensive
+                // return empty bucket of CONTINUOUS and the sent in RESERVE instead of failing the tx incase other instructions want to do something else with the RESERVE
+                debug!("returning CONTINOUS amount: {}", 0);
+                debug!("returning RESERVE amount: {}", collateral.amount());
+                return (self.continuous.take(0),
+                        collateral);
+            }
+
+            // keep the fee and the collateral
+            self.fees.put(collateral.take(fee));
+            self.reserve.put(collateral.take(reserve_amount));
+
+            // mint for return
+            let continuous: BucketOf<CONTINUOUS> = self.continuous_auth.authorize(|minter|
+                self.continuous.resource_def().mint(continuous_wanted, minter).into()
+            );
+
+            debug!("returning CONTINOUS amount: {}", continuous.amount());
+            debug!("returning RESERVE amount: {}", collateral.amount());
+
+            // return the minted CONTINUOUS and the unused RESERVE
+            (continuous,
+             collateral)
+        }
+
+        pub fn sell_exact(&mut self, continuous: BucketOf<CONTINUOUS>, reserve_wanted: Decimal, max_continuous: Decimal) -> (BucketOf<RESERVE>, BucketOf<CONTINUOUS>) {
+            // burn just enough CONTINUOUS to return exactly reserve_wanted RESERVE, and return the rest of the CONTINUOUS
+            assert!(!reserve_wanted.is_negative());
+            assert!(!max_continuous.is_negative());
+
+            debug!("sell_exact for RESERVE amount: {} with CONTINUOUS amount: {}", reserve_wanted, continuous.amount());
+
+            if reserve_wanted.is_zero() { // fast path, don't panic to allow better composability within a single transaction
+                return (self.reserve.take(0),
+                        continuous);
+            }
+
+            // the curve has to return the wanted amount plus the fee, and it can't return more than it holds
+            let fee = self.get_sell_exact_fee_amount(reserve_wanted);
+            if reserve_wanted + fee > self.reserve.amount() {
+                debug!("returning RESERVE amount: {}", 0);
+                debug!("returning CONTINOUS amount: {}", continuous.amount());
+                return (self.reserve.take(0),
+                        continuous);
+            }
+
+            // calculate the amount to burn
+            let burn_amount = self.get_sell_exact_quote_amount(reserve_wanted);
+
+            debug!("will burn CONTINUOUS amount: {}", burn_amount);
+
+            if burn_amount > max_continuous || burn_amount > continuous.amount() { // too expensive
+                // return empty bucket of RESERVE and the sent in CONTINUOUS instead of failing the tx incase other instructions want to do something else with the CONTINUOUS
+                debug!("returning RESERVE amount: {}", 0);
+                debug!("returning CONTINOUS amount: {}", continuous.amount());
+                return (self.reserve.take(0),
+                        continuous);
+            }
+
+            // burn the CONTINUOUS
+            let to_burn = continuous.take(burn_amount);
+            self.continuous_auth.authorize(|burner|
+                to_burn.burn_with_auth(burner)
+            );
+
+            // keep the fee
+            self.fees.put(self.reserve.take(fee));
+
+            // return from reserve vault, and the unused CONTINUOUS
+            (self.reserve.take(reserve_wanted),
+             continuous)
+        }
+
+        // withdraw all collected fees
+        #[auth(owner_badge)]
+        pub fn withdraw_fees(&mut self) -> BucketOf<RESERVE> {
+            self.fees.take_all()
+        }
+
+        pub fn get_fees(&self) -> Decimal {
+            self.fees.amount()
+        }
+
+        pub fn get_fee_rates(&self) -> (Decimal, Decimal) {
+            (self.buy_fee, self.sell_fee)
+        }
+
         pub fn get_price(&self) -> Decimal {
             // use the generated stubs for calling methods on the Component (kind of like a virtual call aka dynamic dispatch, but it happens via the kernel)
             let curve: crate::bonding_curve::BondingCurve = self.bonding_curve.clone().into();
@@ -204,10 +373,8 @@ blueprint! {
             // This variant returns a BucketRef for proof.  Only possible with sell because
             // for buying, we can't return "proof" because the amount isn't minted yet
 
-            // use the generated stubs for calling methods on the Component (kind of like a virtual call aka dynamic dispatch, but it happens via the kernel)
-            let curve: crate::bonding_curve::BondingCurve = self.bonding_curve.clone().into();
-            // calculate the amount that would be returned
-            let return_amount = curve.get_return_amount(continuous_amount, self.reserve.amount(), self.continuous.resource_def().total_supply());
+            // calculate the amount that would be returned (after the fee)
+            let (return_amount, _fee) = self.get_sell_amounts(continuous_amount);
             // return a placeholder for the amount as proof we have it in reserve
             let bucket = self.reserve.take(return_amount);
             bucket.present() // return proof of amount, but don't give it away
@@ -216,17 +383,61 @@ blueprint! {
         pub fn get_buy_quote_amount(&self, collateral_amount: Decimal) -> Decimal {
             // use the generated stubs for calling methods on the Component (kind of like a virtual call aka dynamic dispatch, but it happens via the kernel)
             let curve: crate::bonding_curve::BondingCurve = self.bonding_curve.clone().into();
-            // calculate amount that would be minted
+            // calculate amount that would be minted, only what is left after the fee goes to the curve
+            let collateral_amount = collateral_amount - self.get_buy_fee_amount(collateral_amount);
             curve.get_mint_amount(collateral_amount, self.reserve.amount(), self.continuous.resource_def().total_supply())
         }
 
         pub fn get_sell_quote_amount(&self, continuous_amount: Decimal) -> Decimal {
             // interestingly, trying to reuse self.get_sell_quote to get a BucketRef and then look at the amount leads to failure with dangling buckets no matter what I've tried
-            // instead just calculate the amount directly
+            // instead just calculate the amount directly (after the fee)
+            let (return_amount, _fee) = self.get_sell_amounts(continuous_amount);
+            return_amount
+        }
+
+        pub fn get_buy_exact_quote_amount(&self, continuous_amount: Decimal) -> Decimal {
+            // the RESERVE needed (including the fee) to mint exactly continuous_amount
+            let (reserve_amount, fee) = self.get_buy_exact_amounts(continuous_amount);
+            reserve_amount + fee
+        }
+
+        pub fn get_sell_exact_quote_amount(&self, reserve_amount: Decimal) -> Decimal {
+            // the CONTINUOUS to burn to get exactly reserve_amount (after the fee)
+            // use the generated stubs for calling methods on the Component (kind of like a virtual call aka dynamic dispatch, but it happens via the kernel)
+            let curve: crate::bonding_curve::BondingCurve = self.bonding_curve.clone().into();
+            let curve_amount = reserve_amount + self.get_sell_exact_fee_amount(reserve_amount);
+            curve.get_burn_amount(curve_amount, self.reserve.amount(), self.continuous.resource_def().total_supply())
+        }
+
+        // fee kept from the RESERVE paid in for a buy
+        fn get_buy_fee_amount(&self, collateral_amount: Decimal) -> Decimal {
+            collateral_amount * self.buy_fee
+        }
+
+        // RESERVE returned to the seller and the fee kept from what the curve returns
+        fn get_sell_amounts(&self, continuous_amount: Decimal) -> (Decimal, Decimal) {
             // use the generated stubs for calling methods on the Component (kind of like a virtual call aka dynamic dispatch, but it happens via the kernel)
             let curve: crate::bonding_curve::BondingCurve = self.bonding_curve.clone().into();
             // calculate the amount that would be returned
-            curve.get_return_amount(continuous_amount, self.reserve.amount(), self.continuous.resource_def().total_supply())
+            let curve_amount = curve.get_return_amount(continuous_amount, self.reserve.amount(), self.continuous.resource_def().total_supply());
+            let fee = curve_amount * self.sell_fee;
+            (curve_amount - fee, fee)
+        }
+
+        // RESERVE the curve needs to mint exactly continuous_amount, and the fee on top of it
+        fn get_buy_exact_amounts(&self, continuous_amount: Decimal) -> (Decimal, Decimal) {
+            // use the generated stubs for calling methods on the Component (kind of like a virtual call aka dynamic dispatch, but it happens via the kernel)
+            let curve: crate::bonding_curve::BondingCurve = self.bonding_curve.clone().into();
+            let reserve_amount = curve.get_collateral_amount(continuous_amount, self.reserve.amount(), self.continuous.resource_def().total_supply());
+            // the fee is a fraction of the total paid, so total = reserve_amount / (1 - buy_fee)
+            let fee = reserve_amount / (Decimal::one() - self.buy_fee) - reserve_amount;
+            (reserve_amount, fee)
+        }
+
+        // fee the curve has to return on top of return_amount so the seller gets exactly return_amount
+        fn get_sell_exact_fee_amount(&self, return_amount: Decimal) -> Decimal {
+            // the fee is a fraction of what the curve returns, so total = return_amount / (1 - sell_fee)
+            return_amount / (Decimal::one() - self.sell_fee) - return_amount
         }
 
     }
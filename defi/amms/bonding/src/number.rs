@@ -113,6 +113,50 @@ pub fn scaled_power(scale: &Number, base_n: &Number, base_d: &Number, exp_n: u32
     scale * n / d
 }
 
+/// Number representing 1 at the given precision
+pub fn number_one(precision_bits: u16) -> Number {
+    number_from_decimal(Decimal::one(), precision_bits)
+}
+
+/// a * b keeping the precision scale (fixed point safe for both Number implementations)
+pub fn mul(a: &Number, b: &Number, precision_bits: u16) -> Number {
+    a * b / number_one(precision_bits)
+}
+
+/// a / b keeping the precision scale (fixed point safe for both Number implementations)
+pub fn div(a: &Number, b: &Number, precision_bits: u16) -> Number {
+    assert!(*b != Number::zero(), "div divide by zero"); // nicer error message
+    a * number_one(precision_bits) / b
+}
+
+/// x ^ (exp_n / exp_d) keeping the precision scale
+pub fn pow_frac(x: &Number, exp_n: u32, exp_d: u32, precision_bits: u16) -> Number {
+    let one = number_one(precision_bits);
+    scaled_power(&one, x, &one, exp_n, exp_d)
+}
+
+/// Like `decimal_from_number` but always rounds down (towards negative infinity) instead of to the nearest Decimal
+/// Use this when rounding must never favor the caller (ie. amounts paid out by a curve)
+pub fn decimal_from_number_floor(b: Number, precision_bits: u16) -> Option<Decimal> {
+    let d = decimal_from_number(b.clone(), precision_bits)?;
+    if number_from_decimal(d, precision_bits) > b {
+        Some(d - Decimal(1i128))
+    } else {
+        Some(d)
+    }
+}
+
+/// Like `decimal_from_number` but always rounds up (towards positive infinity) instead of to the nearest Decimal
+/// Use this when rounding must never favor the caller (ie. amounts paid in to a curve)
+pub fn decimal_from_number_ceil(b: Number, precision_bits: u16) -> Option<Decimal> {
+    let d = decimal_from_number(b.clone(), precision_bits)?;
+    if number_from_decimal(d, precision_bits) < b {
+        Some(d + Decimal(1i128))
+    } else {
+        Some(d)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use scrypto::prelude::*;
@@ -148,4 +192,31 @@ mod test {
         assert_eq!(b.to_i128().unwrap(), i);
     }
 
+    #[test]
+    fn test_fixed_point_helpers() {
+        for precision_bits in [0u16, 64, 384] {
+            let two = number_from_decimal(2.into(), precision_bits);
+            let three = number_from_decimal(3.into(), precision_bits);
+            let eight = number_from_decimal(8.into(), precision_bits);
+            assert_eq!(decimal_from_number(mul(&two, &three, precision_bits), precision_bits), Some(6.into()));
+            assert_eq!(decimal_from_number(div(&eight, &two, precision_bits), precision_bits), Some(4.into()));
+            assert_eq!(decimal_from_number(pow_frac(&two, 3, 1, precision_bits), precision_bits), Some(8.into()));
+            assert_eq!(decimal_from_number(pow_frac(&eight, 1, 3, precision_bits), precision_bits), Some(2.into()));
+        }
+    }
+
+    #[test]
+    fn test_decimal_from_number_directed_rounding() {
+        let precision_bits = 384;
+        let one = number_from_decimal(1.into(), precision_bits);
+        let three = number_from_decimal(3.into(), precision_bits);
+        let third = div(&one, &three, precision_bits);
+        let two_thirds = &third + &third;
+        assert_eq!(decimal_from_number(two_thirds.clone(), precision_bits), Decimal::from_str("0.666666666666666667").ok());
+        assert_eq!(decimal_from_number_floor(two_thirds, precision_bits), Decimal::from_str("0.666666666666666666").ok());
+        assert_eq!(decimal_from_number_floor(one.clone(), precision_bits), Some(1.into()));
+        assert_eq!(decimal_from_number_ceil(third, precision_bits), Decimal::from_str("0.333333333333333334").ok());
+        assert_eq!(decimal_from_number_ceil(one, precision_bits), Some(1.into()));
+    }
+
 }
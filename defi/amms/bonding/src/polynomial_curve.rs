@@ -0,0 +1,257 @@
+use scrypto::prelude::*;
+
+use num_traits::{Zero};
+
+use crate::number::*;
+
+// Price = slope * Supply ^ exponent
+//
+// Integrating the price from 0 gives the reserve backing a given supply (in closed form):
+// Reserve = slope * Supply ^ (exponent + 1) / (exponent + 1)
+//
+// The reserve is always derived from the supply so the curve doesn't drift if the actual reserve differs (ie. from fees)
+
+fn calculate_price(s: &Number, slope: &Number, exponent: u32, precision_bits: u16) -> Number {
+    mul(slope, &pow_frac(s, exponent, 1, precision_bits), precision_bits)
+}
+
+fn calculate_reserve(s: &Number, slope: &Number, exponent: u32, precision_bits: u16) -> Number {
+    let n1 = number_from_decimal(Decimal::try_from(exponent + 1).unwrap(), precision_bits);
+    div(&mul(slope, &pow_frac(s, exponent + 1, 1, precision_bits), precision_bits), &n1, precision_bits)
+}
+
+fn calculate_supply(r: &Number, slope: &Number, exponent: u32, precision_bits: u16) -> Number {
+    // inverse of calculate_reserve: Supply = (Reserve * (exponent + 1) / slope) ^ (1 / (exponent + 1))
+    let n1 = number_from_decimal(Decimal::try_from(exponent + 1).unwrap(), precision_bits);
+    pow_frac(&div(&mul(r, &n1, precision_bits), slope, precision_bits), 1, exponent + 1, precision_bits)
+}
+
+fn calculate_curve_mint(c: Number, s: Number, slope: &Number, exponent: u32, precision_bits: u16) -> Number {
+    let r = calculate_reserve(&s, slope, exponent, precision_bits);
+    calculate_supply(&(r + c), slope, exponent, precision_bits) - s
+}
+
+fn calculate_curve_return(c: Number, s: Number, slope: &Number, exponent: u32, precision_bits: u16) -> Number {
+    calculate_reserve(&s, slope, exponent, precision_bits) - calculate_reserve(&(&s - c), slope, exponent, precision_bits)
+}
+
+fn calculate_curve_collateral(m: Number, s: Number, slope: &Number, exponent: u32, precision_bits: u16) -> Number {
+    calculate_reserve(&(&s + m), slope, exponent, precision_bits) - calculate_reserve(&s, slope, exponent, precision_bits)
+}
+
+fn calculate_curve_burn(t: Number, s: Number, slope: &Number, exponent: u32, precision_bits: u16) -> Number {
+    let r = calculate_reserve(&s, slope, exponent, precision_bits);
+    &s - calculate_supply(&(r - t), slope, exponent, precision_bits)
+}
+
+fn get_initial_supply(collateral_amount: Decimal, slope: Decimal, exponent: u32, precision_bits: u16) -> Decimal {
+    assert!(!collateral_amount.is_negative());
+
+    if collateral_amount.is_zero() {
+        return Decimal::zero();
+    }
+
+    let c = number_from_decimal(collateral_amount, precision_bits);
+    let slope = number_from_decimal(slope, precision_bits);
+
+    let result = calculate_supply(&c, &slope, exponent, precision_bits);
+
+    decimal_from_number_floor(result, precision_bits).unwrap()
+}
+
+fn get_mint_amount(collateral_amount: Decimal, supply_amount: Decimal, slope: Decimal, exponent: u32, precision_bits: u16) -> Decimal {
+    assert!(!collateral_amount.is_negative());
+    assert!(!supply_amount.is_negative());
+
+    if collateral_amount.is_zero() {
+        return Decimal::zero();
+    }
+
+    let c = number_from_decimal(collateral_amount, precision_bits);
+    let s = number_from_decimal(supply_amount, precision_bits);
+    let slope = number_from_decimal(slope, precision_bits);
+
+    let result = calculate_curve_mint(c, s, &slope, exponent, precision_bits);
+
+    assert!(result >= Number::zero(), "Calculated negative mint amount");
+
+    // round down so buying can never be used to take more than was paid
+    decimal_from_number_floor(result, precision_bits).unwrap()
+}
+
+fn get_return_amount(continuous_amount: Decimal, supply_amount: Decimal, slope: Decimal, exponent: u32, precision_bits: u16) -> Decimal {
+    assert!(!continuous_amount.is_negative());
+    assert!(!supply_amount.is_negative());
+    assert!(continuous_amount <= supply_amount, "Cannot return more than the supply");
+
+    if continuous_amount.is_zero() {
+        return Decimal::zero();
+    }
+
+    let c = number_from_decimal(continuous_amount, precision_bits);
+    let s = number_from_decimal(supply_amount, precision_bits);
+    let slope = number_from_decimal(slope, precision_bits);
+
+    let result = calculate_curve_return(c, s, &slope, exponent, precision_bits);
+
+    assert!(result >= Number::zero(), "Calculated negative return amount");
+
+    // round down so selling can never return more than the curve holds
+    decimal_from_number_floor(result, precision_bits).unwrap()
+}
+
+fn get_collateral_amount(continuous_amount: Decimal, supply_amount: Decimal, slope: Decimal, exponent: u32, precision_bits: u16) -> Decimal {
+    assert!(!continuous_amount.is_negative());
+    assert!(!supply_amount.is_negative());
+
+    if continuous_amount.is_zero() {
+        return Decimal::zero();
+    }
+
+    let m = number_from_decimal(continuous_amount, precision_bits);
+    let s = number_from_decimal(supply_amount, precision_bits);
+    let slope = number_from_decimal(slope, precision_bits);
+
+    let result = calculate_curve_collateral(m, s, &slope, exponent, precision_bits);
+
+    assert!(result >= Number::zero(), "Calculated negative collateral amount");
+
+    // round up so the curve is never paid less than it mints
+    decimal_from_number_ceil(result, precision_bits).unwrap()
+}
+
+fn get_burn_amount(return_amount: Decimal, supply_amount: Decimal, slope: Decimal, exponent: u32, precision_bits: u16) -> Decimal {
+    assert!(!return_amount.is_negative());
+    assert!(!supply_amount.is_negative());
+
+    if return_amount.is_zero() {
+        return Decimal::zero();
+    }
+
+    let t = number_from_decimal(return_amount, precision_bits);
+    let s = number_from_decimal(supply_amount, precision_bits);
+    let slope = number_from_decimal(slope, precision_bits);
+
+    assert!(t <= calculate_reserve(&s, &slope, exponent, precision_bits), "Cannot return more than the reserve");
+
+    let result = calculate_curve_burn(t, s, &slope, exponent, precision_bits);
+
+    assert!(result >= Number::zero(), "Calculated negative burn amount");
+
+    // round up so the curve never returns more than is burned
+    decimal_from_number_ceil(result, precision_bits).unwrap()
+}
+
+fn get_price(supply_amount: Decimal, slope: Decimal, exponent: u32, precision_bits: u16) -> Decimal {
+    let s = number_from_decimal(supply_amount, precision_bits);
+    let slope = number_from_decimal(slope, precision_bits);
+    let p = calculate_price(&s, &slope, exponent, precision_bits);
+    decimal_from_number(p, precision_bits).unwrap()
+}
+
+
+blueprint! {
+    struct PolynomialBondingCurve {
+        slope: Decimal,
+        exponent: u32,
+        precision_bits: u16,
+    }
+
+    impl PolynomialBondingCurve {
+        pub fn new(slope: Decimal, exponent: u32, precision_bits: u16) -> Component {
+            debug!("PolynomialBondingCurve::new called with {} * s ^ {} @ {} bits", slope, exponent, precision_bits);
+            assert!(slope.is_positive(), "slope must be positive");
+            Self {
+                slope,
+                exponent,
+                precision_bits,
+            }.instantiate()
+        }
+
+    // can't do trait impl's within the blueprint.  Would be ncie to get those compile time checks...
+    // impl BondingCurve for blueprint::PolynomialBondingCurve {
+
+        pub fn get_initial_supply(&self, collateral_amount: Decimal) -> Decimal {
+            get_initial_supply(collateral_amount, self.slope, self.exponent, self.precision_bits)
+        }
+
+        pub fn get_mint_amount(&self, collateral_amount: Decimal, _reserve_amount: Decimal, supply_amount: Decimal) -> Decimal {
+            debug!("PolynomialBondingCurve::get_mint_amount called with:\ncollateral_amount: {}\nsupply_amount: {}",
+                collateral_amount, supply_amount);
+            get_mint_amount(collateral_amount, supply_amount, self.slope, self.exponent, self.precision_bits)
+        }
+
+        pub fn get_return_amount(&self, continuous_amount: Decimal, _reserve_amount: Decimal, supply_amount: Decimal) -> Decimal {
+            debug!("PolynomialBondingCurve::get_return_amount called with:\ncontinuous_amount: {}\nsupply_amount: {}",
+                continuous_amount, supply_amount);
+            get_return_amount(continuous_amount, supply_amount, self.slope, self.exponent, self.precision_bits)
+        }
+
+        pub fn get_collateral_amount(&self, continuous_amount: Decimal, _reserve_amount: Decimal, supply_amount: Decimal) -> Decimal {
+            get_collateral_amount(continuous_amount, supply_amount, self.slope, self.exponent, self.precision_bits)
+        }
+
+        pub fn get_burn_amount(&self, return_amount: Decimal, _reserve_amount: Decimal, supply_amount: Decimal) -> Decimal {
+            get_burn_amount(return_amount, supply_amount, self.slope, self.exponent, self.precision_bits)
+        }
+
+        pub fn get_price(&self, _reserve_amount: Decimal, supply_amount: Decimal) -> Decimal {
+            get_price(supply_amount, self.slope, self.exponent, self.precision_bits)
+        }
+
+    // }
+    }
+
+}
+
+// -------- Testing
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lcg::Lcg;
+
+    #[test]
+    fn test_1_linear() {
+        let precision_bits = 384;
+        // price = 2 * s, reserve = s^2
+        let slope: Decimal = 2.into();
+        assert_eq!(get_initial_supply(100.into(), slope, 1, precision_bits), 10.into());
+        assert_eq!(get_price(10.into(), slope, 1, precision_bits), 20.into());
+        // reserve 100 -> 400 means supply 10 -> 20
+        assert_eq!(get_mint_amount(300.into(), 10.into(), slope, 1, precision_bits), 10.into());
+        assert_eq!(get_return_amount(10.into(), 20.into(), slope, 1, precision_bits), 300.into());
+        assert_eq!(get_collateral_amount(10.into(), 10.into(), slope, 1, precision_bits), 300.into());
+        assert_eq!(get_burn_amount(300.into(), 20.into(), slope, 1, precision_bits), 10.into());
+    }
+
+    #[test]
+    fn test_2_quadratic() {
+        let precision_bits = 384;
+        // price = 3 * s^2, reserve = s^3
+        let slope: Decimal = 3.into();
+        assert_eq!(get_initial_supply(8.into(), slope, 2, precision_bits), 2.into());
+        assert_eq!(get_price(2.into(), slope, 2, precision_bits), 12.into());
+        // reserve 8 -> 27 means supply 2 -> 3
+        assert_eq!(get_mint_amount(19.into(), 2.into(), slope, 2, precision_bits), 1.into());
+        assert_eq!(get_return_amount(1.into(), 3.into(), slope, 2, precision_bits), 19.into());
+    }
+
+    #[test]
+    fn test_3_buy_sell_never_profits() {
+        let precision_bits = 384;
+        let mut rng = Lcg(11);
+        for exponent in 0..4 {
+            for _ in 0..50 {
+                let slope = rng.next_decimal(10) + Decimal::from_str("0.001").unwrap();
+                let supply = rng.next_decimal(1_000);
+                let collateral = rng.next_decimal(10_000);
+                let minted = get_mint_amount(collateral, supply, slope, exponent, precision_bits);
+                let returned = get_return_amount(minted, supply + minted, slope, exponent, precision_bits);
+                assert!(returned <= collateral, "buy {} then sell {} returned {} with slope {} exponent {} supply {}",
+                    collateral, minted, returned, slope, exponent, supply);
+            }
+        }
+    }
+
+}
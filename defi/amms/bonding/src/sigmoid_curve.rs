@@ -0,0 +1,296 @@
+use scrypto::prelude::*;
+
+use num_traits::{Zero};
+
+use crate::number::*;
+
+// Price = cap / 2 * (1 + (Supply - midpoint) / sqrt((Supply - midpoint) ^ 2 + width ^ 2))
+//
+// An algebraic sigmoid: starts near 0, is cap / 2 at the midpoint supply and approaches (but never exceeds) cap.
+// `width` controls how steep the middle of the curve is; a larger width is a gentler slope.
+// Unlike the logistic function this one has a closed form integral (from 0), which is the reserve backing a given supply:
+// Reserve = cap / 2 * (Supply + sqrt((Supply - midpoint) ^ 2 + width ^ 2) - sqrt(midpoint ^ 2 + width ^ 2))
+//
+// The reserve is always derived from the supply so the curve doesn't drift if the actual reserve differs (ie. from fees)
+
+struct Params {
+    half_cap: Number,
+    midpoint: Number,
+    width: Number,
+    precision_bits: u16,
+}
+
+impl Params {
+    fn new(cap: Decimal, midpoint: Decimal, width: Decimal, precision_bits: u16) -> Self {
+        let two = number_from_decimal(2.into(), precision_bits);
+        Params {
+            half_cap: div(&number_from_decimal(cap, precision_bits), &two, precision_bits),
+            midpoint: number_from_decimal(midpoint, precision_bits),
+            width: number_from_decimal(width, precision_bits),
+            precision_bits,
+        }
+    }
+
+    // sqrt((x - midpoint) ^ 2 + width ^ 2)
+    fn distance(&self, x: &Number) -> Number {
+        let d = x - &self.midpoint;
+        let squared = mul(&d, &d, self.precision_bits) + mul(&self.width, &self.width, self.precision_bits);
+        pow_frac(&squared, 1, 2, self.precision_bits)
+    }
+
+    fn price(&self, s: &Number) -> Number {
+        let ratio = div(&(s - &self.midpoint), &self.distance(s), self.precision_bits);
+        &self.half_cap + mul(&self.half_cap, &ratio, self.precision_bits)
+    }
+
+    fn reserve(&self, s: &Number) -> Number {
+        let zero = Number::zero();
+        mul(&self.half_cap, &(s + self.distance(s) - self.distance(&zero)), self.precision_bits)
+    }
+
+    fn supply(&self, r: &Number) -> Number {
+        // inverse of reserve:
+        // let T = Reserve / (cap / 2) + sqrt(midpoint ^ 2 + width ^ 2) = Supply + sqrt((Supply - midpoint) ^ 2 + width ^ 2)
+        // squaring (T - Supply) and solving gives: Supply = (T ^ 2 - midpoint ^ 2 - width ^ 2) / (2 * (T - midpoint))
+        let zero = Number::zero();
+        let t = div(r, &self.half_cap, self.precision_bits) + self.distance(&zero);
+        let n = mul(&t, &t, self.precision_bits)
+            - mul(&self.midpoint, &self.midpoint, self.precision_bits)
+            - mul(&self.width, &self.width, self.precision_bits);
+        let d = &t - &self.midpoint;
+        div(&n, &(&d + &d), self.precision_bits)
+    }
+}
+
+fn calculate_curve_mint(c: Number, s: Number, params: &Params) -> Number {
+    let r = params.reserve(&s);
+    params.supply(&(r + c)) - s
+}
+
+fn calculate_curve_return(c: Number, s: Number, params: &Params) -> Number {
+    params.reserve(&s) - params.reserve(&(&s - c))
+}
+
+fn calculate_curve_collateral(m: Number, s: Number, params: &Params) -> Number {
+    params.reserve(&(&s + m)) - params.reserve(&s)
+}
+
+fn calculate_curve_burn(t: Number, s: Number, params: &Params) -> Number {
+    let r = params.reserve(&s);
+    &s - params.supply(&(r - t))
+}
+
+fn get_initial_supply(collateral_amount: Decimal, params: &Params) -> Decimal {
+    assert!(!collateral_amount.is_negative());
+
+    if collateral_amount.is_zero() {
+        return Decimal::zero();
+    }
+
+    let c = number_from_decimal(collateral_amount, params.precision_bits);
+
+    let result = params.supply(&c);
+
+    decimal_from_number_floor(result, params.precision_bits).unwrap()
+}
+
+fn get_mint_amount(collateral_amount: Decimal, supply_amount: Decimal, params: &Params) -> Decimal {
+    assert!(!collateral_amount.is_negative());
+    assert!(!supply_amount.is_negative());
+
+    if collateral_amount.is_zero() {
+        return Decimal::zero();
+    }
+
+    let c = number_from_decimal(collateral_amount, params.precision_bits);
+    let s = number_from_decimal(supply_amount, params.precision_bits);
+
+    let result = calculate_curve_mint(c, s, params);
+
+    assert!(result >= Number::zero(), "Calculated negative mint amount");
+
+    // round down so buying can never be used to take more than was paid
+    decimal_from_number_floor(result, params.precision_bits).unwrap()
+}
+
+fn get_return_amount(continuous_amount: Decimal, supply_amount: Decimal, params: &Params) -> Decimal {
+    assert!(!continuous_amount.is_negative());
+    assert!(!supply_amount.is_negative());
+    assert!(continuous_amount <= supply_amount, "Cannot return more than the supply");
+
+    if continuous_amount.is_zero() {
+        return Decimal::zero();
+    }
+
+    let c = number_from_decimal(continuous_amount, params.precision_bits);
+    let s = number_from_decimal(supply_amount, params.precision_bits);
+
+    let result = calculate_curve_return(c, s, params);
+
+    assert!(result >= Number::zero(), "Calculated negative return amount");
+
+    // round down so selling can never return more than the curve holds
+    decimal_from_number_floor(result, params.precision_bits).unwrap()
+}
+
+fn get_collateral_amount(continuous_amount: Decimal, supply_amount: Decimal, params: &Params) -> Decimal {
+    assert!(!continuous_amount.is_negative());
+    assert!(!supply_amount.is_negative());
+
+    if continuous_amount.is_zero() {
+        return Decimal::zero();
+    }
+
+    let m = number_from_decimal(continuous_amount, params.precision_bits);
+    let s = number_from_decimal(supply_amount, params.precision_bits);
+
+    let result = calculate_curve_collateral(m, s, params);
+
+    assert!(result >= Number::zero(), "Calculated negative collateral amount");
+
+    // round up so the curve is never paid less than it mints
+    decimal_from_number_ceil(result, params.precision_bits).unwrap()
+}
+
+fn get_burn_amount(return_amount: Decimal, supply_amount: Decimal, params: &Params) -> Decimal {
+    assert!(!return_amount.is_negative());
+    assert!(!supply_amount.is_negative());
+
+    if return_amount.is_zero() {
+        return Decimal::zero();
+    }
+
+    let t = number_from_decimal(return_amount, params.precision_bits);
+    let s = number_from_decimal(supply_amount, params.precision_bits);
+
+    assert!(t <= params.reserve(&s), "Cannot return more than the reserve");
+
+    let result = calculate_curve_burn(t, s, params);
+
+    assert!(result >= Number::zero(), "Calculated negative burn amount");
+
+    // round up so the curve never returns more than is burned
+    decimal_from_number_ceil(result, params.precision_bits).unwrap()
+}
+
+fn get_price(supply_amount: Decimal, params: &Params) -> Decimal {
+    let s = number_from_decimal(supply_amount, params.precision_bits);
+    decimal_from_number(params.price(&s), params.precision_bits).unwrap()
+}
+
+
+blueprint! {
+    struct SigmoidBondingCurve {
+        cap: Decimal,
+        midpoint: Decimal,
+        width: Decimal,
+        precision_bits: u16,
+    }
+
+    impl SigmoidBondingCurve {
+        pub fn new(cap: Decimal, midpoint: Decimal, width: Decimal, precision_bits: u16) -> Component {
+            debug!("SigmoidBondingCurve::new called with cap {} midpoint {} width {} @ {} bits", cap, midpoint, width, precision_bits);
+            assert!(cap.is_positive(), "cap must be positive");
+            assert!(!midpoint.is_negative(), "midpoint cannot be negative");
+            assert!(width.is_positive(), "width must be positive");
+            Self {
+                cap,
+                midpoint,
+                width,
+                precision_bits,
+            }.instantiate()
+        }
+
+        fn params(&self) -> Params {
+            Params::new(self.cap, self.midpoint, self.width, self.precision_bits)
+        }
+
+    // can't do trait impl's within the blueprint.  Would be ncie to get those compile time checks...
+    // impl BondingCurve for blueprint::SigmoidBondingCurve {
+
+        pub fn get_initial_supply(&self, collateral_amount: Decimal) -> Decimal {
+            get_initial_supply(collateral_amount, &self.params())
+        }
+
+        pub fn get_mint_amount(&self, collateral_amount: Decimal, _reserve_amount: Decimal, supply_amount: Decimal) -> Decimal {
+            debug!("SigmoidBondingCurve::get_mint_amount called with:\ncollateral_amount: {}\nsupply_amount: {}",
+                collateral_amount, supply_amount);
+            get_mint_amount(collateral_amount, supply_amount, &self.params())
+        }
+
+        pub fn get_return_amount(&self, continuous_amount: Decimal, _reserve_amount: Decimal, supply_amount: Decimal) -> Decimal {
+            debug!("SigmoidBondingCurve::get_return_amount called with:\ncontinuous_amount: {}\nsupply_amount: {}",
+                continuous_amount, supply_amount);
+            get_return_amount(continuous_amount, supply_amount, &self.params())
+        }
+
+        pub fn get_collateral_amount(&self, continuous_amount: Decimal, _reserve_amount: Decimal, supply_amount: Decimal) -> Decimal {
+            get_collateral_amount(continuous_amount, supply_amount, &self.params())
+        }
+
+        pub fn get_burn_amount(&self, return_amount: Decimal, _reserve_amount: Decimal, supply_amount: Decimal) -> Decimal {
+            get_burn_amount(return_amount, supply_amount, &self.params())
+        }
+
+        pub fn get_price(&self, _reserve_amount: Decimal, supply_amount: Decimal) -> Decimal {
+            get_price(supply_amount, &self.params())
+        }
+
+    // }
+    }
+
+}
+
+// -------- Testing
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lcg::Lcg;
+
+    #[test]
+    fn test_1_price() {
+        let precision_bits = 384;
+        // cap 10, midpoint 4, width 3 -> sqrt(4^2 + 3^2) = 5
+        let params = Params::new(10.into(), 4.into(), 3.into(), precision_bits);
+        assert_eq!(get_price(4.into(), &params), 5.into());
+        // (0 - 4) / 5 -> 5 * (1 - 0.8)
+        assert_eq!(get_price(0.into(), &params), 1.into());
+        // (8 - 4) / 5 -> 5 * (1 + 0.8)
+        assert_eq!(get_price(8.into(), &params), 9.into());
+        // never above the cap
+        assert!(get_price(1_000_000_000.into(), &params) < 10.into());
+    }
+
+    #[test]
+    fn test_2_buy_sell() {
+        let precision_bits = 384;
+        let params = Params::new(10.into(), 4.into(), 3.into(), precision_bits);
+        // reserve at supply 8 = 5 * (8 + 5 - 5) = 40
+        assert_eq!(get_initial_supply(40.into(), &params), 8.into());
+        // reserve at supply 4 = 5 * (4 + 3 - 5) = 10
+        assert_eq!(get_mint_amount(30.into(), 4.into(), &params), 4.into());
+        assert_eq!(get_return_amount(4.into(), 8.into(), &params), 30.into());
+        assert_eq!(get_collateral_amount(4.into(), 4.into(), &params), 30.into());
+        assert_eq!(get_burn_amount(30.into(), 8.into(), &params), 4.into());
+    }
+
+    #[test]
+    fn test_3_buy_sell_never_profits() {
+        let precision_bits = 384;
+        let mut rng = Lcg(17);
+        for _ in 0..200 {
+            let cap = rng.next_decimal(100) + Decimal::from_str("0.001").unwrap();
+            let midpoint = rng.next_decimal(10_000);
+            let width = rng.next_decimal(1_000) + Decimal::from_str("0.001").unwrap();
+            let params = Params::new(cap, midpoint, width, precision_bits);
+            let supply = rng.next_decimal(20_000);
+            let collateral = rng.next_decimal(10_000);
+            let minted = get_mint_amount(collateral, supply, &params);
+            let returned = get_return_amount(minted, supply + minted, &params);
+            assert!(returned <= collateral, "buy {} then sell {} returned {} with cap {} midpoint {} width {} supply {}",
+                collateral, minted, returned, cap, midpoint, width, supply);
+        }
+    }
+
+}
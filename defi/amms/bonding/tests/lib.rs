@@ -297,3 +297,261 @@ fn test_3_quotes() {
     let reserve_in_account = env.get_amount_for_rd(user.account, reserve_def.address());  
     assert_eq!(reserve_in_account, expected_reserve_in_account);
 }
+
+#[test]
+fn test_4_polynomial_curve() {
+    let mut ledger = InMemoryLedger::with_bootstrap();
+    let mut env = TestEnv::new(&mut ledger);
+    let (owner, investor, reserve_def) = setup_fixture(&mut env);
+
+    const BLUEPRINT: &str = "BondingAMM";
+    const CURVE_BLUEPRINT: &str = "PolynomialBondingCurve";
+
+    // switch to the owner to instantiate a new BondingAMM
+    env.acting_as("owner");
+    let user = owner;
+
+    // create linear curve: price = 2 * supply, so reserve = supply ^ 2
+    let mut receipt = env.call_function(CURVE_BLUEPRINT, "new", vec![
+        "2".to_owned(),
+        "1".to_owned(),
+        "384".to_owned(),
+        ]);
+    println!("new curve: receipt: {:?}", receipt);
+    assert!(receipt.success);
+    let curve: Component = return_of_call_function(&mut receipt, CURVE_BLUEPRINT);
+
+    // instantiate amm with the curve
+    let mut receipt = env.call_function(BLUEPRINT, "new_with_curve", vec![
+        format!("100,{}", reserve_def.address()),
+        "Continuous".to_owned(),
+        "XC".to_owned(),
+        format!("{}", curve.address()),
+        ]);
+    println!("new_with_curve: receipt: {:?}", receipt);
+    assert!(receipt.success);
+
+    let continuous_addr = receipt.resource_def(1).unwrap(); // this should be the CONTINUOUS
+
+    // initial supply for 100 reserve is sqrt(100) = 10
+    let continuous_in_account = env.get_amount_for_rd(user.account, continuous_addr);
+    assert_eq!(continuous_in_account, 10.into());
+
+    let ret: (Component, Bucket) = return_of_call_function(&mut receipt, BLUEPRINT);
+    let amm = ret.0;
+
+    // now switch to an investor
+    env.acting_as("investor");
+    let user = investor;
+
+    // buy with 300 reserve moves the reserve 100 -> 400 and the supply 10 -> 20
+    let receipt = env.call_method(&amm.address(), "buy", vec![
+        format!("300,{}", reserve_def.address()),
+        format!("0"),
+    ]);
+    println!("buy: receipt: {:?}", receipt);
+    assert!(receipt.success);
+    let continuous_in_account = env.get_amount_for_rd(user.account, continuous_addr);
+    assert_eq!(continuous_in_account, 10.into());
+
+    // and selling it all back returns the same 300
+    let receipt = env.call_method(&amm.address(), "sell", vec![
+        format!("10,{}", continuous_addr),
+        format!("0"),
+    ]);
+    println!("sell: receipt: {:?}", receipt);
+    assert!(receipt.success);
+    let reserve_in_account = env.get_amount_for_rd(user.account, reserve_def.address());
+    assert_eq!(reserve_in_account, 1_000_000.into());
+    let continuous_in_account = env.get_amount_for_rd(user.account, continuous_addr);
+    assert_eq!(continuous_in_account, 0.into());
+}
+
+#[test]
+fn test_5_fees() {
+    let mut ledger = InMemoryLedger::with_bootstrap();
+    let mut env = TestEnv::new(&mut ledger);
+    let (owner, investor, reserve_def) = setup_fixture(&mut env);
+
+    const BLUEPRINT: &str = "BondingAMM";
+    const CURVE_BLUEPRINT: &str = "BasicBondingCurve";
+
+    // switch to the owner to instantiate a new BondingAMM
+    env.acting_as("owner");
+    let owner_user = owner;
+
+    // create basic curve so the fees are easy to see
+    let mut receipt = env.call_function(CURVE_BLUEPRINT, "new", vec![]);
+    let basic_curve: Component = return_of_call_function(&mut receipt, CURVE_BLUEPRINT);
+
+    // instantiate amm with 1% buy fee and 2% sell fee
+    let mut receipt = env.call_function(BLUEPRINT, "new_with_curve_and_fees", vec![
+        format!("60000,{}", reserve_def.address()),
+        "Continuous".to_owned(),
+        "XC".to_owned(),
+        format!("{}", basic_curve.address()),
+        "0.01".to_owned(),
+        "0.02".to_owned(),
+        ]);
+    println!("new_with_curve_and_fees: receipt: {:?}", receipt);
+    assert!(receipt.success);
+
+    // this is brittle checking the defs based on order...
+    let continuous_addr = receipt.resource_def(1).unwrap(); // this should be the CONTINUOUS
+    let owner_badge_addr = receipt.resource_def(2).unwrap(); // this should be the owner badge
+
+    let ret: (Component, Bucket, Bucket) = return_of_call_function(&mut receipt, BLUEPRINT);
+    let amm = ret.0;
+
+    // now switch to an investor
+    env.acting_as("investor");
+    let user = investor;
+
+    // the quote includes the fee
+    let mut receipt = env.call_method(&amm.address(), "get_buy_quote_amount", vec![
+        format!("300"),
+    ]);
+    assert!(receipt.success);
+    let quote: Decimal = return_of_call_method(&mut receipt, "get_buy_quote_amount");
+    assert_eq!(quote, 297.into());
+
+    // and matches what the buy executes
+    let receipt = env.call_method(&amm.address(), "buy", vec![
+        format!("300,{}", reserve_def.address()),
+        format!("0"),
+    ]);
+    println!("buy: receipt: {:?}", receipt);
+    assert!(receipt.success);
+    let continuous_in_account = env.get_amount_for_rd(user.account, continuous_addr);
+    assert_eq!(continuous_in_account, quote);
+
+    // same for selling
+    let mut receipt = env.call_method(&amm.address(), "get_sell_quote_amount", vec![
+        format!("100"),
+    ]);
+    assert!(receipt.success);
+    let quote: Decimal = return_of_call_method(&mut receipt, "get_sell_quote_amount");
+    assert_eq!(quote, 98.into());
+
+    let receipt = env.call_method(&amm.address(), "sell", vec![
+        format!("100,{}", continuous_addr),
+        format!("0"),
+    ]);
+    println!("sell: receipt: {:?}", receipt);
+    assert!(receipt.success);
+    let reserve_in_account = env.get_amount_for_rd(user.account, reserve_def.address());
+    assert_eq!(reserve_in_account, Decimal::from(1_000_000) - 300 + quote);
+
+    // 3 from the buy and 2 from the sell
+    let mut receipt = env.call_method(&amm.address(), "get_fees", vec![]);
+    assert!(receipt.success);
+    let fees: Decimal = return_of_call_method(&mut receipt, "get_fees");
+    assert_eq!(fees, 5.into());
+
+    // only the owner can withdraw them
+    let receipt = env.call_method(&amm.address(), "withdraw_fees", vec![
+        format!("1,{}", continuous_addr),
+    ]);
+    assert!(!receipt.success);
+
+    env.acting_as("owner");
+    let receipt = env.call_method(&amm.address(), "withdraw_fees", vec![
+        format!("1,{}", owner_badge_addr),
+    ]);
+    println!("withdraw_fees: receipt: {:?}", receipt);
+    assert!(receipt.success);
+    let reserve_in_account = env.get_amount_for_rd(owner_user.account, reserve_def.address());
+    assert_eq!(reserve_in_account, Decimal::from(1_000_000) - 60000 + 5);
+}
+
+#[test]
+fn test_6_exact_buy_and_sell() {
+    let mut ledger = InMemoryLedger::with_bootstrap();
+    let mut env = TestEnv::new(&mut ledger);
+    let (_owner, investor, reserve_def) = setup_fixture(&mut env);
+
+    const BLUEPRINT: &str = "BondingAMM";
+    const CURVE_BLUEPRINT: &str = "BasicBondingCurve";
+
+    // switch to the owner to instantiate a new BondingAMM
+    env.acting_as("owner");
+
+    // create basic curve so the amounts are easy to see
+    let mut receipt = env.call_function(CURVE_BLUEPRINT, "new", vec![]);
+    let basic_curve: Component = return_of_call_function(&mut receipt, CURVE_BLUEPRINT);
+
+    // instantiate amm with 1% buy fee and 2% sell fee
+    let mut receipt = env.call_function(BLUEPRINT, "new_with_curve_and_fees", vec![
+        format!("60000,{}", reserve_def.address()),
+        "Continuous".to_owned(),
+        "XC".to_owned(),
+        format!("{}", basic_curve.address()),
+        "0.01".to_owned(),
+        "0.02".to_owned(),
+        ]);
+    println!("new_with_curve_and_fees: receipt: {:?}", receipt);
+    assert!(receipt.success);
+
+    let continuous_addr = receipt.resource_def(1).unwrap(); // this should be the CONTINUOUS
+
+    let ret: (Component, Bucket, Bucket) = return_of_call_function(&mut receipt, BLUEPRINT);
+    let amm = ret.0;
+
+    // now switch to an investor
+    env.acting_as("investor");
+    let user = investor;
+
+    // 99 CONTINUOUS costs 99 RESERVE plus a 1 fee
+    let mut receipt = env.call_method(&amm.address(), "get_buy_exact_quote_amount", vec![
+        format!("99"),
+    ]);
+    assert!(receipt.success);
+    let quote: Decimal = return_of_call_method(&mut receipt, "get_buy_exact_quote_amount");
+    assert_eq!(quote, 100.into());
+
+    // too expensive for the max, so nothing happens
+    let receipt = env.call_method(&amm.address(), "buy_exact", vec![
+        format!("500,{}", reserve_def.address()),
+        format!("99"),
+        format!("99.9"),
+    ]);
+    println!("buy_exact: receipt: {:?}", receipt);
+    assert!(receipt.success);
+    assert_eq!(env.get_amount_for_rd(user.account, continuous_addr), 0.into());
+    assert_eq!(env.get_amount_for_rd(user.account, reserve_def.address()), 1_000_000.into());
+
+    // pay with more than needed, and get the change back
+    let receipt = env.call_method(&amm.address(), "buy_exact", vec![
+        format!("500,{}", reserve_def.address()),
+        format!("99"),
+        format!("100"),
+    ]);
+    println!("buy_exact: receipt: {:?}", receipt);
+    assert!(receipt.success);
+    assert_eq!(env.get_amount_for_rd(user.account, continuous_addr), 99.into());
+    assert_eq!(env.get_amount_for_rd(user.account, reserve_def.address()), (1_000_000 - 100).into());
+
+    // 49 RESERVE needs 50 from the curve, so 50 CONTINUOUS are burned
+    let mut receipt = env.call_method(&amm.address(), "get_sell_exact_quote_amount", vec![
+        format!("49"),
+    ]);
+    assert!(receipt.success);
+    let quote: Decimal = return_of_call_method(&mut receipt, "get_sell_exact_quote_amount");
+    assert_eq!(quote, 50.into());
+
+    let receipt = env.call_method(&amm.address(), "sell_exact", vec![
+        format!("99,{}", continuous_addr),
+        format!("49"),
+        format!("50"),
+    ]);
+    println!("sell_exact: receipt: {:?}", receipt);
+    assert!(receipt.success);
+    assert_eq!(env.get_amount_for_rd(user.account, continuous_addr), 49.into());
+    assert_eq!(env.get_amount_for_rd(user.account, reserve_def.address()), (1_000_000 - 100 + 49).into());
+
+    // 1 from the buy and 1 from the sell
+    let mut receipt = env.call_method(&amm.address(), "get_fees", vec![]);
+    assert!(receipt.success);
+    let fees: Decimal = return_of_call_method(&mut receipt, "get_fees");
+    assert_eq!(fees, 2.into());
+}
@@ -1,61 +1,97 @@
 use scrypto::prelude::*;
 
+mod market;
+mod pool_depositor;
+mod short_repayer;
+
 /*
  *  This is an example of a component that is requesting a
- *  flash loan from another component to make more XRD and repay the loan
+ *  flash loan from another component to make a profit with an arbitrage
+ *  between two markets and repay the loan
  */
 
 blueprint! {
     struct Caller {
         // Address of the flashloan component
         loaner_component: Address,
-        // Used to store payment for the opportunity
-        hidden_vault: Vault,
-        // Used to represent tokens that can be accessed if the user gets the loan
-        opportunity: Vault
+        // Markets trading the same token for XRD at different prices
+        market_a: Address,
+        market_b: Address
     }
 
     impl Caller {
-        pub fn new(loaner_component_address: Address, opportunity: Bucket) -> Component {
-            assert!(opportunity.amount() > 1000.into(), "The amount of the opportunity must be bigger than 1000");
-            assert!(opportunity.resource_address() == RADIX_TOKEN, "The tokens for the opportunity must be XRD");
+        pub fn new(loaner_component_address: Address, market_a: Address, market_b: Address) -> Component {
+            let token_a: Address = Component::from(market_a).call::<Address>("get_token", vec![]);
+            let token_b: Address = Component::from(market_b).call::<Address>("get_token", vec![]);
+            assert!(token_a == token_b, "The markets must trade the same token");
 
             Self {
                 loaner_component: loaner_component_address,
-                hidden_vault: Vault::new(RADIX_TOKEN),
-                opportunity: Vault::with_bucket(opportunity)
+                market_a: market_a,
+                market_b: market_b
             }
             .instantiate()
         }
 
-        pub fn call(&self, this_component_address: Address) -> Bucket {
-            // Get a loan of 1000 XRD
-            let amount:Decimal = 1000.into();
+        pub fn call(&self, this_component_address: Address, xrd_amount: Decimal, token_amount: Decimal, min_profit: Decimal) -> Vec<Bucket> {
+            // Get a loan of XRD and/or of the token traded by the markets
+            let token: Address = Component::from(self.market_a).call::<Address>("get_token", vec![]);
+            let mut loans: Vec<(Address, Decimal)> = Vec::new();
+            if xrd_amount > Decimal::zero() {
+                loans.push((RADIX_TOKEN, xrd_amount));
+            }
+            if token_amount > Decimal::zero() {
+                loans.push((token, token_amount));
+            }
+
+            // The loaner calls back the arbitrage method with the loans and min_profit
             let args = vec![
-                scrypto_encode(&amount),
-                scrypto_encode(&this_component_address)
+                scrypto_encode(&loans),
+                scrypto_encode(&this_component_address),
+                scrypto_encode(&"arbitrage".to_owned()),
+                scrypto_encode(&vec![scrypto_encode(&min_profit)])
             ];
 
             // Call request loan and return the change back to the user
-            Component::from(self.loaner_component).call::<Bucket>("request_loan", args).into()
+            Component::from(self.loaner_component).call::<Vec<Bucket>>("request_loan", args)
         }
 
-        // Used to simulate an opportunity to make some XRD by paying 1000 XRD
-        fn get_opportunity(&mut self, payment: Bucket) -> Bucket {
-            assert!(payment.amount() >= 1000.into(), "You need to pay 1000 XRD !");
-            self.hidden_vault.put(payment);
+        // Trade every loan between the markets, each must make at least min_profit
+        pub fn arbitrage(&self, loans: Vec<Bucket>, min_profit: Decimal) -> Vec<Bucket> {
+            let mut results: Vec<Bucket> = Vec::new();
+            for loan in loans {
+                let amount = loan.amount();
+                let result = self.trade(loan);
+                info!("Made {} from a loan of {}", result.amount() - amount, amount);
+                assert!(result.amount() >= amount + min_profit, "Arbitrage didn't make {}", min_profit);
+                results.push(result);
+            }
 
-            self.opportunity.take_all()
+            // Repay the loans (you get the change back)
+            results
         }
 
-        pub fn execute(&mut self, loan: Bucket) -> Bucket {
+        // Single loan callback, for loaners calling "execute" like the PoolFlashLoan
+        pub fn execute(&self, loan: Bucket) -> Bucket {
             info!("Received {} from loaner !", loan.amount());
-            info!("Found opportunity to make {} XRD !", self.opportunity.amount());
+            self.trade(loan)
+        }
 
-            let opportunity_result = self.get_opportunity(loan);
+        // XRD buys tokens on the cheaper market to sell them on the other one, tokens go the other way round
+        fn trade(&self, loan: Bucket) -> Bucket {
+            let market_a = Component::from(self.market_a);
+            let market_b = Component::from(self.market_b);
+            let price_a: Decimal = market_a.call::<Decimal>("get_price", vec![]);
+            let price_b: Decimal = market_b.call::<Decimal>("get_price", vec![]);
+            let (cheap, expensive) = if price_a <= price_b { (market_a, market_b) } else { (market_b, market_a) };
 
-            // Repay the loan (you get the change back)
-            opportunity_result
+            if loan.resource_address() == RADIX_TOKEN {
+                let tokens: Bucket = cheap.call::<Bucket>("buy", vec![scrypto_encode(&loan)]);
+                expensive.call::<Bucket>("sell", vec![scrypto_encode(&tokens)])
+            } else {
+                let xrd: Bucket = expensive.call::<Bucket>("sell", vec![scrypto_encode(&loan)]);
+                cheap.call::<Bucket>("buy", vec![scrypto_encode(&xrd)])
+            }
         }
     }
 }
@@ -0,0 +1,57 @@
+use scrypto::prelude::*;
+
+/*
+ *  A market buying and selling a token for XRD at a fixed price.
+ *  Two of them with different prices are an arbitrage opportunity for the Caller
+ */
+
+blueprint! {
+    struct Market {
+        tokens: Vault,
+        xrd: Vault,
+        // Price of one token in XRD
+        price: Decimal
+    }
+
+    impl Market {
+        pub fn new(tokens: Bucket, xrd: Bucket, price: Decimal) -> Component {
+            assert!(xrd.resource_address() == RADIX_TOKEN, "The market buys tokens with XRD");
+            assert!(price > Decimal::zero(), "The price must be positive");
+
+            Self {
+                tokens: Vault::with_bucket(tokens),
+                xrd: Vault::with_bucket(xrd),
+                price: price
+            }
+            .instantiate()
+        }
+
+        // Pay XRD to get tokens
+        pub fn buy(&self, payment: Bucket) -> Bucket {
+            assert!(payment.resource_address() == RADIX_TOKEN, "You have to pay with XRD");
+            let amount = payment.amount() / self.price;
+            assert!(amount <= self.tokens.amount(), "Not enough tokens to sell {}", amount);
+
+            self.xrd.put(payment);
+            self.tokens.take(amount)
+        }
+
+        // Pay tokens to get XRD
+        pub fn sell(&self, payment: Bucket) -> Bucket {
+            assert!(payment.resource_address() == self.tokens.resource_address(), "Wrong token");
+            let amount = payment.amount() * self.price;
+            assert!(amount <= self.xrd.amount(), "Not enough XRD to buy for {}", amount);
+
+            self.tokens.put(payment);
+            self.xrd.take(amount)
+        }
+
+        pub fn get_price(&self) -> Decimal {
+            self.price
+        }
+
+        pub fn get_token(&self) -> Address {
+            self.tokens.resource_address()
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use scrypto::prelude::*;
+
+/*
+ *  A borrower of the PoolFlashLoan adding or removing liquidity of the pool while it holds the loan.
+ *  The pool holds less than it is worth during the loan so it must refuse both.
+ */
+
+blueprint! {
+    struct PoolDepositor {
+        pool: Address,
+        xrd: Vault,
+        lp_tokens: Vault,
+        // XRD deposited to the pool and LP tokens given back during the loan
+        deposit: Decimal,
+        withdraw: Decimal,
+        // XRD added to the loan to pay the interest
+        interest: Decimal
+    }
+
+    impl PoolDepositor {
+        pub fn new(pool: Address, xrd: Bucket, lp_tokens: Bucket, interest: Decimal) -> Component {
+            Self {
+                pool: pool,
+                xrd: Vault::with_bucket(xrd),
+                lp_tokens: Vault::with_bucket(lp_tokens),
+                deposit: Decimal::zero(),
+                withdraw: Decimal::zero(),
+                interest: interest
+            }
+            .instantiate()
+        }
+
+        pub fn set_attack(&mut self, deposit: Decimal, withdraw: Decimal) {
+            self.deposit = deposit;
+            self.withdraw = withdraw;
+        }
+
+        // Single loan callback of the PoolFlashLoan
+        pub fn execute(&self, loan: Bucket) -> Bucket {
+            let pool = Component::from(self.pool);
+            if self.deposit > Decimal::zero() {
+                let lp_tokens: Bucket = pool.call::<Bucket>("add_liquidity", vec![scrypto_encode(&self.xrd.take(self.deposit)), scrypto_encode(&Decimal::zero())]);
+                self.lp_tokens.put(lp_tokens);
+            }
+            if self.withdraw > Decimal::zero() {
+                let xrd: Bucket = pool.call::<Bucket>("remove_liquidity", vec![scrypto_encode(&self.lp_tokens.take(self.withdraw)), scrypto_encode(&Decimal::zero())]);
+                self.xrd.put(xrd);
+            }
+
+            loan.put(self.xrd.take(self.interest));
+            loan
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use scrypto::prelude::*;
+
+/*
+ *  A borrower of the FlashLoan repaying the XRD loan in full but not the token loan:
+ *  the token loan comes back without the interest, or XRD is given in place of the token.
+ *  The FlashLoan must check the repayment of every resource on its own.
+ */
+
+blueprint! {
+    struct ShortRepayer {
+        loaner_component: Address,
+        // XRD for the interest and the substitute repayments, tokens kept in place of the loan
+        xrd: Vault,
+        tokens: Vault,
+        interest: Decimal
+    }
+
+    impl ShortRepayer {
+        pub fn new(loaner_component_address: Address, xrd: Bucket, tokens: Bucket, interest: Decimal) -> Component {
+            Self {
+                loaner_component: loaner_component_address,
+                xrd: Vault::with_bucket(xrd),
+                tokens: Vault::with_bucket(tokens),
+                interest: interest
+            }
+            .instantiate()
+        }
+
+        // Loan XRD and tokens, the tokens are repaid short or replaced with XRD when `substitute`
+        pub fn call(&self, this_component_address: Address, xrd_amount: Decimal, token_amount: Decimal, substitute: bool) -> Vec<Bucket> {
+            let mut loans: Vec<(Address, Decimal)> = vec![(RADIX_TOKEN, xrd_amount)];
+            if token_amount > Decimal::zero() {
+                loans.push((self.tokens.resource_address(), token_amount));
+            }
+
+            let args = vec![
+                scrypto_encode(&loans),
+                scrypto_encode(&this_component_address),
+                scrypto_encode(&"repay".to_owned()),
+                scrypto_encode(&vec![scrypto_encode(&substitute)])
+            ];
+            Component::from(self.loaner_component).call::<Vec<Bucket>>("request_loan", args)
+        }
+
+        // Callback of the FlashLoan
+        pub fn repay(&self, loans: Vec<Bucket>, substitute: bool) -> Vec<Bucket> {
+            let mut repayments: Vec<Bucket> = Vec::new();
+            for loan in loans {
+                let interest = loan.amount() * self.interest / 100;
+                if loan.resource_address() == RADIX_TOKEN {
+                    loan.put(self.xrd.take(interest));
+                    repayments.push(loan);
+                } else if substitute {
+                    repayments.push(self.xrd.take(loan.amount() + interest));
+                    self.tokens.put(loan);
+                } else {
+                    repayments.push(loan);
+                }
+            }
+            repayments
+        }
+    }
+}
@@ -1,9 +1,24 @@
 use scrypto::prelude::*;
 
+mod pool_flash_loan;
+
+#[derive(NftData)]
+pub struct LoanReceipt {
+    // loaned resource
+    resource_address: Address,
+    // loaned amount plus interest
+    amount_owed: Decimal
+}
+
 blueprint! {
     struct FlashLoan {
         interest: Decimal,
-        vault: Vault
+        vaults: HashMap<Address, Vault>,
+        // Mints and burns the loan receipts
+        receipt_minter: Vault,
+        // Loan receipts can't be deposited, they have to be burned by `repay` in the same transaction
+        receipt_def: ResourceDef,
+        receipt_id_counter: u128
     }
 
     impl FlashLoan {
@@ -11,31 +26,150 @@ blueprint! {
          * Generate a FlashLoan component with a specified interest and a specified bucket as funds
          */
         pub fn new(tokens: Bucket, interest: Decimal) -> Component {
+            let mut vaults = HashMap::new();
+            vaults.insert(tokens.resource_address(), Vault::with_bucket(tokens));
+
+            let receipt_minter = ResourceBuilder::new_fungible(DIVISIBILITY_NONE)
+                .metadata("name", "Loan Receipt Minter")
+                .initial_supply_fungible(1);
+
+            // Without anybody allowed to transfer them, the receipts can't be put in a vault (ie. an account)
+            let receipt_def = ResourceBuilder::new_non_fungible()
+                .metadata("name", "Loan Receipt")
+                .flags(MINTABLE | BURNABLE | RESTRICTED_TRANSFER)
+                .badge(receipt_minter.resource_def(), MAY_MINT | MAY_BURN)
+                .no_initial_supply();
+
             Self {
-                vault: Vault::with_bucket(tokens),
+                vaults: vaults,
                 interest: interest,
+                receipt_minter: Vault::with_bucket(receipt_minter),
+                receipt_def: receipt_def,
+                receipt_id_counter: 0
             }
             .instantiate()
         }
 
-        pub fn request_loan(&self, amount: Decimal, component_address: Address) -> Bucket {
-            assert!(amount < self.vault.amount(), "Not enough funds to loan");
+        /*
+         * Add funds to loan, in a new vault if the resource isn't loaned yet
+         */
+        pub fn add_funds(&mut self, tokens: Bucket) {
+            let resource_address = tokens.resource_address();
+            match self.vaults.get(&resource_address) {
+                Some(vault) => vault.put(tokens),
+                None => {
+                    self.vaults.insert(resource_address, Vault::with_bucket(tokens));
+                }
+            }
+        }
+
+        pub fn get_funds(&self, resource_address: Address) -> Decimal {
+            match self.vaults.get(&resource_address) {
+                Some(vault) => vault.amount(),
+                None => Decimal::zero()
+            }
+        }
 
-            // Call the execute method at the specified component's address with the requested funds
-            let args = vec![
-                scrypto_encode(&self.vault.take(amount))
+        /*
+         * Loan every (resource, amount) and call `method` at the specified component's address with the loans
+         * as a Vec<Bucket> followed by `args`, which have to be already encoded with scrypto_encode.
+         * The method has to return a Vec<Bucket> with at least the amount plus interest of every loaned resource,
+         * the change is returned.
+         */
+        pub fn request_loan(&self, loans: Vec<(Address, Decimal)>, component_address: Address, method: String, args: Vec<Vec<u8>>) -> Vec<Bucket> {
+            assert!(!loans.is_empty(), "No loan requested");
+
+            let mut buckets: Vec<Bucket> = Vec::new();
+            // Owed amount per resource, a resource requested several times is owed once in total
+            let mut owed: HashMap<Address, Decimal> = HashMap::new();
+            for (resource_address, amount) in loans {
+                assert!(amount > Decimal::zero(), "Loan amount must be positive");
+                let vault = match self.vaults.get(&resource_address) {
+                    Some(vault) => vault,
+                    None => panic!("No funds of {} to loan", resource_address)
+                };
+                assert!(amount <= vault.amount(), "Not enough funds of {} to loan", resource_address);
+
+                buckets.push(vault.take(amount));
+                let owed_amount = owed.entry(resource_address).or_insert(Decimal::zero());
+                *owed_amount = *owed_amount + amount * ((self.interest / 100) + 1);
+            }
+
+            // Call the method at the specified component's address with the requested funds and the extra args
+            let mut call_args = vec![
+                scrypto_encode(&buckets)
             ];
+            call_args.extend(args);
 
-            let returned_bucket: Bucket = Component::from(component_address).call::<Bucket>("execute", args).into();
+            let returned_buckets: Vec<Bucket> = Component::from(component_address).call::<Vec<Bucket>>(&method, call_args);
 
-            // Make sure they repaid in loan in full
-            let amount_to_take = amount * ((self.interest / 100) + 1);
-            assert!(returned_bucket.amount() >= amount_to_take, "You have to return more than {}", amount_to_take);
+            // Gather what was returned per resource
+            let mut repaid: HashMap<Address, Bucket> = HashMap::new();
+            for bucket in returned_buckets {
+                match repaid.get(&bucket.resource_address()) {
+                    Some(total) => total.put(bucket),
+                    None => {
+                        repaid.insert(bucket.resource_address(), bucket);
+                    }
+                }
+            }
 
-            self.vault.put(returned_bucket.take(amount_to_take));
+            // Make sure they repaid every loan in full
+            for (resource_address, amount_to_take) in owed.iter() {
+                let repaid_amount = repaid.get(resource_address).map(|bucket| bucket.amount()).unwrap_or(Decimal::zero());
+                assert!(repaid_amount >= *amount_to_take, "You have to return more than {} of {}", amount_to_take, resource_address);
+
+                let returned_bucket = repaid.get(resource_address).unwrap();
+                self.vaults.get(resource_address).unwrap().put(returned_bucket.take(*amount_to_take));
+            }
 
             // Return the change back to the component
-            return returned_bucket;
+            repaid.into_values().collect()
+        }
+
+        /*
+         * Loan without calling back: the loan comes with a receipt that can't be deposited, so the transaction
+         * fails unless the receipt is given back to `repay` with the amount plus interest before it ends.
+         * This allows composing flash loans in a transaction without a borrowing component.
+         */
+        pub fn take_loan(&mut self, resource_address: Address, amount: Decimal) -> (Bucket, Bucket) {
+            assert!(amount > Decimal::zero(), "Loan amount must be positive");
+            let vault = match self.vaults.get(&resource_address) {
+                Some(vault) => vault,
+                None => panic!("No funds of {} to loan", resource_address)
+            };
+            assert!(amount <= vault.amount(), "Not enough funds of {} to loan", resource_address);
+
+            let data = LoanReceipt {
+                resource_address: resource_address,
+                amount_owed: amount * ((self.interest / 100) + 1)
+            };
+            let receipt = self.receipt_minter.authorize(|auth| {
+                self.receipt_def.mint_nft(self.receipt_id_counter, data, auth)
+            });
+            self.receipt_id_counter += 1;
+
+            (vault.take(amount), receipt)
+        }
+
+        /*
+         * Repay a loan taken with `take_loan` and burn its receipt, the change is returned
+         */
+        pub fn repay(&mut self, repayment: Bucket, receipt: Bucket) -> Bucket {
+            assert!(receipt.resource_def() == self.receipt_def, "Invalid loan receipt");
+            assert!(receipt.amount() == Decimal::one(), "Repay one loan at a time");
+
+            let data: LoanReceipt = self.receipt_def.get_nft_data(receipt.get_nft_id());
+            assert!(repayment.resource_address() == data.resource_address, "You have to repay with {}", data.resource_address);
+            assert!(repayment.amount() >= data.amount_owed, "You have to return more than {}", data.amount_owed);
+
+            self.vaults.get(&data.resource_address).unwrap().put(repayment.take(data.amount_owed));
+            self.receipt_minter.authorize(|auth| {
+                receipt.burn_with_auth(auth);
+            });
+
+            // Return the change
+            repayment
         }
     }
 }
@@ -0,0 +1,57 @@
+use scrypto::prelude::*;
+
+blueprint! {
+    struct PoolFlashLoan {
+        interest: Decimal,
+        // SingleTokenLiquidityPool the loans are taken from
+        pool: Address,
+        // Badge allowing to borrow from the pool
+        lender_badge: Vault
+    }
+
+    impl PoolFlashLoan {
+        /*
+         * Generate a PoolFlashLoan component with a specified interest lending the liquidity of a SingleTokenLiquidityPool.
+         * The lender badge is the one returned by SingleTokenLiquidityPool::new_with_lender.
+         * Interest is given to the pool as fees so LP token holders earn it.
+         */
+        pub fn new(pool_address: Address, lender_badge: Bucket, interest: Decimal) -> Component {
+            Self {
+                interest: interest,
+                pool: pool_address,
+                lender_badge: Vault::with_bucket(lender_badge)
+            }
+            .instantiate()
+        }
+
+        pub fn request_loan(&self, amount: Decimal, component_address: Address) -> Bucket {
+            let pool = Component::from(self.pool);
+
+            // Borrow the funds from the pool, it fails if the pool doesn't hold enough
+            let loan: Bucket = self.lender_badge.authorize(|badge| {
+                pool.call::<Bucket>("borrow", vec![scrypto_encode(&amount), scrypto_encode(&badge)])
+            });
+
+            // Call the execute method at the specified component's address with the requested funds
+            let args = vec![
+                scrypto_encode(&loan)
+            ];
+
+            let returned_bucket: Bucket = Component::from(component_address).call::<Bucket>("execute", args).into();
+
+            // Make sure they repaid in loan in full
+            let amount_to_take = amount * ((self.interest / 100) + 1);
+            assert!(returned_bucket.amount() >= amount_to_take, "You have to return more than {}", amount_to_take);
+
+            // Give the principal back to the pool and the interest to its liquidity providers
+            let principal = returned_bucket.take(amount);
+            self.lender_badge.authorize(|badge| {
+                pool.call::<()>("repay", vec![scrypto_encode(&principal), scrypto_encode(&badge)])
+            });
+            pool.call::<()>("add_fees", vec![scrypto_encode(&returned_bucket.take(amount_to_take - amount))]);
+
+            // Return the change back to the component
+            return returned_bucket;
+        }
+    }
+}
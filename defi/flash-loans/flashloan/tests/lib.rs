@@ -0,0 +1,303 @@
+use radix_engine::ledger::*;
+use radix_engine::transaction::*;
+use scrypto::prelude::*;
+
+// The pool and the caller are separate packages, build them with `scrypto build` before running these tests
+const POOL_CODE: &[u8] = include_bytes!("../../../single-token-liquidity-pool/target/wasm32-unknown-unknown/release/single_token_liquidity_pool.wasm");
+const CALLER_CODE: &[u8] = include_bytes!("../../caller/target/wasm32-unknown-unknown/release/caller.wasm");
+
+struct TestFlashLoans {
+    key: Address,
+    account: Address,
+    caller_package: Address,
+    token: Address,
+    market_a: Address,
+    market_b: Address,
+    pool: Address,
+    lp_token: Address,
+    pool_flashloan: Address,
+    flashloan: Address,
+    loan_receipt: Address,
+    caller: Address,
+}
+
+// Markets trade GUM at 1 and 1.2 XRD, the second one only holds 2000 XRD to buy GUM.
+// The FlashLoan lends 1000 XRD and 500 GUM, the PoolFlashLoan lends a pool of 10000 XRD, both at 5% interest.
+fn setup<'a, L: Ledger>(executor: &mut TransactionExecutor<'a, L>) -> TestFlashLoans {
+    let key = executor.new_public_key();
+    let account = executor.new_account(key);
+
+    let package = executor.publish_package(include_code!("flashloan"));
+    let pool_package = executor.publish_package(POOL_CODE);
+    let caller_package = executor.publish_package(CALLER_CODE);
+
+    let mut metadata = HashMap::new();
+    metadata.insert("symbol".to_owned(), "GUM".to_owned());
+    let transaction1 = TransactionBuilder::new(executor)
+        .new_token_fixed(metadata, 100000.into())
+        .deposit_all_buckets(account)
+        .build(vec![key])
+        .unwrap();
+    let receipt1 = executor.run(transaction1, false).unwrap();
+    println!("{:?}\n", receipt1);
+    assert!(receipt1.success);
+    let token = receipt1.resource_def(0).unwrap();
+
+    let transaction2 = TransactionBuilder::new(executor)
+        .call_function(caller_package, "Market", "new", vec![format!("5000,{}", token), format!("5000,{}", RADIX_TOKEN), "1".to_owned()], Some(account))
+        .call_function(caller_package, "Market", "new", vec![format!("5000,{}", token), format!("2000,{}", RADIX_TOKEN), "1.2".to_owned()], Some(account))
+        .call_function(package, "FlashLoan", "new", vec![format!("1000,{}", RADIX_TOKEN), "5".to_owned()], Some(account))
+        .drop_all_bucket_refs()
+        .deposit_all_buckets(account)
+        .build(vec![key])
+        .unwrap();
+    let receipt2 = executor.run(transaction2, false).unwrap();
+    println!("{:?}\n", receipt2);
+    assert!(receipt2.success);
+    let market_a = receipt2.component(0).unwrap();
+    let market_b = receipt2.component(1).unwrap();
+    let flashloan = receipt2.component(2).unwrap();
+    let loan_receipt = receipt2.resource_def(1).unwrap();
+
+    let transaction3 = TransactionBuilder::new(executor)
+        .call_method(flashloan, "add_funds", vec![format!("500,{}", token)], Some(account))
+        .call_function(caller_package, "Caller", "new", vec![flashloan.to_string(), market_a.to_string(), market_b.to_string()], Some(account))
+        .call_function(pool_package, "SingleTokenLiquidityPool", "new_with_lender", vec![format!("10000,{}", RADIX_TOKEN)], Some(account))
+        .drop_all_bucket_refs()
+        .deposit_all_buckets(account)
+        .build(vec![key])
+        .unwrap();
+    let receipt3 = executor.run(transaction3, false).unwrap();
+    println!("{:?}\n", receipt3);
+    assert!(receipt3.success);
+    let caller = receipt3.component(0).unwrap();
+    let pool = receipt3.component(1).unwrap();
+    let lender_badge = receipt3.resource_def(0).unwrap();
+    let lp_token = receipt3.resource_def(2).unwrap();
+
+    let transaction4 = TransactionBuilder::new(executor)
+        .call_function(package, "PoolFlashLoan", "new", vec![pool.to_string(), format!("1,{}", lender_badge), "5".to_owned()], Some(account))
+        .drop_all_bucket_refs()
+        .deposit_all_buckets(account)
+        .build(vec![key])
+        .unwrap();
+    let receipt4 = executor.run(transaction4, false).unwrap();
+    println!("{:?}\n", receipt4);
+    assert!(receipt4.success);
+
+    TestFlashLoans {
+        key,
+        account,
+        caller_package,
+        token,
+        market_a,
+        market_b,
+        pool,
+        lp_token,
+        pool_flashloan: receipt4.component(0).unwrap(),
+        flashloan,
+        loan_receipt,
+        caller,
+    }
+}
+
+fn call<'a, L: Ledger>(executor: &mut TransactionExecutor<'a, L>, test: &TestFlashLoans, component: Address, method: &str, args: Vec<String>) -> bool {
+    let transaction = TransactionBuilder::new(executor)
+        .call_method(component, method, args, Some(test.account))
+        .drop_all_bucket_refs()
+        .deposit_all_buckets(test.account)
+        .build(vec![test.key])
+        .unwrap();
+    let receipt = executor.run(transaction, false).unwrap();
+    println!("{:?}\n", receipt);
+    receipt.success
+}
+
+fn get_decimal<'a, L: Ledger>(executor: &mut TransactionExecutor<'a, L>, test: &TestFlashLoans, component: Address, method: &str, args: Vec<String>) -> Decimal {
+    let transaction = TransactionBuilder::new(executor)
+        .call_method(component, method, args, Some(test.account))
+        .build(vec![test.key])
+        .unwrap();
+    let mut receipt = executor.run(transaction, false).unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.success);
+    scrypto_decode(&receipt.results.swap_remove(0).unwrap().unwrap().encoded).unwrap()
+}
+
+#[test]
+fn test_multi_asset_loan_is_repaid_per_resource() {
+    let mut ledger = InMemoryLedger::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, 0, 0);
+    let test = setup(&mut executor);
+
+    // the whole 1000 XRD and 500 GUM are lent at once, each arbitrage makes 20% so 5% interest is repaid per resource
+    assert!(call(&mut executor, &test, test.caller, "call", vec![test.caller.to_string(), "1000".to_owned(), "500".to_owned(), "100".to_owned()]));
+    assert_eq!(get_decimal(&mut executor, &test, test.flashloan, "get_funds", vec![RADIX_TOKEN.to_string()]), 1050.into());
+    assert_eq!(get_decimal(&mut executor, &test, test.flashloan, "get_funds", vec![test.token.to_string()]), 525.into());
+
+    // the expensive market has 200 XRD left so the XRD loan can't be repaid anymore, nothing changes
+    assert!(!call(&mut executor, &test, test.caller, "call", vec![test.caller.to_string(), "1000".to_owned(), "0".to_owned(), "0".to_owned()]));
+    assert_eq!(get_decimal(&mut executor, &test, test.flashloan, "get_funds", vec![RADIX_TOKEN.to_string()]), 1050.into());
+}
+
+#[test]
+fn test_loan_checks() {
+    let mut ledger = InMemoryLedger::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, 0, 0);
+    let test = setup(&mut executor);
+
+    // more than the funds
+    assert!(!call(&mut executor, &test, test.caller, "call", vec![test.caller.to_string(), "1001".to_owned(), "0".to_owned(), "0".to_owned()]));
+
+    // the extra argument of the callback: 1000 XRD only make 200 XRD
+    assert!(!call(&mut executor, &test, test.caller, "call", vec![test.caller.to_string(), "1000".to_owned(), "0".to_owned(), "201".to_owned()]));
+    assert!(call(&mut executor, &test, test.caller, "call", vec![test.caller.to_string(), "1000".to_owned(), "0".to_owned(), "200".to_owned()]));
+    assert_eq!(get_decimal(&mut executor, &test, test.flashloan, "get_funds", vec![RADIX_TOKEN.to_string()]), 1050.into());
+}
+
+// the arbitrage of the Caller done within the transaction, repaying with `repay_amount` XRD
+fn transient_loan<'a, L: Ledger>(executor: &mut TransactionExecutor<'a, L>, test: &TestFlashLoans, repay_amount: &str) -> bool {
+    let transaction = TransactionBuilder::new(executor)
+        .call_method(test.flashloan, "take_loan", vec![RADIX_TOKEN.to_string(), "1000".to_owned()], None)
+        .call_method(test.market_a, "buy", vec![format!("1000,{}", RADIX_TOKEN)], None)
+        .call_method(test.market_b, "sell", vec![format!("1000,{}", test.token)], None)
+        .call_method(test.flashloan, "repay", vec![format!("{},{}", repay_amount, RADIX_TOKEN), format!("1,{}", test.loan_receipt)], None)
+        .drop_all_bucket_refs()
+        .deposit_all_buckets(test.account)
+        .build(vec![test.key])
+        .unwrap();
+    let receipt = executor.run(transaction, false).unwrap();
+    println!("{:?}\n", receipt);
+    receipt.success
+}
+
+#[test]
+fn test_transient_loan_repaid_in_the_transaction() {
+    let mut ledger = InMemoryLedger::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, 0, 0);
+    let test = setup(&mut executor);
+
+    // 1040 XRD don't cover the 5% interest
+    assert!(!transient_loan(&mut executor, &test, "1040"));
+    assert_eq!(get_decimal(&mut executor, &test, test.flashloan, "get_funds", vec![RADIX_TOKEN.to_string()]), 1000.into());
+
+    assert!(transient_loan(&mut executor, &test, "1200"));
+    assert_eq!(get_decimal(&mut executor, &test, test.flashloan, "get_funds", vec![RADIX_TOKEN.to_string()]), 1050.into());
+}
+
+#[test]
+fn test_unrepaid_loan_receipt_fails_the_transaction() {
+    let mut ledger = InMemoryLedger::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, 0, 0);
+    let test = setup(&mut executor);
+
+    // the receipt can't be deposited in the account
+    assert!(!call(&mut executor, &test, test.flashloan, "take_loan", vec![RADIX_TOKEN.to_string(), "1000".to_owned()]));
+    assert_eq!(get_decimal(&mut executor, &test, test.flashloan, "get_funds", vec![RADIX_TOKEN.to_string()]), 1000.into());
+}
+
+#[test]
+fn test_pool_loan_interest_goes_to_liquidity_providers() {
+    let mut ledger = InMemoryLedger::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, 0, 0);
+    let test = setup(&mut executor);
+
+    // deposit, the account now owns all 20000 LP tokens but the locked minimum liquidity
+    assert!(!call(&mut executor, &test, test.pool, "add_liquidity", vec![format!("10000,{}", RADIX_TOKEN), "10001".to_owned()]));
+    assert!(call(&mut executor, &test, test.pool, "add_liquidity", vec![format!("10000,{}", RADIX_TOKEN), "10000".to_owned()]));
+    assert_eq!(get_decimal(&mut executor, &test, test.pool, "get_liquidity", vec![]), 20000.into());
+
+    // loan of 1000 XRD repaid with 50 XRD of interest
+    assert!(call(&mut executor, &test, test.pool_flashloan, "request_loan", vec!["1000".to_owned(), test.caller.to_string()]));
+    assert_eq!(get_decimal(&mut executor, &test, test.pool, "get_liquidity", vec![]), 20000.into());
+    assert_eq!(get_decimal(&mut executor, &test, test.pool, "get_fees", vec![]), 50.into());
+
+    // the arbitrage opportunity is gone so the second loan can't be repaid and nothing changes
+    assert!(!call(&mut executor, &test, test.pool_flashloan, "request_loan", vec!["1000".to_owned(), test.caller.to_string()]));
+    assert_eq!(get_decimal(&mut executor, &test, test.pool, "get_liquidity", vec![]), 20000.into());
+    assert_eq!(get_decimal(&mut executor, &test, test.pool, "get_fees", vec![]), 50.into());
+
+    // removing half of the LP tokens returns half of the pool and of the interest
+    assert!(!call(&mut executor, &test, test.pool, "remove_liquidity", vec![format!("10000,{}", test.lp_token), "10026".to_owned()]));
+    assert!(call(&mut executor, &test, test.pool, "remove_liquidity", vec![format!("10000,{}", test.lp_token), "10025".to_owned()]));
+    assert_eq!(get_decimal(&mut executor, &test, test.pool, "get_liquidity", vec![]), 10000.into());
+    assert_eq!(get_decimal(&mut executor, &test, test.pool, "get_fees", vec![]), 25.into());
+}
+
+#[test]
+fn test_only_the_lender_can_borrow_from_the_pool() {
+    let mut ledger = InMemoryLedger::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, 0, 0);
+    let test = setup(&mut executor);
+
+    // the lender badge is held by the PoolFlashLoan component
+    assert!(!call(&mut executor, &test, test.pool, "borrow", vec!["100".to_owned()]));
+    assert_eq!(get_decimal(&mut executor, &test, test.pool, "get_liquidity", vec![]), 10000.into());
+}
+
+#[test]
+fn test_pool_liquidity_cant_change_during_a_loan() {
+    let mut ledger = InMemoryLedger::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, 0, 0);
+    let test = setup(&mut executor);
+
+    let transaction = TransactionBuilder::new(&executor)
+        .call_function(test.caller_package, "PoolDepositor", "new", vec![test.pool.to_string(), format!("1000,{}", RADIX_TOKEN), format!("100,{}", test.lp_token), "50".to_owned()], Some(test.account))
+        .drop_all_bucket_refs()
+        .deposit_all_buckets(test.account)
+        .build(vec![test.key])
+        .unwrap();
+    let receipt = executor.run(transaction, false).unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.success);
+    let depositor = receipt.component(0).unwrap();
+
+    // depositing 500 XRD while 1000 are lent would mint LP tokens against a pool of 9000 XRD
+    assert!(call(&mut executor, &test, depositor, "set_attack", vec!["500".to_owned(), "0".to_owned()]));
+    assert!(!call(&mut executor, &test, test.pool_flashloan, "request_loan", vec!["1000".to_owned(), depositor.to_string()]));
+
+    // withdrawing would leave the lent XRD to the other LP token holders
+    assert!(call(&mut executor, &test, depositor, "set_attack", vec!["0".to_owned(), "100".to_owned()]));
+    assert!(!call(&mut executor, &test, test.pool_flashloan, "request_loan", vec!["1000".to_owned(), depositor.to_string()]));
+    assert_eq!(get_decimal(&mut executor, &test, test.pool, "get_liquidity", vec![]), 10000.into());
+    assert_eq!(get_decimal(&mut executor, &test, test.pool, "get_fees", vec![]), 0.into());
+
+    // the same loan only paying the interest goes through
+    assert!(call(&mut executor, &test, depositor, "set_attack", vec!["0".to_owned(), "0".to_owned()]));
+    assert!(call(&mut executor, &test, test.pool_flashloan, "request_loan", vec!["1000".to_owned(), depositor.to_string()]));
+    assert_eq!(get_decimal(&mut executor, &test, test.pool, "get_liquidity", vec![]), 10000.into());
+    assert_eq!(get_decimal(&mut executor, &test, test.pool, "get_fees", vec![]), 50.into());
+}
+
+#[test]
+fn test_every_resource_has_to_be_repaid() {
+    let mut ledger = InMemoryLedger::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, 0, 0);
+    let test = setup(&mut executor);
+
+    let transaction = TransactionBuilder::new(&executor)
+        .call_function(test.caller_package, "ShortRepayer", "new", vec![test.flashloan.to_string(), format!("1000,{}", RADIX_TOKEN), format!("100,{}", test.token), "5".to_owned()], Some(test.account))
+        .drop_all_bucket_refs()
+        .deposit_all_buckets(test.account)
+        .build(vec![test.key])
+        .unwrap();
+    let receipt = executor.run(transaction, false).unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.success);
+    let repayer = receipt.component(0).unwrap();
+
+    // the XRD loan is repaid with interest but the GUM loan comes back without it
+    assert!(!call(&mut executor, &test, repayer, "call", vec![repayer.to_string(), "1000".to_owned(), "500".to_owned(), "false".to_owned()]));
+    assert_eq!(get_decimal(&mut executor, &test, test.flashloan, "get_funds", vec![RADIX_TOKEN.to_string()]), 1000.into());
+    assert_eq!(get_decimal(&mut executor, &test, test.flashloan, "get_funds", vec![test.token.to_string()]), 500.into());
+
+    // 525 more XRD don't repay the 525 GUM
+    assert!(!call(&mut executor, &test, repayer, "call", vec![repayer.to_string(), "1000".to_owned(), "500".to_owned(), "true".to_owned()]));
+    assert_eq!(get_decimal(&mut executor, &test, test.flashloan, "get_funds", vec![RADIX_TOKEN.to_string()]), 1000.into());
+    assert_eq!(get_decimal(&mut executor, &test, test.flashloan, "get_funds", vec![test.token.to_string()]), 500.into());
+
+    // the XRD loan alone is repaid
+    assert!(call(&mut executor, &test, repayer, "call", vec![repayer.to_string(), "1000".to_owned(), "0".to_owned(), "false".to_owned()]));
+    assert_eq!(get_decimal(&mut executor, &test, test.flashloan, "get_funds", vec![RADIX_TOKEN.to_string()]), 1050.into());
+    assert_eq!(get_decimal(&mut executor, &test, test.flashloan, "get_funds", vec![test.token.to_string()]), 500.into());
+}
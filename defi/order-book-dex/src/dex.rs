@@ -1,6 +1,9 @@
 use crate::trading_pair::*;
 use scrypto::prelude::*;
 
+/// The maximum number of trading pairs a routed order may pass through
+const MAX_ROUTE_HOPS: usize = 3;
+
 blueprint! {
 
     /// The Dex component is the central component of this system's architecture.
@@ -33,6 +36,8 @@ blueprint! {
         }
 
         /// Adds a trading pair for the given base_resource_address and quote_resource_address.
+        /// The maker_fee and taker_fee rates are charged on the resources received by makers and takers.
+        /// The fees are collected by the trading pair and can be withdrawn using this component's admin badge.
         /// Checks that the same trading pair cannot be added twice.
         /// Does not check that the reverse of an existing trading pair is not added!
         #[auth(admin_badge)]
@@ -40,6 +45,8 @@ blueprint! {
             &mut self,
             base_resource_address: Address,
             quote_resource_address: Address,
+            maker_fee: Decimal,
+            taker_fee: Decimal,
         ) {
             // Prevent the same trading pair from being added twice
             assert!(
@@ -55,6 +62,9 @@ blueprint! {
             let trainding_pair_component = TradingPair::instantiate(
                 ResourceDef::from(base_resource_address),
                 ResourceDef::from(quote_resource_address),
+                maker_fee,
+                taker_fee,
+                self.admin_badge.clone(),
             );
 
             // Save the info on the newly created trading pair in the trading_pairs HashMap
@@ -84,6 +94,103 @@ blueprint! {
                 .get(&(base_resource_address, quote_resource_address))
                 .map(|trading_pair| trading_pair.component_address)
         }
+
+        /// Exchanges the given funds for the target resource, even if no trading pair exists for the two resources.
+        /// A route of at most three trading pairs is searched and the funds are exchanged along that route via market
+        /// orders. The route with the fewest trading pairs is chosen.
+        ///
+        /// Returns two values with
+        /// 1. The traded funds of the target resource.
+        /// 2. Any dust that remains of the supplied funds and the intermediate resources, one bucket per trading pair.
+        ///
+        /// Panics if no route exists, if one of the market orders cannot be filled or if less than min_output of the
+        /// target resource is received.
+        pub fn route_market_order(
+            &self,
+            funds: Bucket,
+            target_resource_address: Address,
+            min_output: Decimal,
+        ) -> (Bucket, Vec<Bucket>) {
+            let route = self
+                .find_route(funds.resource_address(), target_resource_address)
+                .expect("No route found between the supplied and the target resource");
+
+            // Execute a market order on every trading pair along the route, passing the traded funds on to the next one
+            let mut traded_funds = funds;
+            let mut dust = Vec::new();
+            for trading_pair_info in route {
+                let trading_pair: TradingPair = trading_pair_info.component_address.into();
+                let (remainder, traded) = trading_pair.new_market_order(traded_funds);
+                dust.push(remainder);
+                traded_funds = traded;
+            }
+
+            assert!(
+                traded_funds.amount() >= min_output,
+                "Insufficient output: received {} but expected at least {}",
+                traded_funds.amount(),
+                min_output
+            );
+
+            (traded_funds, dust)
+        }
+
+        /// Returns the trading pairs that a routed order from the given source resource to the given target resource
+        /// would pass through or None if no route of at most three trading pairs exists.
+        pub fn get_route(
+            &self,
+            source_resource_address: Address,
+            target_resource_address: Address,
+        ) -> Option<Vec<TradingPairInfo>> {
+            self.find_route(source_resource_address, target_resource_address)
+        }
+
+        /// Searches the shortest route between the two resources using a breadth first search over all trading pairs.
+        /// Trading pairs can be traversed in both directions, from the base to the quote resource and vice versa.
+        fn find_route(
+            &self,
+            source_resource_address: Address,
+            target_resource_address: Address,
+        ) -> Option<Vec<TradingPairInfo>> {
+            assert_ne!(
+                source_resource_address, target_resource_address,
+                "The source and target resources must be different"
+            );
+
+            // Each entry holds the resource reached so far and the trading pairs used to reach it
+            let mut routes: Vec<(Address, Vec<TradingPairInfo>)> =
+                vec![(source_resource_address, Vec::new())];
+            let mut visited: HashSet<Address> = HashSet::new();
+            visited.insert(source_resource_address);
+
+            for _ in 0..MAX_ROUTE_HOPS {
+                let mut next_routes = Vec::new();
+                for (resource_address, route) in routes.iter() {
+                    for trading_pair in self.trading_pairs.values() {
+                        let next_resource_address =
+                            if trading_pair.base_resource_address == *resource_address {
+                                trading_pair.quote_resource_address
+                            } else if trading_pair.quote_resource_address == *resource_address {
+                                trading_pair.base_resource_address
+                            } else {
+                                continue;
+                            };
+
+                        let mut next_route = route.clone();
+                        next_route.push(trading_pair.clone());
+                        if next_resource_address == target_resource_address {
+                            return Some(next_route);
+                        }
+                        if visited.insert(next_resource_address) {
+                            next_routes.push((next_resource_address, next_route));
+                        }
+                    }
+                }
+                routes = next_routes;
+            }
+
+            None
+        }
     }
 }
 
@@ -1,3 +1,5 @@
 mod dex;
 mod model;
 mod trading_pair;
+
+pub use model::Trade;
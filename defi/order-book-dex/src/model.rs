@@ -42,6 +42,17 @@ pub(crate) struct LimitOrder {
     /// The amount of the order quantity that has already been filled.
     #[scrypto(mutable)]
     pub quantity_filled: Decimal,
+
+    /// The amount of the filled quantity for which the traded resources have already been claimed by the user.
+    #[scrypto(mutable)]
+    pub quantity_claimed: Decimal,
+
+    /// Whether the order has been canceled, i.e. removed from the order book and the unfilled quantity refunded.
+    #[scrypto(mutable)]
+    pub canceled: bool,
+
+    /// The last epoch in which the order can be filled. None if the order is good until canceled.
+    pub expiry_epoch: Option<u64>,
 }
 
 impl LimitOrder {
@@ -61,9 +72,40 @@ impl LimitOrder {
             price,
             quantity,
             quantity_filled: Decimal::zero(),
+            quantity_claimed: Decimal::zero(),
+            canceled: false,
+            expiry_epoch: None,
         }
     }
 
+    /// Makes this a good-til-epoch order that can only be filled until (and including) the given epoch
+    pub fn with_expiry(mut self, expiry_epoch: u64) -> LimitOrder {
+        self.expiry_epoch = Some(expiry_epoch);
+        self
+    }
+
+    /// Returns true if the order can no longer be filled in the given epoch
+    pub fn is_expired(&self, current_epoch: u64) -> bool {
+        is_expired(self.expiry_epoch, current_epoch)
+    }
+
+    /// Returns true if the order is still live in the order book, i.e. it has neither been canceled nor filled completely
+    pub fn is_open(&self) -> bool {
+        !self.canceled && self.quantity_filled < self.quantity
+    }
+
+    /// Cancel the order, so that it can no longer be filled
+    /// Panics if the order has already been canceled
+    pub fn cancel(&mut self) {
+        assert!(!self.canceled, "The order has already been canceled");
+        self.canceled = true;
+    }
+
+    /// Mark the traded resources of the filled quantity as claimed
+    pub fn claim(&mut self) {
+        self.quantity_claimed = self.quantity_filled;
+    }
+
     /// Fill the market order with the given quantity
     /// Panics if the given quantity would "overfill" the order
     pub fn fill(&mut self, quantity: Decimal) {
@@ -76,18 +118,43 @@ impl LimitOrder {
 
     /// Calculate the amounts of resources the user will receive upon closing the order.
     /// The first value returned is the amount the user will be refunded in case the order has not been filled fully.
-    /// The second value returned is the amount that the user has successfully traded/received when the order was filled/partially filled.
+    /// If the order has been canceled, the unfilled quantity has already been refunded and this amount is zero.
+    /// The second value returned is the amount that the user has successfully traded/received when the order was filled/partially filled
+    /// and that has not been claimed yet.
     pub fn calculate_close_amounts(&self) -> (Decimal, Decimal) {
-        let refund_amount = self.quantity - self.quantity_filled;
+        let refund_amount = if self.canceled {
+            Decimal::zero()
+        } else {
+            self.quantity - self.quantity_filled
+        };
+        let unclaimed_quantity = self.quantity_filled - self.quantity_claimed;
         let traded_amount = match self.side {
-            Side::Ask => self.quantity_filled * self.price,
-            Side::Bid => self.quantity_filled / self.price,
+            Side::Ask => unclaimed_quantity * self.price,
+            Side::Bid => unclaimed_quantity / self.price,
         };
 
         (refund_amount, traded_amount)
     }
 }
 
+/// Returns true if an order with the given expiry epoch can no longer be filled in the given epoch
+fn is_expired(expiry_epoch: Option<u64>, current_epoch: u64) -> bool {
+    match expiry_epoch {
+        Some(expiry_epoch) => current_epoch > expiry_epoch,
+        None => false,
+    }
+}
+
+/// Represents a reference to a limit order in the order book.
+#[derive(sbor::TypeId, sbor::Encode, sbor::Decode, sbor::Describe, Clone, Debug)]
+struct OrderBookEntry {
+    /// The key of the referenced order
+    order_key: NonFungibleKey,
+
+    /// The expiry epoch of the referenced order. Stored here so that expired orders can be purged without loading them.
+    expiry_epoch: Option<u64>,
+}
+
 /// Represents one of the sides of an order book.
 #[derive(sbor::TypeId, sbor::Encode, sbor::Decode, sbor::Describe, Debug)]
 struct OrderBookSide {
@@ -95,8 +162,8 @@ struct OrderBookSide {
     side: Side,
 
     /// The orders on this side of the order book
-    /// Keys represent the price level while values are vectors holding the keys of all orders that live on this price level.
-    orders: BTreeMap<Decimal, Vec<NonFungibleKey>>,
+    /// Keys represent the price level while values are vectors holding the entries of all orders that live on this price level.
+    orders: BTreeMap<Decimal, Vec<OrderBookEntry>>,
 }
 
 impl OrderBookSide {
@@ -109,7 +176,7 @@ impl OrderBookSide {
 
     /// Retrieves the best price level that exists on this side of the order book. If no orders exist, None is returned.
     /// The returned tuple contains 1) the price level and 2) all orders at this price level.
-    fn get_best_price_level(&self) -> Option<(&Decimal, &Vec<NonFungibleKey>)> {
+    fn get_best_price_level(&self) -> Option<(&Decimal, &Vec<OrderBookEntry>)> {
         // Implementation is likely inefficient and should be replaced with a better solution before production.
         // Once https://github.com/rust-lang/rust/issues/62924 is resolved, switch to the appropriate methods.
         match self.side {
@@ -118,35 +185,86 @@ impl OrderBookSide {
         }
     }
 
+    /// Removes expired orders from the best price level until the best order is not expired.
+    /// Expired orders are only purged lazily, i.e. when they would otherwise be the best order.
+    fn purge_expired(&mut self, current_epoch: u64) {
+        while let Some((price, best_orders)) = self.get_best_price_level() {
+            let price = *price;
+            if best_orders
+                .iter()
+                .all(|entry| !is_expired(entry.expiry_epoch, current_epoch))
+            {
+                return;
+            }
+            let price_level = self.orders.get_mut(&price).unwrap();
+            price_level.retain(|entry| !is_expired(entry.expiry_epoch, current_epoch));
+            if price_level.is_empty() {
+                self.orders.remove(&price);
+            }
+        }
+    }
+
     /// Returns the key of the best order that exists on this side of the order book. Returns None if no order exists.
-    fn get_best_order(&self) -> Option<&NonFungibleKey> {
+    /// Expired orders are purged and never returned.
+    fn get_best_order(&mut self, current_epoch: u64) -> Option<NonFungibleKey> {
+        self.purge_expired(current_epoch);
         let (_, best_orders) = self.get_best_price_level()?;
-        best_orders.first()
+        best_orders.first().map(|entry| entry.order_key.clone())
     }
 
     /// Returns the price of the best order that exists on this side of the order book. Returns None if no order exists.
-    fn get_best_price(&self) -> Option<Decimal> {
+    /// Expired orders are purged and never considered.
+    fn get_best_price(&mut self, current_epoch: u64) -> Option<Decimal> {
+        self.purge_expired(current_epoch);
         let (price, _) = self.get_best_price_level()?;
         Some(*price)
     }
 
+    /// Returns up to the given number of price levels, starting with the best one. Every price level contains the keys
+    /// of its orders that are not expired in the given epoch. Price levels with only expired orders are skipped.
+    /// In contrast to get_best_order, nothing is purged.
+    fn get_price_levels(&self, levels: usize, current_epoch: u64) -> Vec<(Decimal, Vec<NonFungibleKey>)> {
+        let price_levels: Box<dyn Iterator<Item = (&Decimal, &Vec<OrderBookEntry>)>> = match self.side {
+            Side::Ask => Box::new(self.orders.iter()),
+            Side::Bid => Box::new(self.orders.iter().rev()),
+        };
+        price_levels
+            .map(|(price, entries)| {
+                let order_keys: Vec<NonFungibleKey> = entries
+                    .iter()
+                    .filter(|entry| !is_expired(entry.expiry_epoch, current_epoch))
+                    .map(|entry| entry.order_key.clone())
+                    .collect();
+                (*price, order_keys)
+            })
+            .filter(|(_, order_keys)| !order_keys.is_empty())
+            .take(levels)
+            .collect()
+    }
+
     /// Inserts the given limit order into this side of the order book
     fn insert_limit_order(&mut self, order: &LimitOrder) {
+        let entry = OrderBookEntry {
+            order_key: order.order_key.clone(),
+            expiry_epoch: order.expiry_epoch,
+        };
         match self.orders.get_mut(&order.price) {
-            Some(price_level) => price_level.push(order.order_key.clone()),
+            Some(price_level) => price_level.push(entry),
             None => {
-                self.orders
-                    .insert(order.price, vec![order.order_key.clone()]);
+                self.orders.insert(order.price, vec![entry]);
             }
         }
     }
 
-    /// Removes the given order form this side of the order book
+    /// Removes the given order form this side of the order book.
+    /// Does nothing if the order has already been purged because it expired.
     fn remove_order(&mut self, to_remove: &LimitOrder) {
-        let price_level = self.orders.get_mut(&to_remove.price).unwrap();
-        price_level.retain(|order_key| order_key.to_vec() != to_remove.order_key.to_vec());
-        if price_level.is_empty() {
-            self.orders.remove(&to_remove.price);
+        if let Some(price_level) = self.orders.get_mut(&to_remove.price) {
+            price_level
+                .retain(|entry| entry.order_key.to_vec() != to_remove.order_key.to_vec());
+            if price_level.is_empty() {
+                self.orders.remove(&to_remove.price);
+            }
         }
     }
 }
@@ -171,21 +289,36 @@ impl OrderBook {
     }
 
     /// Returns the key of the best order in the order book for the given side. Returns None if no orders exists
-    /// on that side of the order book.
-    pub fn get_best_order(&self, side: Side) -> Option<&NonFungibleKey> {
+    /// on that side of the order book. Orders that are expired in the given epoch are purged and never returned.
+    pub fn get_best_order(&mut self, side: Side, current_epoch: u64) -> Option<NonFungibleKey> {
+        let side = match side {
+            Side::Ask => &mut self.asks,
+            Side::Bid => &mut self.bids,
+        };
+        side.get_best_order(current_epoch)
+    }
+
+    /// Returns up to the given number of price levels of the given side, starting with the best one.
+    /// Every price level contains the price and the keys of all orders on that level that are not expired in the given epoch.
+    pub fn get_price_levels(
+        &self,
+        side: Side,
+        levels: usize,
+        current_epoch: u64,
+    ) -> Vec<(Decimal, Vec<NonFungibleKey>)> {
         let side = match side {
             Side::Ask => &self.asks,
             Side::Bid => &self.bids,
         };
-        side.get_best_order()
+        side.get_price_levels(levels, current_epoch)
     }
 
     /// Inserts a limit order into the given side of the order book.
     /// Panics if the order is priced such that it would constitute a market order.
-    pub fn insert_limit_order(&mut self, order: &LimitOrder) {
+    pub fn insert_limit_order(&mut self, order: &LimitOrder, current_epoch: u64) {
         match order.side {
             Side::Ask => {
-                let best_bid_price = self.bids.get_best_price();
+                let best_bid_price = self.bids.get_best_price(current_epoch);
                 assert!(
                     best_bid_price.is_none() || order.price > best_bid_price.unwrap(),
                     "Order would be a market order"
@@ -193,7 +326,7 @@ impl OrderBook {
                 self.asks.insert_limit_order(order);
             }
             Side::Bid => {
-                let best_ask_price = self.asks.get_best_price();
+                let best_ask_price = self.asks.get_best_price(current_epoch);
                 assert!(
                     best_ask_price.is_none() || order.price < best_ask_price.unwrap(),
                     "Order would be a market order"
@@ -213,6 +346,74 @@ impl OrderBook {
     }
 }
 
+/// Represents a single fill of a limit order by a market order.
+#[derive(sbor::TypeId, sbor::Encode, sbor::Decode, sbor::Describe, Clone, Debug, PartialEq)]
+pub struct Trade {
+    /// The price of the filled limit order
+    pub price: Decimal,
+
+    /// The filled quantity in units of the base resource, irrespective of the side of the limit order
+    pub quantity: Decimal,
+
+    /// The epoch in which the trade happened
+    pub epoch: u64,
+}
+
+/// A bounded ring buffer holding the most recent trades of a trading pair.
+#[derive(sbor::TypeId, sbor::Encode, sbor::Decode, sbor::Describe, Debug)]
+pub(crate) struct TradeHistory {
+    /// The maximum number of trades that are kept
+    capacity: u32,
+
+    /// The recorded trades. Once the capacity is reached, the oldest trade is overwritten.
+    trades: Vec<Trade>,
+
+    /// The index in trades where the next trade will be recorded
+    next_index: u32,
+}
+
+impl TradeHistory {
+    /// Creates a new empty trade history keeping at most the given number of trades
+    /// Panics if capacity is zero
+    pub fn new(capacity: u32) -> Self {
+        assert!(capacity > 0, "Parameter capacity must be > zero");
+        Self {
+            capacity,
+            trades: Vec::new(),
+            next_index: 0,
+        }
+    }
+
+    /// Records the given trade, overwriting the oldest trade if the capacity is reached
+    pub fn record(&mut self, trade: Trade) {
+        let index = self.next_index as usize;
+        if index < self.trades.len() {
+            self.trades[index] = trade;
+        } else {
+            self.trades.push(trade);
+        }
+        self.next_index = (self.next_index + 1) % self.capacity;
+    }
+
+    /// Returns the most recent trade or None if no trade has been recorded yet
+    pub fn last(&self) -> Option<&Trade> {
+        if self.trades.is_empty() {
+            return None;
+        }
+        let index = (self.next_index + self.capacity - 1) % self.capacity;
+        self.trades.get(index as usize)
+    }
+
+    /// Returns all recorded trades, starting with the most recent one
+    pub fn recent(&self) -> Vec<Trade> {
+        let len = self.trades.len();
+        let next_index = self.next_index as usize;
+        (1..=len)
+            .map(|offset| self.trades[(next_index + len - offset) % len].clone())
+            .collect()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -260,4 +461,162 @@ mod test {
             (-1).into(),
         );
     }
+
+    #[test]
+    fn test_calculate_close_amounts_after_claim() {
+        let mut order = LimitOrder::new(
+            NonFungibleKey::from_str("1234").unwrap(),
+            Side::Ask,
+            10.into(),
+            100.into(),
+        );
+        order.fill(40.into());
+        assert_eq!(order.calculate_close_amounts(), (60.into(), 400.into()));
+
+        order.claim();
+        assert_eq!(order.calculate_close_amounts(), (60.into(), 0.into()));
+
+        order.fill(10.into());
+        assert_eq!(order.calculate_close_amounts(), (50.into(), 100.into()));
+    }
+
+    #[test]
+    fn test_calculate_close_amounts_after_cancel() {
+        let mut order = LimitOrder::new(
+            NonFungibleKey::from_str("1234").unwrap(),
+            Side::Bid,
+            10.into(),
+            100.into(),
+        );
+        order.fill(40.into());
+        order.cancel();
+        assert!(!order.is_open());
+        assert_eq!(order.calculate_close_amounts(), (0.into(), 4.into()));
+
+        order.claim();
+        assert_eq!(order.calculate_close_amounts(), (0.into(), 0.into()));
+    }
+
+    #[test]
+    #[should_panic(expected = "The order has already been canceled")]
+    fn test_cancel_panics_on_canceled_order() {
+        let mut order = LimitOrder::new(
+            NonFungibleKey::from_str("1234").unwrap(),
+            Side::Ask,
+            1.into(),
+            1.into(),
+        );
+        order.cancel();
+        order.cancel();
+    }
+
+    #[test]
+    fn test_expired_orders_are_purged_lazily() {
+        let mut order_book = OrderBook::new();
+        let expiring = LimitOrder::new(
+            NonFungibleKey::from_str("1234").unwrap(),
+            Side::Ask,
+            1.into(),
+            1.into(),
+        )
+        .with_expiry(10);
+        let good_til_canceled = LimitOrder::new(
+            NonFungibleKey::from_str("5678").unwrap(),
+            Side::Ask,
+            2.into(),
+            1.into(),
+        );
+        order_book.insert_limit_order(&expiring, 0);
+        order_book.insert_limit_order(&good_til_canceled, 0);
+
+        // The order can be filled until and including its expiry epoch
+        assert!(!expiring.is_expired(10));
+        assert_eq!(
+            order_book.get_best_order(Side::Ask, 10).unwrap().to_vec(),
+            expiring.order_key.to_vec()
+        );
+
+        // Afterwards it is purged and the next order becomes the best one
+        assert!(expiring.is_expired(11));
+        assert_eq!(
+            order_book.get_best_order(Side::Ask, 11).unwrap().to_vec(),
+            good_til_canceled.order_key.to_vec()
+        );
+
+        // Removing a purged order does nothing
+        order_book.remove_limit_order(&expiring);
+        order_book.remove_limit_order(&good_til_canceled);
+        assert!(order_book.get_best_order(Side::Ask, 11).is_none());
+    }
+
+    #[test]
+    fn test_expired_orders_do_not_block_insert() {
+        let mut order_book = OrderBook::new();
+        let expiring_bid = LimitOrder::new(
+            NonFungibleKey::from_str("1234").unwrap(),
+            Side::Bid,
+            10.into(),
+            1.into(),
+        )
+        .with_expiry(10);
+        order_book.insert_limit_order(&expiring_bid, 0);
+
+        // An ask below the expired bid is not a market order anymore
+        let ask = LimitOrder::new(
+            NonFungibleKey::from_str("5678").unwrap(),
+            Side::Ask,
+            5.into(),
+            1.into(),
+        );
+        order_book.insert_limit_order(&ask, 11);
+        assert!(order_book.get_best_order(Side::Bid, 11).is_none());
+    }
+
+    #[test]
+    fn test_price_levels_skip_expired_orders() {
+        let mut order_book = OrderBook::new();
+        for (key, price, expiry) in vec![("01", 10, None), ("02", 10, Some(5)), ("03", 8, Some(5)), ("04", 5, None)] {
+            let mut order = LimitOrder::new(
+                NonFungibleKey::from_str(key).unwrap(),
+                Side::Bid,
+                price.into(),
+                1.into(),
+            );
+            if let Some(expiry) = expiry {
+                order = order.with_expiry(expiry);
+            }
+            order_book.insert_limit_order(&order, 0);
+        }
+
+        // Bids start with the highest price
+        let price_levels = order_book.get_price_levels(Side::Bid, 10, 0);
+        let prices: Vec<Decimal> = price_levels.iter().map(|(price, _)| *price).collect();
+        assert_eq!(prices, vec![10.into(), 8.into(), 5.into()]);
+        assert_eq!(price_levels[0].1.len(), 2);
+
+        // The level @8 only holds an expired order
+        let price_levels = order_book.get_price_levels(Side::Bid, 2, 6);
+        let prices: Vec<Decimal> = price_levels.iter().map(|(price, _)| *price).collect();
+        assert_eq!(prices, vec![10.into(), 5.into()]);
+        assert_eq!(price_levels[0].1.len(), 1);
+    }
+
+    #[test]
+    fn test_trade_history_is_bounded() {
+        let mut trade_history = TradeHistory::new(3);
+        assert!(trade_history.last().is_none());
+        assert!(trade_history.recent().is_empty());
+
+        for epoch in 1..=5 {
+            trade_history.record(Trade {
+                price: epoch.into(),
+                quantity: 1.into(),
+                epoch,
+            });
+        }
+
+        assert_eq!(trade_history.last().unwrap().epoch, 5);
+        let epochs: Vec<u64> = trade_history.recent().iter().map(|trade| trade.epoch).collect();
+        assert_eq!(epochs, vec![5, 4, 3]);
+    }
 }
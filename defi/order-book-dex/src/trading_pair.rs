@@ -4,6 +4,9 @@ use scrypto::prelude::*;
 
 use crate::model::*;
 
+/// The number of recent trades that are kept by a trading pair
+const TRADE_HISTORY_CAPACITY: u32 = 100;
+
 blueprint! {
 
     /// This component represents a trading pair like e.g. XRD/rUSD. It can be used by traders to
@@ -23,14 +26,44 @@ blueprint! {
 
         /// The resources that market makers have deposited into this component by creating limit bid orders
         quote_funds: Vault,
+
+        /// The fee rate charged to market makers on the resources they receive when their limit orders are filled
+        maker_fee: Decimal,
+
+        /// The fee rate charged to market takers on the resources they receive from a market order
+        taker_fee: Decimal,
+
+        /// The fees that have been collected in the base resource
+        base_fees: Vault,
+
+        /// The fees that have been collected in the quote resource
+        quote_fees: Vault,
+
+        /// The badge that is required to withdraw the collected fees
+        admin_badge: ResourceDef,
+
+        /// The most recent fills of limit orders
+        trade_history: TradeHistory,
     }
 
     impl TradingPair {
         /// Instantiates a new TradingPair component for the given base_resource and quote_resource.
-        /// To create a trading pair XRD/rUSD, one would issue a call like this: `instantiate(xrd_address, rusd_address)`
-        pub fn instantiate(base_resource: ResourceDef, quote_resource: ResourceDef) -> Component {
+        /// To create a trading pair XRD/rUSD, one would issue a call like this: `instantiate(xrd_address, rusd_address, ...)`
+        /// The maker_fee and taker_fee are rates (e.g. 0.001 for 0.1%) that are charged on the resources received by
+        /// makers and takers. The collected fees can be withdrawn using the given admin_badge.
+        ///
+        /// Panics if a fee rate is not in the range [0, 1)
+        pub fn instantiate(
+            base_resource: ResourceDef,
+            quote_resource: ResourceDef,
+            maker_fee: Decimal,
+            taker_fee: Decimal,
+            admin_badge: ResourceDef,
+        ) -> Component {
             assert_is_fungible(&base_resource);
             assert_is_fungible(&quote_resource);
+            assert_is_valid_fee(maker_fee);
+            assert_is_valid_fee(taker_fee);
 
             let minter =
                 ResourceBuilder::new_fungible(DIVISIBILITY_NONE).initial_supply_fungible(1);
@@ -54,8 +87,14 @@ blueprint! {
                 minter: Vault::with_bucket(minter),
                 order_resource,
                 order_book: OrderBook::new(),
-                base_funds: Vault::new(base_resource),
-                quote_funds: Vault::new(quote_resource),
+                base_funds: Vault::new(base_resource.clone()),
+                quote_funds: Vault::new(quote_resource.clone()),
+                maker_fee,
+                taker_fee,
+                base_fees: Vault::new(base_resource),
+                quote_fees: Vault::new(quote_resource),
+                admin_badge,
+                trade_history: TradeHistory::new(TRADE_HISTORY_CAPACITY),
             }
             .instantiate()
         }
@@ -71,34 +110,52 @@ blueprint! {
         /// Panics if the order would be a market order. Never executes directly against existing market orders.
         /// Panics if price is <= 0
         pub fn new_limit_order(&mut self, funds: Bucket, price: Decimal) -> Bucket {
-            // Determine the side of the order
-            let side = self.get_order_side(&funds);
-            // Generate a new random order key
-            let order_key = NonFungibleKey::from(Uuid::generate());
-            // Create a new limit order object. This will check that the price is not <= 0
-            let order = LimitOrder::new(order_key.clone(), side, price, funds.amount());
-            // Insert the limit order into the order book. This panics if the order would be a market order.
-            // The order book will only store a reference to the order (the order key). The order NFR will be given to the
-            // user at the end of this method.
-            self.order_book.insert_limit_order(&order);
+            self.create_limit_order(funds, price, None)
+        }
 
-            // Store the user supplied funds in the correct vault
-            match side {
-                Side::Ask => self.base_funds.put(funds),
-                Side::Bid => self.quote_funds.put(funds),
-            }
+        /// Creates a new good-til-epoch limit order. It works like `new_limit_order`, but the order can only be filled
+        /// until (and including) the given expiry_epoch. Afterwards it is skipped by market orders and lazily purged
+        /// from the order book. The unfilled funds of an expired order can be redeemed by closing or canceling it.
+        ///
+        /// Panics if the expiry_epoch has already passed.
+        pub fn new_limit_order_until(&mut self, funds: Bucket, price: Decimal, expiry_epoch: u64) -> Bucket {
+            assert!(
+                expiry_epoch >= Context::current_epoch(),
+                "The expiry epoch has already passed"
+            );
+            self.create_limit_order(funds, price, Some(expiry_epoch))
+        }
 
-            // Mint a new NFR representing the order and give it to the user
-            self.minter.authorize(|auth| {
-                self.order_resource
-                    .mint_non_fungible(&order_key, order, auth)
-            })
+        /// Creates a new limit order that is never placed in the order book. It is executed immediately against existing
+        /// limit orders, like a market order, but only against orders with a price that is equal to or better than the
+        /// given price. Whatever cannot be filled immediately is canceled.
+        ///
+        /// Returns two buckets with
+        /// 1. The funds that could not be filled.
+        /// 2. The traded funds that are received in exchange for the supplied funds, minus the taker fee.
+        pub fn new_immediate_or_cancel_order(&mut self, funds: Bucket, price: Decimal) -> (Bucket, Bucket) {
+            assert!(price.is_positive(), "Parameter price must be > zero");
+            let (remaining_funds, traded_funds, _) = self.execute_market_order(funds, Some(price));
+            (remaining_funds, traded_funds)
+        }
+
+        /// Creates a new limit order that is executed immediately and completely, or not at all. It works like
+        /// `new_immediate_or_cancel_order` but panics if the order cannot be filled completely at the given price or better.
+        ///
+        /// Returns two buckets with
+        /// 1. Any dust that might still exist in the supplied funds bucket after executing the order.
+        /// 2. The traded funds that are received in exchange for the supplied funds, minus the taker fee.
+        pub fn new_fill_or_kill_order(&mut self, funds: Bucket, price: Decimal) -> (Bucket, Bucket) {
+            assert!(price.is_positive(), "Parameter price must be > zero");
+            let (remaining_funds, traded_funds, filled) = self.execute_market_order(funds, Some(price));
+            assert!(filled, "Fill-or-kill order cannot be filled completely");
+            (remaining_funds, traded_funds)
         }
 
         /// Closes the given limit order. If the order has not been filled completely, it is canceled, i.e. removed from the order book.
         /// The two buckets contain
         /// 1. the refund of the provided resource, in case the order has not been filled completely
-        /// 2. the traded/received resource, in case the order has been filled/filled partially
+        /// 2. the traded/received resource, in case the order has been filled/filled partially, minus the maker fee
         pub fn close_limit_order(&mut self, order_bucket: Bucket) -> (Bucket, Bucket) {
             // Make sure the given bucket does indeed contain an order NFR
             assert_eq!(
@@ -110,9 +167,9 @@ blueprint! {
             let order: LimitOrder =
                 order_bucket.get_non_fungible_data(&order_bucket.get_non_fungible_key());
 
-            // If the order has not been filled completely, it still is referenced in the order book so we have to remove it.
-            // If the order has already been filled completely, it will already have been removed from the order book.
-            if order.quantity_filled < order.quantity {
+            // If the order is still open, it still is referenced in the order book so we have to remove it.
+            // If the order has already been filled completely or canceled, it will already have been removed from the order book.
+            if order.is_open() {
                 self.order_book.remove_limit_order(&order);
             }
             // Burn the order NFR. It is no longer needed as the order will no longer exist after this method finishes.
@@ -124,16 +181,92 @@ blueprint! {
             let (refund_amount, traded_amount) = order.calculate_close_amounts();
 
             // Depending on the order side, take the refund and traded resources out of the correct vaults and give them to the user.
-            match order.side {
-                Side::Ask => (
-                    self.base_funds.take(refund_amount),
-                    self.quote_funds.take(traded_amount),
-                ),
-                Side::Bid => (
-                    self.quote_funds.take(refund_amount),
-                    self.base_funds.take(traded_amount),
-                ),
+            (
+                self.take_refund(order.side, refund_amount),
+                self.take_traded(order.side, traded_amount),
+            )
+        }
+
+        /// Cancels the limit order identified by the given order NFR, so that it can no longer be filled.
+        /// The order is removed from the order book but the order NFR remains in the user's possession, so that
+        /// the resources the order has already received can still be claimed via `claim_filled` or `close_limit_order`.
+        ///
+        /// Returns a bucket with the refund of the provided resource that has not been filled yet.
+        ///
+        /// Panics if the order has already been canceled.
+        #[auth(order_resource)]
+        pub fn cancel_limit_order(&mut self) -> Bucket {
+            let order_key = get_single_order_key(&auth);
+            let mut order: LimitOrder = self.order_resource.get_non_fungible_data(&order_key);
+
+            // A completely filled order has already been removed from the order book
+            if order.is_open() {
+                self.order_book.remove_limit_order(&order);
             }
+
+            // The refund has to be calculated before the order is marked as canceled
+            let (refund_amount, _) = order.calculate_close_amounts();
+            order.cancel();
+            let side = order.side;
+
+            self.minter.authorize(|auth| {
+                self.order_resource
+                    .update_non_fungible_data(&order_key, order, auth)
+            });
+
+            self.take_refund(side, refund_amount)
+        }
+
+        /// Claims the resources that the limit order identified by the given order NFR has received so far.
+        /// This can be called any number of times on a live order, each call only returns what has been received
+        /// since the previous claim. The order NFR remains in the user's possession.
+        ///
+        /// Returns a bucket with the traded/received resource, minus the maker fee.
+        #[auth(order_resource)]
+        pub fn claim_filled(&mut self) -> Bucket {
+            let order_key = get_single_order_key(&auth);
+            let mut order: LimitOrder = self.order_resource.get_non_fungible_data(&order_key);
+
+            let (_, traded_amount) = order.calculate_close_amounts();
+            order.claim();
+            let side = order.side;
+
+            self.minter.authorize(|auth| {
+                self.order_resource
+                    .update_non_fungible_data(&order_key, order, auth)
+            });
+
+            self.take_traded(side, traded_amount)
+        }
+
+        /// Creates a new limit order with the given optional expiry epoch. See `new_limit_order`.
+        fn create_limit_order(&mut self, funds: Bucket, price: Decimal, expiry_epoch: Option<u64>) -> Bucket {
+            // Determine the side of the order
+            let side = self.get_order_side(&funds);
+            // Generate a new random order key
+            let order_key = NonFungibleKey::from(Uuid::generate());
+            // Create a new limit order object. This will check that the price is not <= 0
+            let mut order = LimitOrder::new(order_key.clone(), side, price, funds.amount());
+            if let Some(expiry_epoch) = expiry_epoch {
+                order = order.with_expiry(expiry_epoch);
+            }
+            // Insert the limit order into the order book. This panics if the order would be a market order.
+            // The order book will only store a reference to the order (the order key). The order NFR will be given to the
+            // user at the end of this method.
+            self.order_book
+                .insert_limit_order(&order, Context::current_epoch());
+
+            // Store the user supplied funds in the correct vault
+            match side {
+                Side::Ask => self.base_funds.put(funds),
+                Side::Bid => self.quote_funds.put(funds),
+            }
+
+            // Mint a new NFR representing the order and give it to the user
+            self.minter.authorize(|auth| {
+                self.order_resource
+                    .mint_non_fungible(&order_key, order, auth)
+            })
         }
 
         /// Creates a new market order that is executed directly against existing limit orders.
@@ -147,10 +280,24 @@ blueprint! {
         ///
         /// Returns two buckets with
         /// 1. Any dust that might still exist in the supplied funds bucket after executing the market order.
-        /// 2. The traded funds that are received in exchange for the supplied funds.
+        /// 2. The traded funds that are received in exchange for the supplied funds, minus the taker fee.
         ///
         /// Panics if the order cannot be filled by existing limit orders.
-        pub fn new_market_order(&mut self, mut funds: Bucket) -> (Bucket, Bucket) {
+        pub fn new_market_order(&mut self, funds: Bucket) -> (Bucket, Bucket) {
+            let (remaining_funds, traded_funds, _) = self.execute_market_order(funds, None);
+            (remaining_funds, traded_funds)
+        }
+
+        /// Executes the given funds against existing limit orders. If a price_limit is given, only limit orders with a
+        /// price equal to or better than the limit are filled and the execution stops when no such orders are left.
+        /// Without a price_limit, this panics if the funds cannot be filled completely.
+        /// Expired limit orders are never filled.
+        ///
+        /// Returns 1) the remaining funds, 2) the traded funds minus the taker fee and 3) whether the funds have been
+        /// filled completely.
+        fn execute_market_order(&mut self, mut funds: Bucket, price_limit: Option<Decimal>) -> (Bucket, Bucket, bool) {
+            let current_epoch = Context::current_epoch();
+
             // Infer the side of the order
             let market_order_side = self.get_order_side(&funds);
             let limit_order_side = market_order_side.opposite();
@@ -167,17 +314,39 @@ blueprint! {
             // This may fail in a low liquidity situation where there are too few funds on the limit
             // order side to fill the market order.
             let mut last_price = Decimal(1i128);
+            let mut filled = true;
             while !is_almost_zero(funds.amount(), last_price) {
                 // Get the current best limit order from the order book (this only returns the order key).
-                // Panic if there are no more limit orders that can be matched to the market order
-                let limit_order_key = self.order_book.get_best_order(limit_order_side)
-                    .expect("Insufficient liquidity: no limit orders found that can be matched to the market order")
-                    .clone();
+                // Expired limit orders are purged by the order book and never returned.
+                // Panic if there are no more limit orders that can be matched to the market order, unless the order has a price limit.
+                let limit_order_key = match self.order_book.get_best_order(limit_order_side, current_epoch) {
+                    Some(limit_order_key) => limit_order_key,
+                    None => {
+                        assert!(
+                            price_limit.is_some(),
+                            "Insufficient liquidity: no limit orders found that can be matched to the market order"
+                        );
+                        filled = false;
+                        break;
+                    }
+                };
 
                 // Using the order key, load the data for the limit order
                 let mut limit_order: LimitOrder =
                     self.order_resource.get_non_fungible_data(&limit_order_key);
 
+                // Stop if the best limit order is priced worse than the price limit
+                if let Some(price_limit) = price_limit {
+                    let acceptable = match limit_order_side {
+                        Side::Ask => limit_order.price <= price_limit,
+                        Side::Bid => limit_order.price >= price_limit,
+                    };
+                    if !acceptable {
+                        filled = false;
+                        break;
+                    }
+                }
+
                 // Save the limit order's price as the last known price
                 last_price = limit_order.price;
 
@@ -213,6 +382,17 @@ blueprint! {
                     }
                 }
 
+                // Record the fill in the trade history, always in units of the base resource
+                let base_quantity = match limit_order_side {
+                    Side::Ask => fill_quantity,
+                    Side::Bid => fill_quantity / limit_order.price,
+                };
+                self.trade_history.record(Trade {
+                    price: limit_order.price,
+                    quantity: base_quantity,
+                    epoch: current_epoch,
+                });
+
                 // Check if the limit order has been filled completely.
                 // If so, remove it from the order book.
                 // The limit order NFR representing the limit order remains in the user's possession.
@@ -230,9 +410,116 @@ blueprint! {
                 });
             }
 
+            // Take the taker fee from the traded funds. It is charged once on the total, so the fee is exact
+            // irrespective of how many limit orders and price levels were needed to fill the market order.
+            let taker_fee = funds_to_return.amount() * self.taker_fee;
+            match market_order_side {
+                Side::Ask => self.quote_fees.put(funds_to_return.take(taker_fee)),
+                Side::Bid => self.base_fees.put(funds_to_return.take(taker_fee)),
+            }
+
             // Finally return to the user 1) the unspent funds of the market order
             // and 2) the traded funds coming from the limit order(s)
-            (funds, funds_to_return)
+            (funds, funds_to_return, filled)
+        }
+
+        /// Withdraws all fees that have been collected by this trading pair.
+        /// Returns two buckets with 1) the fees collected in the base resource and 2) the fees collected in the quote resource.
+        #[auth(admin_badge)]
+        pub fn withdraw_fees(&mut self) -> (Bucket, Bucket) {
+            (self.base_fees.take_all(), self.quote_fees.take_all())
+        }
+
+        /// Returns the maker and the taker fee rates of this trading pair
+        pub fn get_fees(&self) -> (Decimal, Decimal) {
+            (self.maker_fee, self.taker_fee)
+        }
+
+        /// Returns the aggregated depth of the order book for up to the given number of price levels on each side.
+        /// The two vectors hold the ask and the bid price levels, each starting with the best price.
+        /// Every price level is a tuple of the price and the total unfilled quantity at that price in units of the base resource.
+        /// Expired orders are not included.
+        pub fn get_depth(&self, levels: u32) -> (Vec<(Decimal, Decimal)>, Vec<(Decimal, Decimal)>) {
+            (
+                self.get_side_depth(Side::Ask, levels),
+                self.get_side_depth(Side::Bid, levels),
+            )
+        }
+
+        /// Returns the best bid and the best ask price. A price is None if there is no order on that side.
+        pub fn get_best_bid_ask(&self) -> (Option<Decimal>, Option<Decimal>) {
+            (self.get_best_price(Side::Bid), self.get_best_price(Side::Ask))
+        }
+
+        /// Returns the difference between the best ask and the best bid price or None if one of the sides is empty.
+        pub fn get_spread(&self) -> Option<Decimal> {
+            match self.get_best_bid_ask() {
+                (Some(best_bid), Some(best_ask)) => Some(best_ask - best_bid),
+                _ => None,
+            }
+        }
+
+        /// Returns the price of the most recent trade or None if no trade has happened yet.
+        pub fn get_last_trade_price(&self) -> Option<Decimal> {
+            self.trade_history.last().map(|trade| trade.price)
+        }
+
+        /// Returns the most recent trades, starting with the latest one. At most the last 100 trades are kept.
+        pub fn get_recent_trades(&self) -> Vec<Trade> {
+            self.trade_history.recent()
+        }
+
+        /// Returns the price levels of the given side with the total unfilled quantity in units of the base resource.
+        fn get_side_depth(&self, side: Side, levels: u32) -> Vec<(Decimal, Decimal)> {
+            self.order_book
+                .get_price_levels(side, levels as usize, Context::current_epoch())
+                .into_iter()
+                .map(|(price, order_keys)| {
+                    let mut quantity = Decimal::zero();
+                    for order_key in order_keys {
+                        let order: LimitOrder = self.order_resource.get_non_fungible_data(&order_key);
+                        quantity += order.quantity - order.quantity_filled;
+                    }
+                    match side {
+                        Side::Ask => (price, quantity),
+                        Side::Bid => (price, quantity / price),
+                    }
+                })
+                .collect()
+        }
+
+        /// Returns the best price of the given side that is not expired or None if there is no such order.
+        fn get_best_price(&self, side: Side) -> Option<Decimal> {
+            self.order_book
+                .get_price_levels(side, 1, Context::current_epoch())
+                .first()
+                .map(|(price, _)| *price)
+        }
+
+        /// Takes the refund of a limit order on the given side out of the vault holding the funds provided by makers.
+        fn take_refund(&mut self, side: Side, refund_amount: Decimal) -> Bucket {
+            match side {
+                Side::Ask => self.base_funds.take(refund_amount),
+                Side::Bid => self.quote_funds.take(refund_amount),
+            }
+        }
+
+        /// Takes the traded resources of a limit order on the given side out of the correct vault.
+        /// The maker fee is taken from the traded resources and kept in the fee vault.
+        fn take_traded(&mut self, side: Side, traded_amount: Decimal) -> Bucket {
+            let maker_fee = traded_amount * self.maker_fee;
+            match side {
+                Side::Ask => {
+                    let traded = self.quote_funds.take(traded_amount);
+                    self.quote_fees.put(traded.take(maker_fee));
+                    traded
+                }
+                Side::Bid => {
+                    let traded = self.base_funds.take(traded_amount);
+                    self.base_fees.put(traded.take(maker_fee));
+                    traded
+                }
+            }
         }
 
         /// Infers the side of the order from the resource contained in the given bucket.
@@ -261,6 +548,25 @@ fn assert_is_fungible(resource: &ResourceDef) {
     }
 }
 
+/// Returns the key of the single order NFR in the given bucket ref.
+/// Panics if the bucket ref does not contain exactly one order.
+fn get_single_order_key(order_ref: &BucketRef) -> NonFungibleKey {
+    assert_eq!(
+        order_ref.amount(),
+        Decimal::one(),
+        "Exactly one order must be supplied"
+    );
+    order_ref.get_non_fungible_key()
+}
+
+fn assert_is_valid_fee(fee: Decimal) {
+    assert!(
+        fee >= Decimal::zero() && fee < Decimal::one(),
+        "Invalid fee: {} is not in the range [0, 1)",
+        fee
+    );
+}
+
 /// Determines if the given amount is as good as zero with respect to the given price.
 /// Essentially, this is used to check whether enough funds remain in a market order
 /// in order to at least partially fill the next limit order.
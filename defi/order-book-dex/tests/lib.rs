@@ -1,4 +1,6 @@
+use order_book_dex::Trade;
 use radix_engine::ledger::*;
+use sbor::Decode;
 use scrypto::prelude::*;
 use scrypto_unit::*;
 
@@ -240,10 +242,403 @@ fn test_bid_order_fractional_numbers() {
     dex_fixture.assert_resource_amount("maker", dex_fixture.quote_resource, Decimal::from("999980.0000000000002"),);
 }
 
-fn setup_fixture<L: SubstateStore>(mut env: TestEnv<L>) -> DexFixture<L> {
+#[test]
+fn test_cancel_partially_filled_order_and_claim() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let env = TestEnv::new(&mut ledger);
+    let mut dex_fixture = setup_fixture(env);
+
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    let order_key = dex_fixture.new_limit_order(
+        FungibleBucket(100_000.into(), dex_fixture.base_resource),
+        10.into(),
+    );
+
+    // The order is filled to 50%
+    dex_fixture.env.acting_as(dex_fixture.user_taker.name);
+    dex_fixture.new_market_order(FungibleBucket(500_000.into(), dex_fixture.quote_resource));
+
+    // The maker claims the received rUSD while the order stays live
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    dex_fixture.claim_filled(NonFungibleBucket(order_key.clone(), dex_fixture.order_resource));
+    dex_fixture.assert_resource_amount("maker", dex_fixture.quote_resource, 1_500_000.into());
+    dex_fixture.claim_filled(NonFungibleBucket(order_key.clone(), dex_fixture.order_resource));
+    dex_fixture.assert_resource_amount("maker", dex_fixture.quote_resource, 1_500_000.into());
+
+    // The order is filled to 75% and claimed again
+    dex_fixture.env.acting_as(dex_fixture.user_taker.name);
+    dex_fixture.new_market_order(FungibleBucket(250_000.into(), dex_fixture.quote_resource));
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    dex_fixture.claim_filled(NonFungibleBucket(order_key.clone(), dex_fixture.order_resource));
+    dex_fixture.assert_resource_amount("maker", dex_fixture.quote_resource, 1_750_000.into());
+
+    // Canceling refunds the unfilled XRD and keeps the NFR
+    dex_fixture.cancel_limit_order(NonFungibleBucket(order_key.clone(), dex_fixture.order_resource));
+    dex_fixture.assert_resource_amount("maker", dex_fixture.base_resource, 925_000.into());
+    let order_keys = dex_fixture
+        .env
+        .get_non_fungible_keys_for_rd(dex_fixture.user_maker.account, dex_fixture.order_resource);
+    assert_eq!(order_keys.len(), 1);
+
+    // The canceled order can no longer be filled
+    dex_fixture.env.acting_as(dex_fixture.user_taker.name);
+    assert!(!dex_fixture.try_new_market_order(FungibleBucket(10.into(), dex_fixture.quote_resource)));
+
+    // Closing the canceled order burns the NFR without paying out anything twice
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    dex_fixture.close_limit_order(NonFungibleBucket(order_key, dex_fixture.order_resource));
+    dex_fixture.assert_resource_amount("maker", dex_fixture.base_resource, 925_000.into());
+    dex_fixture.assert_resource_amount("maker", dex_fixture.quote_resource, 1_750_000.into());
+}
+
+#[test]
+fn test_expired_order_is_skipped_and_refundable() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let env = TestEnv::new(&mut ledger);
+    let mut dex_fixture = setup_fixture(env);
+
+    // The maker asks @10 until epoch 5 and @20 without expiry
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    let expiring_key = dex_fixture.new_limit_order_until(
+        FungibleBucket(100_000.into(), dex_fixture.base_resource),
+        10.into(),
+        5,
+    );
+    dex_fixture.new_limit_order(
+        FungibleBucket(10_000.into(), dex_fixture.base_resource),
+        20.into(),
+    );
+    dex_fixture.assert_resource_amount("maker", dex_fixture.base_resource, 890_000.into());
+
+    // The order @10 is filled to 50% in its expiry epoch
+    dex_fixture.set_current_epoch(5);
+    dex_fixture.env.acting_as(dex_fixture.user_taker.name);
+    dex_fixture.new_market_order(FungibleBucket(500_000.into(), dex_fixture.quote_resource));
+    dex_fixture.assert_resource_amount("taker", dex_fixture.base_resource, 1_050_000.into());
+
+    // Afterwards it is skipped, the market order is filled @20
+    dex_fixture.set_current_epoch(6);
+    dex_fixture.new_market_order(FungibleBucket(200_000.into(), dex_fixture.quote_resource));
+    dex_fixture.assert_resource_amount("taker", dex_fixture.base_resource, 1_060_000.into());
+    dex_fixture.assert_resource_amount("taker", dex_fixture.quote_resource, 300_000.into());
+
+    // Only the expired order is left, so nothing is filled and the funds are returned
+    assert!(!dex_fixture.try_new_market_order(FungibleBucket(10.into(), dex_fixture.quote_resource)));
+    assert!(dex_fixture.new_time_in_force_order(
+        "new_immediate_or_cancel_order",
+        FungibleBucket(100_000.into(), dex_fixture.quote_resource),
+        10.into()
+    ));
+    dex_fixture.assert_resource_amount("taker", dex_fixture.base_resource, 1_060_000.into());
+    dex_fixture.assert_resource_amount("taker", dex_fixture.quote_resource, 300_000.into());
+
+    // The maker recovers the unfilled XRD and the received rUSD of the expired order
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    dex_fixture.cancel_limit_order(NonFungibleBucket(expiring_key.clone(), dex_fixture.order_resource));
+    dex_fixture.assert_resource_amount("maker", dex_fixture.base_resource, 940_000.into());
+    dex_fixture.close_limit_order(NonFungibleBucket(expiring_key, dex_fixture.order_resource));
+    dex_fixture.assert_resource_amount("maker", dex_fixture.base_resource, 940_000.into());
+    dex_fixture.assert_resource_amount("maker", dex_fixture.quote_resource, 1_500_000.into());
+}
+
+#[test]
+fn test_immediate_or_cancel_and_fill_or_kill_orders() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let env = TestEnv::new(&mut ledger);
+    let mut dex_fixture = setup_fixture(env);
+
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    dex_fixture.new_limit_order(
+        FungibleBucket(50_000.into(), dex_fixture.base_resource),
+        5.into(),
+    );
+    dex_fixture.new_limit_order(
+        FungibleBucket(50_000.into(), dex_fixture.base_resource),
+        10.into(),
+    );
+
+    // Only the order @5 is filled, the rest of the rUSD is returned
+    dex_fixture.env.acting_as(dex_fixture.user_taker.name);
+    assert!(dex_fixture.new_time_in_force_order(
+        "new_immediate_or_cancel_order",
+        FungibleBucket(700_000.into(), dex_fixture.quote_resource),
+        5.into()
+    ));
+    dex_fixture.assert_resource_amount("taker", dex_fixture.base_resource, 1_050_000.into());
+    dex_fixture.assert_resource_amount("taker", dex_fixture.quote_resource, 750_000.into());
+
+    // No order is left @5, so the fill-or-kill order fails without trading anything
+    assert!(!dex_fixture.new_time_in_force_order(
+        "new_fill_or_kill_order",
+        FungibleBucket(100.into(), dex_fixture.quote_resource),
+        5.into()
+    ));
+    dex_fixture.assert_resource_amount("taker", dex_fixture.quote_resource, 750_000.into());
+
+    // The order @10 can fill the fill-or-kill order completely
+    assert!(dex_fixture.new_time_in_force_order(
+        "new_fill_or_kill_order",
+        FungibleBucket(450_000.into(), dex_fixture.quote_resource),
+        10.into()
+    ));
+    dex_fixture.assert_resource_amount("taker", dex_fixture.base_resource, 1_095_000.into());
+    dex_fixture.assert_resource_amount("taker", dex_fixture.quote_resource, 300_000.into());
+}
+
+#[test]
+fn test_order_book_queries() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let env = TestEnv::new(&mut ledger);
+    let mut dex_fixture = setup_fixture(env);
+
+    // The queries also work on an empty order book
+    dex_fixture.env.acting_as(dex_fixture.user_taker.name);
+    let depth: (Vec<(Decimal, Decimal)>, Vec<(Decimal, Decimal)>) = dex_fixture.call_query("get_depth", vec!["5".to_owned()]);
+    assert_eq!(depth, (vec![], vec![]));
+    let best_bid_ask: (Option<Decimal>, Option<Decimal>) = dex_fixture.call_query("get_best_bid_ask", vec![]);
+    assert_eq!(best_bid_ask, (None, None));
+    let spread: Option<Decimal> = dex_fixture.call_query("get_spread", vec![]);
+    assert_eq!(spread, None);
+    let last_trade_price: Option<Decimal> = dex_fixture.call_query("get_last_trade_price", vec![]);
+    assert_eq!(last_trade_price, None);
+    let recent_trades: Vec<Trade> = dex_fixture.call_query("get_recent_trades", vec![]);
+    assert!(recent_trades.is_empty());
+
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    dex_fixture.new_limit_order(
+        FungibleBucket(50_000.into(), dex_fixture.base_resource),
+        10.into(),
+    );
+    dex_fixture.new_limit_order(
+        FungibleBucket(100_000.into(), dex_fixture.quote_resource),
+        5.into(),
+    );
+
+    // 100,000 rUSD buy 10,000 XRD @10
+    dex_fixture.env.acting_as(dex_fixture.user_taker.name);
+    dex_fixture.new_market_order(FungibleBucket(100_000.into(), dex_fixture.quote_resource));
+
+    // The bid of 100,000 rUSD @5 is 20,000 XRD deep
+    let depth: (Vec<(Decimal, Decimal)>, Vec<(Decimal, Decimal)>) = dex_fixture.call_query("get_depth", vec!["5".to_owned()]);
+    assert_eq!(depth, (vec![(10.into(), 40_000.into())], vec![(5.into(), 20_000.into())]));
+    let best_bid_ask: (Option<Decimal>, Option<Decimal>) = dex_fixture.call_query("get_best_bid_ask", vec![]);
+    assert_eq!(best_bid_ask, (Some(5.into()), Some(10.into())));
+    let spread: Option<Decimal> = dex_fixture.call_query("get_spread", vec![]);
+    assert_eq!(spread, Some(5.into()));
+    let last_trade_price: Option<Decimal> = dex_fixture.call_query("get_last_trade_price", vec![]);
+    assert_eq!(last_trade_price, Some(10.into()));
+    let recent_trades: Vec<Trade> = dex_fixture.call_query("get_recent_trades", vec![]);
+    assert_eq!(
+        recent_trades,
+        vec![Trade {
+            price: 10.into(),
+            quantity: 10_000.into(),
+            epoch: 0,
+        }]
+    );
+}
+
+#[test]
+fn test_fees_across_price_levels() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let env = TestEnv::new(&mut ledger);
+    // 1% maker fee and 2% taker fee
+    let mut dex_fixture = setup_fixture_with_fees(env, "0.01".into(), "0.02".into());
+
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    // Order 1 is worse than order 2 and will only be filled partially
+    let order1_key = dex_fixture.new_limit_order(
+        FungibleBucket(50_000.into(), dex_fixture.base_resource),
+        10.into(),
+    );
+    let order2_key = dex_fixture.new_limit_order(
+        FungibleBucket(50_000.into(), dex_fixture.base_resource),
+        5.into(),
+    );
+
+    // 700,000 rUSD buy 50,000 XRD @5 and 45,000 XRD @10, the taker fee is 2% of 95,000 XRD
+    dex_fixture.env.acting_as(dex_fixture.user_taker.name);
+    dex_fixture.new_market_order(FungibleBucket(700_000.into(), dex_fixture.quote_resource));
+    dex_fixture.assert_resource_amount("taker", dex_fixture.base_resource, 1_093_100.into());
+    dex_fixture.assert_resource_amount("taker", dex_fixture.quote_resource, 300_000.into());
+
+    // The maker fee is 1% of the 450,000 rUSD and 250,000 rUSD received
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    dex_fixture.close_limit_order(NonFungibleBucket(order1_key, dex_fixture.order_resource));
+    dex_fixture.assert_resource_amount("maker", dex_fixture.base_resource, 905_000.into());
+    dex_fixture.assert_resource_amount("maker", dex_fixture.quote_resource, 1_445_500.into());
+
+    dex_fixture.close_limit_order(NonFungibleBucket(order2_key, dex_fixture.order_resource));
+    dex_fixture.assert_resource_amount("maker", dex_fixture.base_resource, 905_000.into());
+    dex_fixture.assert_resource_amount("maker", dex_fixture.quote_resource, 1_693_000.into());
+
+    // Only the admin can withdraw the fees
+    assert!(!dex_fixture.withdraw_fees());
+    dex_fixture.env.acting_as(dex_fixture.user_admin.name);
+    dex_fixture.assert_resource_amount("admin", dex_fixture.base_resource, 1_000_000.into());
+    dex_fixture.assert_resource_amount("admin", dex_fixture.quote_resource, 0.into());
+    assert!(dex_fixture.withdraw_fees());
+    dex_fixture.assert_resource_amount("admin", dex_fixture.base_resource, 1_001_900.into());
+    dex_fixture.assert_resource_amount("admin", dex_fixture.quote_resource, 7_000.into());
+}
+
+#[test]
+fn test_route_market_order_across_two_trading_pairs() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let env = TestEnv::new(&mut ledger);
+    let mut dex_fixture = setup_fixture(env);
+
+    // The taker holds rBTC which can only be traded against rUSD
+    dex_fixture.env.acting_as(dex_fixture.user_taker.name);
+    let resource_rbtc = dex_fixture.env.create_token(10.into()).address();
+    let (rbtc_pair_component, rbtc_order_resource) =
+        dex_fixture.add_trading_pair(resource_rbtc, dex_fixture.quote_resource);
+
+    // The maker bids rUSD for rBTC and asks rUSD for XRD
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    dex_fixture.new_limit_order_on(
+        rbtc_pair_component,
+        rbtc_order_resource,
+        FungibleBucket(500_000.into(), dex_fixture.quote_resource),
+        50_000.into(),
+    );
+    dex_fixture.new_limit_order(
+        FungibleBucket(100_000.into(), dex_fixture.base_resource),
+        10.into(),
+    );
+
+    // rBTC -> rUSD -> XRD: 5 rBTC are sold for 250,000 rUSD which buy 25,000 XRD
+    dex_fixture.env.acting_as(dex_fixture.user_taker.name);
+    assert!(!dex_fixture.route_market_order(
+        FungibleBucket(5.into(), resource_rbtc),
+        dex_fixture.base_resource,
+        25_001.into()
+    ));
+    assert!(dex_fixture.route_market_order(
+        FungibleBucket(5.into(), resource_rbtc),
+        dex_fixture.base_resource,
+        25_000.into()
+    ));
+    dex_fixture.assert_resource_amount("taker", resource_rbtc, 5.into());
+    dex_fixture.assert_resource_amount("taker", dex_fixture.base_resource, 1_025_000.into());
+    dex_fixture.assert_resource_amount("taker", dex_fixture.quote_resource, 1_000_000.into());
+}
+
+#[test]
+fn test_route_market_order_across_three_trading_pairs() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let env = TestEnv::new(&mut ledger);
+    let mut dex_fixture = setup_fixture(env);
+    let (resource_rbtc, resource_reth) = setup_three_hop_route(&mut dex_fixture);
+
+    // rBTC -> rUSD -> XRD -> rETH: 5 rBTC are sold for 250,000 rUSD which buy 25,000 XRD which buy 250 rETH
+    dex_fixture.env.acting_as(dex_fixture.user_taker.name);
+    assert!(!dex_fixture.route_market_order(
+        FungibleBucket(5.into(), resource_rbtc),
+        resource_reth,
+        251.into()
+    ));
+    assert!(dex_fixture.route_market_order(
+        FungibleBucket(5.into(), resource_rbtc),
+        resource_reth,
+        250.into()
+    ));
+    dex_fixture.assert_resource_amount("taker", resource_rbtc, 5.into());
+    dex_fixture.assert_resource_amount("taker", resource_reth, 250.into());
+    dex_fixture.assert_resource_amount("taker", dex_fixture.base_resource, 1_000_000.into());
+    dex_fixture.assert_resource_amount("taker", dex_fixture.quote_resource, 1_000_000.into());
+}
+
+#[test]
+fn test_route_market_order_beyond_max_hops() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let env = TestEnv::new(&mut ledger);
+    let mut dex_fixture = setup_fixture(env);
+    let (resource_rbtc, resource_reth) = setup_three_hop_route(&mut dex_fixture);
+
+    // rDOT can only be reached from rBTC via a fourth trading pair
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    let resource_rdot = dex_fixture.env.create_token(1_000.into()).address();
+    let (rdot_pair_component, rdot_order_resource) = dex_fixture.add_trading_pair(resource_rdot, resource_reth);
+    dex_fixture.new_limit_order_on(
+        rdot_pair_component,
+        rdot_order_resource,
+        FungibleBucket(1_000.into(), resource_rdot),
+        1.into(),
+    );
+
+    dex_fixture.env.acting_as(dex_fixture.user_taker.name);
+    assert!(!dex_fixture.route_market_order(
+        FungibleBucket(5.into(), resource_rbtc),
+        resource_rdot,
+        0.into()
+    ));
+    dex_fixture.assert_resource_amount("taker", resource_rbtc, 10.into());
+}
+
+#[test]
+fn test_route_market_order_without_route() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let env = TestEnv::new(&mut ledger);
+    let mut dex_fixture = setup_fixture(env);
+
+    // There is no trading pair for rBTC
+    dex_fixture.env.acting_as(dex_fixture.user_taker.name);
+    let resource_rbtc = dex_fixture.env.create_token(10.into()).address();
+    assert!(!dex_fixture.route_market_order(
+        FungibleBucket(5.into(), resource_rbtc),
+        dex_fixture.base_resource,
+        0.into()
+    ));
+    dex_fixture.assert_resource_amount("taker", resource_rbtc, 10.into());
+}
+
+/// Sets up the route rBTC -> rUSD -> XRD -> rETH with enough liquidity for 5 rBTC.
+/// The taker holds 10 rBTC, returns the addresses of rBTC and rETH.
+fn setup_three_hop_route<L: SubstateStore>(dex_fixture: &mut DexFixture<L>) -> (Address, Address) {
+    dex_fixture.env.acting_as(dex_fixture.user_taker.name);
+    let resource_rbtc = dex_fixture.env.create_token(10.into()).address();
+    let (rbtc_pair_component, rbtc_order_resource) =
+        dex_fixture.add_trading_pair(resource_rbtc, dex_fixture.quote_resource);
+
+    dex_fixture.env.acting_as(dex_fixture.user_maker.name);
+    let resource_reth = dex_fixture.env.create_token(1_000.into()).address();
+    let (reth_pair_component, reth_order_resource) =
+        dex_fixture.add_trading_pair(resource_reth, dex_fixture.base_resource);
+
+    // The maker bids rUSD for rBTC, asks rUSD for XRD and asks XRD for rETH
+    dex_fixture.new_limit_order_on(
+        rbtc_pair_component,
+        rbtc_order_resource,
+        FungibleBucket(500_000.into(), dex_fixture.quote_resource),
+        50_000.into(),
+    );
+    dex_fixture.new_limit_order(
+        FungibleBucket(100_000.into(), dex_fixture.base_resource),
+        10.into(),
+    );
+    dex_fixture.new_limit_order_on(
+        reth_pair_component,
+        reth_order_resource,
+        FungibleBucket(1_000.into(), resource_reth),
+        100.into(),
+    );
+
+    (resource_rbtc, resource_reth)
+}
+
+fn setup_fixture<L: SubstateStore>(env: TestEnv<L>) -> DexFixture<L> {
+    setup_fixture_with_fees(env, Decimal::zero(), Decimal::zero())
+}
+
+fn setup_fixture_with_fees<L: SubstateStore>(
+    mut env: TestEnv<L>,
+    maker_fee: Decimal,
+    taker_fee: Decimal,
+) -> DexFixture<L> {
     let maker_name = "maker";
     let taker_name = "taker";
-    let _admin = env.create_user("admin");
+    let admin = env.create_user("admin");
     let maker = env.create_user(maker_name);
     let taker = env.create_user(taker_name);
 
@@ -270,6 +665,8 @@ fn setup_fixture<L: SubstateStore>(mut env: TestEnv<L>) -> DexFixture<L> {
         vec![
             format!("{}", base_resource),
             format!("{}", quote_resource),
+            maker_fee.to_string(),
+            taker_fee.to_string(),
             format!("1,{}", admin_badge),
         ],
     );
@@ -279,7 +676,8 @@ fn setup_fixture<L: SubstateStore>(mut env: TestEnv<L>) -> DexFixture<L> {
 
     DexFixture {
         env,
-        _dex_component: dex_component,
+        dex_component,
+        admin_badge,
         trading_pair_component,
         base_resource,
         quote_resource,
@@ -292,6 +690,10 @@ fn setup_fixture<L: SubstateStore>(mut env: TestEnv<L>) -> DexFixture<L> {
             account: taker.account,
             name: taker_name,
         },
+        user_admin: DexUser {
+            account: admin.account,
+            name: "admin",
+        },
     }
 }
 
@@ -302,34 +704,93 @@ struct DexUser {
 
 struct DexFixture<'a, L: SubstateStore> {
     env: TestEnv<'a, L>,
-    _dex_component: Address,
+    dex_component: Address,
+    admin_badge: Address,
     trading_pair_component: Address,
     base_resource: Address,
     quote_resource: Address,
     order_resource: Address,
     user_maker: DexUser,
     user_taker: DexUser,
+    user_admin: DexUser,
 }
 
 impl<'a, L: SubstateStore> DexFixture<'a, L> {
+    /// Adds a trading pair acting as the admin and returns the addresses of the trading pair component
+    /// and of its order resource
+    fn add_trading_pair(&mut self, base_resource: Address, quote_resource: Address) -> (Address, Address) {
+        let current_user = self.env.current_user.unwrap();
+        self.env.acting_as("admin");
+        let receipt = self.env.call_method(
+            &self.dex_component,
+            "add_trading_pair",
+            vec![
+                format!("{}", base_resource),
+                format!("{}", quote_resource),
+                Decimal::zero().to_string(),
+                Decimal::zero().to_string(),
+                format!("1,{}", self.admin_badge),
+            ],
+        );
+        assert!(receipt.result.is_ok());
+        self.env.current_user = Some(current_user);
+
+        (receipt.component(0).unwrap(), receipt.resource_def(1).unwrap())
+    }
+
     fn new_limit_order(&mut self, funds: FungibleBucket, price: Decimal) -> NonFungibleKey {
+        let trading_pair_component = self.trading_pair_component;
+        let order_resource = self.order_resource;
+        self.new_limit_order_on(trading_pair_component, order_resource, funds, price)
+    }
+
+    fn new_limit_order_on(
+        &mut self,
+        trading_pair_component: Address,
+        order_resource: Address,
+        funds: FungibleBucket,
+        price: Decimal,
+    ) -> NonFungibleKey {
+        self.new_order_on(
+            trading_pair_component,
+            order_resource,
+            "new_limit_order",
+            vec![funds.to_string(), price.to_string()],
+        )
+    }
+
+    fn new_limit_order_until(&mut self, funds: FungibleBucket, price: Decimal, expiry_epoch: u64) -> NonFungibleKey {
+        let trading_pair_component = self.trading_pair_component;
+        let order_resource = self.order_resource;
+        self.new_order_on(
+            trading_pair_component,
+            order_resource,
+            "new_limit_order_until",
+            vec![funds.to_string(), price.to_string(), expiry_epoch.to_string()],
+        )
+    }
+
+    /// Calls a method placing a limit order and returns the key of the order NFR the current user received
+    fn new_order_on(
+        &mut self,
+        trading_pair_component: Address,
+        order_resource: Address,
+        method: &str,
+        args: Vec<String>,
+    ) -> NonFungibleKey {
         let order_keys_before = self.env.get_non_fungible_keys_for_rd(
             self.env.current_user.unwrap().account,
-            self.order_resource,
+            order_resource,
         );
         let order_keys_before: HashSet<NonFungibleKey> =
             order_keys_before.iter().cloned().collect();
 
-        let receipt = self.env.call_method(
-            &self.trading_pair_component,
-            "new_limit_order",
-            vec![funds.to_string(), price.to_string()],
-        );
+        let receipt = self.env.call_method(&trading_pair_component, method, args);
         assert!(receipt.result.is_ok());
 
         let order_keys_now = self.env.get_non_fungible_keys_for_rd(
             self.env.current_user.unwrap().account,
-            self.order_resource,
+            order_resource,
         );
         let order_keys_now: HashSet<NonFungibleKey> = order_keys_now.iter().cloned().collect();
         let new_order_keys: HashSet<NonFungibleKey> = order_keys_now
@@ -341,6 +802,10 @@ impl<'a, L: SubstateStore> DexFixture<'a, L> {
         new_order_keys.into_iter().next().unwrap()
     }
 
+    fn set_current_epoch(&mut self, epoch: u64) {
+        self.env.executor.ledger_mut().set_epoch(epoch);
+    }
+
     fn close_limit_order(&mut self, order: NonFungibleBucket) {
         let receipt = self.env.call_method(
             &self.trading_pair_component,
@@ -352,6 +817,46 @@ impl<'a, L: SubstateStore> DexFixture<'a, L> {
         assert!(receipt.result.is_ok());
     }
 
+    fn cancel_limit_order(&mut self, order: NonFungibleBucket) {
+        let receipt = self.env.call_method(
+            &self.trading_pair_component,
+            "cancel_limit_order",
+            vec![order.to_string()],
+        );
+        println!("{:?}", receipt);
+        assert!(receipt.result.is_ok());
+    }
+
+    fn claim_filled(&mut self, order: NonFungibleBucket) {
+        let receipt = self.env.call_method(
+            &self.trading_pair_component,
+            "claim_filled",
+            vec![order.to_string()],
+        );
+        println!("{:?}", receipt);
+        assert!(receipt.result.is_ok());
+    }
+
+    fn try_new_market_order(&mut self, funds: FungibleBucket) -> bool {
+        let receipt = self.env.call_method(
+            &self.trading_pair_component,
+            "new_market_order",
+            vec![funds.to_string()],
+        );
+        println!("{:?}", receipt);
+        receipt.result.is_ok()
+    }
+
+    fn new_time_in_force_order(&mut self, method: &str, funds: FungibleBucket, price: Decimal) -> bool {
+        let receipt = self.env.call_method(
+            &self.trading_pair_component,
+            method,
+            vec![funds.to_string(), price.to_string()],
+        );
+        println!("{:?}", receipt);
+        receipt.result.is_ok()
+    }
+
     fn new_market_order(&mut self, funds: FungibleBucket) {
         let receipt = self.env.call_method(
             &self.trading_pair_component,
@@ -362,11 +867,49 @@ impl<'a, L: SubstateStore> DexFixture<'a, L> {
         assert!(receipt.result.is_ok());
     }
 
+    fn withdraw_fees(&mut self) -> bool {
+        let receipt = self.env.call_method(
+            &self.trading_pair_component,
+            "withdraw_fees",
+            vec![format!("1,{}", self.admin_badge)],
+        );
+        println!("{:?}", receipt);
+        receipt.result.is_ok()
+    }
+
+    fn route_market_order(
+        &mut self,
+        funds: FungibleBucket,
+        target_resource: Address,
+        min_output: Decimal,
+    ) -> bool {
+        let receipt = self.env.call_method(
+            &self.dex_component,
+            "route_market_order",
+            vec![funds.to_string(), format!("{}", target_resource), min_output.to_string()],
+        );
+        println!("{:?}", receipt);
+        receipt.result.is_ok()
+    }
+
+    fn call_query<T: Decode>(&mut self, method: &str, args: Vec<String>) -> T {
+        let receipt = self.env.call_method(&self.trading_pair_component, method, args);
+        println!("{:?}", receipt);
+        assert!(receipt.result.is_ok());
+        receipt
+            .outputs
+            .iter()
+            .find_map(|output| scrypto_decode(&output.raw).ok())
+            .expect("No output of the expected type")
+    }
+
     fn assert_resource_amount(&mut self, user: &str, resource: Address, expected_amount: Decimal) {
         let account = if user == self.user_maker.name {
             self.user_maker.account
         } else if user == self.user_taker.name {
             self.user_taker.account
+        } else if user == self.user_admin.name {
+            self.user_admin.account
         } else {
             panic!("Invalid user:  {}", user);
         };
@@ -10,12 +10,14 @@ blueprint! {
         
         // Candy Hashmap with name, symbol, price.              
         candy_map: HashMap<Address, (String, String, Decimal)>,        
-        // metaBadge Hashmap with entry fee level, metaCandy amount & Candy address.  
-        badge_map: HashMap<Address, (Decimal, Decimal, Address)>,         
         // Candy Hashmap with accrued fee, metaCandy amount & address.
         meta_map: HashMap<Address, (Decimal, Decimal, Address)>,         
         // metaCandy Hashmap with MetaToken resource adresses. 
         meta: HashMap<Address, MetaToken>,  
+        // Candy Hashmap with cumulative price accumulators.
+        oracle_map: HashMap<Address, PriceOracle>,
+        // TWAP observation window in epochs.
+        twap_window: u64,
         
         // Badge to mint and burn metaCandies.                      
         minter_badge: Vault,         
@@ -44,9 +46,10 @@ blueprint! {
                 collected_xrd: Vault::new(RADIX_TOKEN),
                 candy_vaults: HashMap::new(),
                 candy_map: HashMap::new(),
-                badge_map: HashMap::new(),
                 meta_map: HashMap::new(),
                 meta: HashMap::new(),
+                oracle_map: HashMap::new(),
+                twap_window: 10,
                 minter_badge: Vault::with_bucket(minter_badge),
                 owner_badge: badge_bucket.resource_def(),
                 xrd_fee: dec!(0),
@@ -57,64 +60,9 @@ blueprint! {
             (component,badge_bucket)
         }
 
-            // Create a metaBadge and populate a hashmap to associate a determinated entry fee level
-            // to a candy stoke event.
-            fn add_meta_badge(
-                &mut self, 
-                symbol: String, 
-                candy_addr: Address, 
-                entry_fee: Decimal, 
-                meta_amnt: Decimal
-            ) -> Bucket {
-                let mut meta_badge_res_def: ResourceDef = 
-                    ResourceBuilder::new_fungible(DIVISIBILITY_MAXIMUM)
-                        .metadata("symbol", format!(" mBadge{}", symbol.clone()))
-                        .flags(MINTABLE | BURNABLE)
-                        .badge(self.minter_badge.resource_def(), MAY_MINT | MAY_BURN)
-                        .no_initial_supply();
-                
-                let badge_address = meta_badge_res_def.address();               
-                self.badge_map.insert(badge_address.clone(),(entry_fee, meta_amnt, candy_addr));
-
-                self.minter_badge.authorize(|auth| {meta_badge_res_def.mint(1, auth)})
-            }
-
-            // Populate a hashmap relating a determinated metaBadge address to a metaCandy amount.
-            fn meta_amounts(
-                &mut self, 
-                meta_badge: Bucket, 
-                meta_amount: Decimal, 
-                flag: Decimal
-            ) -> Bucket {
-                let meta_badge_addr: Address = meta_badge.resource_address().clone();
-                
-                let (a,mut m_candy_amnt,c) = *self.badge_map.get(&meta_badge_addr).unwrap();
-                let entry_fee = a;
-                let candy_addr = c;               
-                
-                if flag == dec!(0) {
-                    m_candy_amnt = m_candy_amnt+meta_amount;
-                    self.badge_map.insert(meta_badge_addr,(entry_fee,m_candy_amnt,candy_addr));
-                    meta_badge
-                } else if flag == dec!(1) {
-                    assert!( meta_amount <= m_candy_amnt," Let's check passed amount ");
-                    m_candy_amnt = m_candy_amnt-meta_amount;
-                  
-                    if m_candy_amnt == dec!(0) {
-                        self.badge_map.remove(&meta_badge_addr);
-                        CandyDex::badge_burn(self, meta_badge);
-                        Bucket::new(RADIX_TOKEN)
-                    } else { 
-                        self.badge_map.insert(meta_badge_addr,(entry_fee,m_candy_amnt,candy_addr));
-                        meta_badge 
-                    }
-                } else { 
-                    std::process::abort() 
-                }
-            }
-
             // Create a metaCandy resource relative to a kind of candy provided to protocol by end 
-            // users.
+            // users, along with a position resource whose units record each liquidity provider 
+            // entry fee level and metaCandy amount.
             fn add_meta_candy(
                 &mut self, 
                 name: String, 
@@ -131,13 +79,26 @@ blueprint! {
                         .badge(self.minter_badge.resource_def(), MAY_MINT | MAY_BURN)
                         .no_initial_supply();
 
+                let position_res_def: ResourceDef = ResourceBuilder::new_non_fungible()
+                    .metadata("name", format!(" mPosition{}", name.clone()))
+                    .metadata("symbol", format!(" mPosition{}", symbol.clone()))
+                    .flags(MINTABLE | BURNABLE | INDIVIDUAL_METADATA_MUTABLE)
+                    .badge(
+                        self.minter_badge.resource_def(), 
+                        MAY_MINT | MAY_BURN | MAY_CHANGE_INDIVIDUAL_METADATA
+                    )
+                    .no_initial_supply();
+
                 let meta_address = meta_res_def.address();  
 
-                self.meta.insert(address.clone(),MetaToken::new(name, symbol, address, meta_res_def));
+                self.meta.insert(
+                    address.clone(),
+                    MetaToken::new(name, symbol, address, meta_res_def, position_res_def)
+                );
                 
                 match self.meta_map.get_mut(&address.clone()) {
                     Some((_a,_b,c)) => *c = meta_address,
-                    None => std::process::abort()                  
+                    None => panic!(" Candy {} has no metaCandy entry ", address)
                 };
                
                 meta_address
@@ -148,7 +109,7 @@ blueprint! {
             fn meta_mint(&mut self, candy_amnt: Decimal, candy_address: Address) -> Bucket {             
                 match self.meta_map.get_mut(&candy_address.clone()) {
                     Some((_a,minted_amnt,_c)) => *minted_amnt = *minted_amnt+candy_amnt,
-                    None => std::process::abort()                  
+                    None => panic!(" Candy {} has no metaCandy entry ", candy_address)
                 };
 
                 let m_candy = self.meta.get_mut(&candy_address).unwrap();
@@ -162,10 +123,28 @@ blueprint! {
                 self.minter_badge.authorize(|auth| {meta_candy.burn_with_auth(auth)});
             }
 
-            // Burn metaBadge once all relative metaCandy has been claimed by end user 
-            // via "unstock_candy" function.
-            fn badge_burn(&mut self, meta_badge: Bucket) {
-                self.minter_badge.authorize(|auth| {meta_badge.burn_with_auth(auth)});
+            // Mint a position relative to an amount of metaCandy minted at a determinated accrued 
+            // fee level.
+            fn position_mint(
+                &mut self, 
+                candy_addr: Address, 
+                entry_fee: Decimal, 
+                meta_amount: Decimal
+            ) -> Bucket {
+                let mut position_res_def = self.meta.get(&candy_addr).unwrap().position_res_def.clone();
+                let position = CandyPosition { entry_fee, meta_amount };
+
+                self.minter_badge.authorize(|auth| {
+                    position_res_def.mint_non_fungible(&NonFungibleKey::from(Uuid::generate()), position, auth)
+                })
+            }
+
+            // Retrieve candy address relative to a position resource address.
+            fn position_candy(&self, position_addr: Address) -> Address {
+                match self.meta.values().find(|m| m.position_res_def.address() == position_addr) {
+                    Some(meta_token) => meta_token.candy_address,
+                    None => panic!(" Position resource {} unrecognized! ", position_addr)
+                }
             }
 
             // Retrieve price of specific candy type.
@@ -174,15 +153,24 @@ blueprint! {
                 
                 match self.candy_map.get(&candy_addr) {
                     Some((_a,_b,c)) => price = *c,
-                    None => { 
-                        info!(" Candy not in stock! ");
-                        std::process::abort()
-                    }
+                    None => panic!(" Candy {} not in stock! ", candy_addr)
                 };
                 
                 price
             }
 
+            // Update candy cumulative price accumulator with current candy price.
+            fn oracle_update(&mut self, candy_addr: Address) {
+                let price = CandyDex::candyprice(self, candy_addr);
+                let epoch = Context::current_epoch();
+                let window = self.twap_window;
+
+                match self.oracle_map.get_mut(&candy_addr) {
+                    Some(oracle) => oracle.update(price, epoch, window),
+                    None => panic!(" No price oracle for candy {} ", candy_addr)
+                }
+            }
+
             // Put candy to sell in vault whenever end user specify an exact number of candy to sell 
             // using swap functions.
             fn candyput_pri(
@@ -207,6 +195,8 @@ blueprint! {
 
                 v.put(candy);
 
+                CandyDex::oracle_update(self, candy_addr);
+
                 (nmbr,amount)
             }
 
@@ -235,6 +225,8 @@ blueprint! {
 
                 v.put(candy.take(amnt_in));
 
+                CandyDex::oracle_update(self, candy_addr);
+
                 candy
             }
 
@@ -242,14 +234,14 @@ blueprint! {
             fn candytake(&mut self, candy_out_nbr: Decimal, candy_out_addr: Address) -> Bucket {
                 match self.meta_map.get_mut(&candy_out_addr.clone()) {
                     Some((amnt_fee,_b,_c)) => *amnt_fee = *amnt_fee+candy_out_nbr*self.fee/100,
-                    None => std::process::abort()                  
+                    None => panic!(" Candy {} not in stock! ", candy_out_addr)
                 };
 
+                CandyDex::oracle_update(self, candy_out_addr);
+
                 match self.candy_vaults.get_mut(&candy_out_addr) {
                     Some(vault) => vault.take(candy_out_nbr-(candy_out_nbr*self.fee/100)),
-                    None => { info!("Candy not in stock! ");
-                              std::process::abort()
-                    }
+                    None => panic!(" Candy {} not in stock! ", candy_out_addr)
                 }
             }
 
@@ -306,6 +298,28 @@ blueprint! {
                 }
             }
 
+            // Check a candy to candy swap is between different stocked candies and within deadline.
+            fn check_candy_swap(
+                &self, 
+                addr_in: Address, 
+                addr_out: Address, 
+                deadline_epoch: u64
+            ) {
+                assert!( 
+                    Context::current_epoch() <= deadline_epoch, 
+                    " Swap deadline epoch {} expired ", deadline_epoch
+                );
+                assert!(addr_in != addr_out," Same candy's address detect! ");
+                assert!( 
+                    self.candy_map.contains_key(&addr_in), 
+                    " Candy {} not in stock! ", addr_in
+                );
+                assert!( 
+                    self.candy_map.contains_key(&addr_out), 
+                    " Candy {} not in stock! ", addr_out
+                );
+            }
+
             // Adjust buying exact candy amount neutralizing protocol fee incidence on final amount.
             fn adjust_fee(&mut self, amount_in: Decimal ) -> Decimal {
                 amount_in*dec!(100)/(dec!(100)-self.fee)
@@ -320,6 +334,16 @@ blueprint! {
             info!(" Protocol fee set to {}% ", self.fee);
         }
 
+            // Set TWAP observation window in epochs function whom only protocol owner can succesfully 
+            // call.
+        #[auth(owner_badge)]
+        pub fn set_twap_window(&mut self, epochs: u64) {
+            assert!(epochs > 0, " TWAP window must last at least one epoch ");
+
+            self.twap_window = epochs;
+            info!(" TWAP window set to {} epochs ", self.twap_window);
+        }
+
             // Claim accrued XRD fee function whom only protocol owner can succesfully call.
         #[auth(owner_badge)]
         pub fn claim_xrd_fee(&mut self) -> Bucket {
@@ -347,11 +371,10 @@ blueprint! {
 
             assert!( candy_addr != RADIX_TOKEN," Cannot stock XRD as candy ");
             assert!(new_price > dec!(0), "new price must be a positive value");
-            
-            if self.candy_map.contains_key(&candy_addr) {
-                info!(" Candy already in Vault. Please use restock_candy function ");
-                std::process::abort()
-            }
+            assert!(
+                !self.candy_map.contains_key(&candy_addr),
+                " Candy already in Vault. Please use restock_candy function "
+            );
             info!(" Added {} {} candy, {} symbol @{}XRD price ", candy_amnt, name, symbol ,new_price);
             
             let v = self.candy_vaults.entry(candy_addr).or_insert(Vault::new(candy_addr));
@@ -362,20 +385,16 @@ blueprint! {
             
             self.candy_map.insert(candy_addr,(name.clone(),symbol.clone(),new_price));
             self.meta_map.insert(candy_addr,(none,none,candy_addr));
+            self.oracle_map.insert(candy_addr,PriceOracle::new(new_price, Context::current_epoch()));
             
             CandyDex::add_meta_candy(self, name.clone(), symbol.clone(), candy_addr);
             
             let meta_candy: Bucket = CandyDex::meta_mint(self, candy_amnt, candy_addr);           
             
             let meta_amount = meta_candy.amount();
-            let meta_badge: Bucket = 
-                CandyDex::add_meta_badge(self, symbol, candy_addr, none, meta_amount);
-            
-            self.badge_map.insert(meta_badge.resource_address().clone(),(none,none,candy_addr));
-            
-            let output_badge: Bucket = CandyDex::meta_amounts(self, meta_badge, meta_amount, none);
+            let position: Bucket = CandyDex::position_mint(self, candy_addr, none, meta_amount);
             
-            (meta_candy,output_badge)
+            (meta_candy,position)
         }
 
             // Restock candy function callable by an end user wishing to supply present liquidity to 
@@ -389,10 +408,7 @@ blueprint! {
             match self.candy_map.get(&candy_addr) {
                 Some((a,b,c)) => 
                     info!(" Adding {} {} candy, {} symbol, @{} $XRD price ", amnt, a.to_string(), b.to_string(), c),
-                _ => { 
-                    info!(" Found no candy in Vault. Please use stock_candy function ");
-                    std::process::abort()
-                }
+                _ => panic!(" Found no candy in Vault. Please use stock_candy function ")
             }
             
             let v = self.candy_vaults.get_mut(&candy_addr).unwrap();
@@ -405,50 +421,50 @@ blueprint! {
             info!(" entry_fee {} ",accrued_fee);
             
             let entry_fee = *accrued_fee;            
-            let (_name,symbol,_price) = self.candy_map.get(&candy_addr).unwrap();
-            let symbol_str = symbol.to_string();
             let meta_amount = meta_candy.amount();
-            let meta_badge: Bucket = 
-                CandyDex::add_meta_badge(self, symbol_str, candy_addr, entry_fee, meta_amount);
+            let position: Bucket = CandyDex::position_mint(self, candy_addr, entry_fee, meta_amount);
             
-            self.badge_map.insert(meta_badge.resource_address()
-                .clone(),(entry_fee,dec!(0),candy_addr));
-            
-            let output_badge: Bucket = 
-                CandyDex::meta_amounts(self, meta_badge, meta_amount, dec!(0));
-            
-            (meta_candy,output_badge)
+            (meta_candy,position)
         }
 
             // Unstock candy function callable by an end user wishing to withdraw owned candy 
-            // liquidity amount from protocol.
+            // liquidity amount from protocol. Position is updated with remaining metaCandy amount 
+            // or burned once all of it has been claimed.
         pub fn unstock_candy(
             &mut self, 
             candy_addr: Address, 
             meta_candy: Bucket, 
-            meta_badge: Bucket
+            position: Bucket
         ) -> (Bucket,Bucket,Bucket) {
-            let badge_amnt: Decimal = meta_badge.amount();
-            assert!( badge_amnt >= dec!(1), " Please supply meta badge ");
+            assert!( position.amount() == dec!(1), " Please supply exactly one position ");
+            assert!( 
+                CandyDex::position_candy(self, position.resource_address()) == candy_addr,
+                " Position resource unrecognized! "
+            );
             
-            let (accrued_fee,total_minted,meta_address) = self.meta_map.get(&candy_addr).unwrap();
-            assert!(meta_address == &meta_candy.resource_address()," Mismatch between Candy & metaCandy! ");
+            let (accrued_fee,total_minted,meta_address) = *self.meta_map.get(&candy_addr).unwrap();
+            assert!(meta_address == meta_candy.resource_address()," Mismatch between Candy & metaCandy! ");
             
-            let (entry_fee,_b,candy_address) = 
-                self.badge_map.get(&meta_badge.resource_address()).unwrap();
-            assert!(candy_address == &candy_addr," MetaBadge address unrecognized! ");
+            let mut position_res_def = position.resource_def();
+            let position_key = position.get_non_fungible_key();
+            let mut position_data: CandyPosition = position_res_def.get_non_fungible_data(&position_key);
             
             let meta_candy_amnt: Decimal = meta_candy.amount();
+            assert!( 
+                meta_candy_amnt <= position_data.meta_amount, 
+                " Position holds {} metaCandy only ", position_data.meta_amount
+            );
+            
             let candy_out_nbr: Decimal;
             let candy_bucket: Bucket;
             let xrd_out: Bucket;
-            let delta_fee: Decimal = *accrued_fee-(*entry_fee);
+            let delta_fee: Decimal = accrued_fee-position_data.entry_fee;
             
-            candy_out_nbr = meta_candy_amnt+delta_fee*meta_candy_amnt/(*total_minted);
+            candy_out_nbr = meta_candy_amnt+delta_fee*meta_candy_amnt/total_minted;
             
             info!(" total_minted {} ",total_minted);
             info!(" accrued_fee {} ",accrued_fee);
-            info!(" entry_fee {} ",entry_fee);
+            info!(" entry_fee {} ",position_data.entry_fee);
             info!(" delta_fee {} ",delta_fee);
             info!(" candy_out_nbr {} ",candy_out_nbr);
             
@@ -457,20 +473,14 @@ blueprint! {
             if candy_out_nbr <= total_candy {
                     candy_bucket = match self.candy_vaults.get_mut(&candy_addr) {
                         Some(vault) => vault.take(candy_out_nbr),
-                        None => {
-                            info!("Candy not in stock !");
-                            std::process::abort()
-                        }
+                        None => panic!(" Candy {} not in stock! ", candy_addr)
                     };
                     let zero: Decimal = dec!(0);
                     xrd_out = self.collected_xrd.take(zero);
             }else{  let delta_candy = candy_out_nbr-total_candy;
                     candy_bucket = match self.candy_vaults.get_mut(&candy_addr) {
                         Some(vault) => vault.take(total_candy),
-                        None => {
-                            info!("Candy not in stock !");
-                            std::process::abort()
-                        }
+                        None => panic!(" Candy {} not in stock! ", candy_addr)
                     };
                     let price_in: Decimal = CandyDex::candyprice(self, candy_addr);
                     let xrd_amnt = delta_candy*price_in;
@@ -478,61 +488,114 @@ blueprint! {
                     xrd_out = self.collected_xrd.take(xrd_amnt);
             }
 
-            let meta_candy_amnt: Decimal = meta_candy.amount().clone();
-            
             CandyDex::meta_burn(self, meta_candy);
             
-            let output_badge: Bucket = 
-                CandyDex::meta_amounts(self, meta_badge, meta_candy_amnt, dec!(1));
-            
-            (candy_bucket,xrd_out,output_badge)
-        }
-
-            // Retrieve liquidity provider position providing a relative metaBadge as reference.
-        pub fn stock_position(&mut self, meta_badge: BucketRef) {
-            let badge_amnt: Decimal = meta_badge.amount();
-            assert!( badge_amnt >= dec!(1), " Please provide your own metaBadge as reference ");
-            
-            match self.badge_map.get(&meta_badge.resource_address()) {
-                Some((a,b,c)) => { 
-                    let (accrued_fee,total_minted,_meta_address) = self.meta_map.get(&c).unwrap();
-                    let delta_fee: Decimal = *accrued_fee-(*a);
-                    let candy_out_nbr = *b+delta_fee*(*b)/(*total_minted);
-                    info!(" accrued_fee {} ",accrued_fee);
-                    info!(" total_minted {} ",total_minted);
-                    info!(" entry_fee {} ",a);
-                    info!(" delta_fee {} ",delta_fee);
-                    info!(" candy_out_nbr {} ",candy_out_nbr);
-                },
-                None => {
-                    info!(" No badge's correspondence! ");
-                    std::process::abort()
-                }
+            let output_position: Bucket = if meta_candy_amnt == position_data.meta_amount {
+                let position_addr = position.resource_address();
+                self.minter_badge.authorize(|auth| {position.burn_with_auth(auth)});
+                Bucket::new(position_addr)
+            } else {
+                position_data.meta_amount = position_data.meta_amount-meta_candy_amnt;
+                self.minter_badge.authorize(|auth| {
+                    position_res_def.update_non_fungible_data(&position_key, position_data, auth)
+                });
+                position
+            };
+            
+            (candy_bucket,xrd_out,output_position)
+        }
+
+            // Merge several positions on the same candy into a single one. Entry fee level of the 
+            // merged position is metaCandy weighted average of merged ones, so accrued fee claimable 
+            // is unchanged.
+        pub fn merge_positions(&mut self, positions: Bucket) -> Bucket {
+            let candy_addr = CandyDex::position_candy(self, positions.resource_address());
+            let position_res_def = positions.resource_def();
+            
+            let keys = positions.get_non_fungible_keys();
+            assert!( keys.len() >= 2, " Please supply at least two positions to merge ");
+            
+            let mut meta_amount: Decimal = dec!(0);
+            let mut weighted_fee: Decimal = dec!(0);
+            for key in keys.iter() {
+                let position_data: CandyPosition = position_res_def.get_non_fungible_data(key);
+                meta_amount += position_data.meta_amount;
+                weighted_fee += position_data.entry_fee*position_data.meta_amount;
             }
+            
+            self.minter_badge.authorize(|auth| {positions.burn_with_auth(auth)});
+            
+            let entry_fee = if meta_amount == dec!(0) { dec!(0) } else { weighted_fee/meta_amount };
+            
+            CandyDex::position_mint(self, candy_addr, entry_fee, meta_amount)
         }
 
-            // Get price, name, symbol of a determinated candy giving his resource address.
-        pub fn get_price(&self, candy_addr: Address) {
+            // Retrieve liquidity provider position providing a relative position as reference.
+        pub fn stock_position(&mut self, position: BucketRef) -> LpPosition {
+            assert!( position.amount() == dec!(1), " Please provide exactly one position as reference ");
+            
+            let candy_addr = CandyDex::position_candy(self, position.resource_address());
+            let position_data: CandyPosition = 
+                position.resource_def().get_non_fungible_data(&position.get_non_fungible_key());
+            let entry_fee = position_data.entry_fee;
+            let meta_amount = position_data.meta_amount;
+            
+            let (accrued_fee,total_minted,_meta_address) = *self.meta_map.get(&candy_addr).unwrap();
+            let fee_share: Decimal = (accrued_fee-entry_fee)*meta_amount/total_minted;
+            let candy_out_nbr = meta_amount+fee_share;
+            
+            // Mirror unstock_candy: candy shortfall in vault is repaid in XRD.
+            let total_candy = self.candy_vaults.get(&candy_addr).unwrap().amount();
+            let (candy_amount,xrd_amount) = if candy_out_nbr <= total_candy {
+                (candy_out_nbr,dec!(0))
+            } else {
+                let price = CandyDex::candyprice(self, candy_addr);
+                (total_candy,(candy_out_nbr-total_candy)*price)
+            };
+            
+            LpPosition {
+                candy_address: candy_addr,
+                meta_amount,
+                entry_fee,
+                accrued_fee,
+                fee_share,
+                candy_amount,
+                xrd_amount
+            }
+        }
+
+            // Get price of a determinated candy giving his resource address.
+        pub fn get_price(&self, candy_addr: Address) -> Decimal {
             assert!( candy_addr != RADIX_TOKEN, " XRD is priceless ");
            
             match self.candy_map.get(&candy_addr) {
-                Some((a,b,c)) => 
-                    info!(" Address: {}, Name: {}, Symbol: {}, Price: @{} $XRD ",candy_addr ,a,b,c),
-                None => info!("Could not find candy in stock !")
+                Some((_a,_b,c)) => *c,
+                None => panic!(" Could not find candy in stock! ")
+            }
+        }
+
+            // Get time weighted average price of a determinated candy over protocol's TWAP window.
+        pub fn get_twap(&self, candy_addr: Address) -> Decimal {
+            self.get_twap_over(candy_addr, self.twap_window)
+        }
+
+            // Get time weighted average price of a determinated candy over a number of epochs not 
+            // longer than protocol's TWAP window.
+        pub fn get_twap_over(&self, candy_addr: Address, epochs: u64) -> Decimal {
+            assert!(epochs > 0, " TWAP must last at least one epoch ");
+            assert!(epochs <= self.twap_window, " Observations only cover {} epochs ", self.twap_window);
+
+            match self.oracle_map.get(&candy_addr) {
+                Some(oracle) => oracle.twap(Context::current_epoch(), epochs),
+                None => panic!(" Could not find candy in stock! ")
             }
         }
 
             // Get reserve amount of a determinated candy giving his resource address.
-        pub fn get_reserve(&self, candy_addr: Address) {
-            match self.candy_map.get(&candy_addr) {
-                Some((a,_b,_c)) => { 
-                    let total_candy = self.candy_vaults.get(&candy_addr).unwrap().amount();
-                    info!(" {} candy reserve amount is {} ", a, total_candy);
-                },
-                None => {
-                    info!(" Could not find candy in stock !");
-                    std::process::abort()
-                }
+        pub fn get_reserve(&self, candy_addr: Address) -> Decimal {
+            match self.candy_vaults.get(&candy_addr) {
+                Some(vault) => vault.amount(),
+                None => panic!(" Could not find candy in stock! ")
             }
         }
 
@@ -548,7 +611,7 @@ blueprint! {
             &mut self, 
             candy_addr: Address, 
             xrd_amnt: Decimal
-        ) -> Decimal {
+        ) -> SwapQuote {
             let xrd_amount = CandyDex::adjust_fee(self, xrd_amnt);
 
             let price = CandyDex::candyprice(self, candy_addr);
@@ -556,7 +619,9 @@ blueprint! {
             let new_price: Decimal = 
                 CandyDex::price_mod(self, xrd_amount/price, candy_addr, price, dec!(0));
             
-            xrd_amount/new_price
+            let candy_amount = xrd_amount/new_price;
+
+            SwapQuote::new(candy_amount, xrd_amnt, xrd_amount, candy_amount*price, false)
         }
 
             // Get XRD buy amount. Use with function "buy_xrd_sell_exact_candy" (bxsec)
@@ -564,13 +629,21 @@ blueprint! {
             &mut self, 
             candy_addr: Address, 
             candy_amnt: Decimal
-        ) -> Decimal {
+        ) -> SwapQuote {
             let price = CandyDex::candyprice(self, candy_addr);
             
             let new_price: Decimal = 
                 CandyDex::price_mod(self, candy_amnt, candy_addr, price, dec!(0));
             
-            (candy_amnt*new_price)-(candy_amnt*new_price)*self.fee/100
+            let xrd_amount = candy_amnt*new_price;
+            
+            SwapQuote::new(
+                candy_amnt, 
+                xrd_amount-xrd_amount*self.fee/100, 
+                xrd_amount, 
+                candy_amnt*price, 
+                false
+            )
         }
 
             // Get candy buy amount. Use with function "buy_candy_sell_exact_xrd" (bcsex)
@@ -578,13 +651,21 @@ blueprint! {
             &mut self, 
             candy_addr: Address, 
             xrd_amnt: Decimal
-        ) -> Decimal {
+        ) -> SwapQuote {
             let price = CandyDex::candyprice(self, candy_addr);
             
             let new_price: Decimal = 
                 CandyDex::price_mod(self, xrd_amnt/price, candy_addr, price, dec!(1));
             
-            (xrd_amnt/new_price)-(xrd_amnt/new_price)*self.fee/100
+            let candy_amount = xrd_amnt/new_price;
+
+            SwapQuote::new(
+                xrd_amnt, 
+                candy_amount-candy_amount*self.fee/100, 
+                candy_amount, 
+                xrd_amnt/price, 
+                true
+            )
         }
             
             // Get XRD sell amount. Use with function "buy_exact_candy_sell_xrd" (becsx)
@@ -592,7 +673,7 @@ blueprint! {
             &mut self, 
             candy_addr: Address, 
             candy_amnt: Decimal
-        ) -> Decimal {
+        ) -> SwapQuote {
             let candy_amount = CandyDex::adjust_fee(self, candy_amnt);
             
             let price = CandyDex::candyprice(self, candy_addr);
@@ -600,7 +681,9 @@ blueprint! {
             let new_price: Decimal = 
                 CandyDex::price_mod(self,  candy_amount, candy_addr, price, dec!(1));
             
-            candy_amount*new_price
+            let xrd_amount = candy_amount*new_price;
+
+            SwapQuote::new(xrd_amount, candy_amnt, candy_amount, xrd_amount/price, true)
         }
 
             // Get candy sell amount. Use with function "buy_exact_candy_sell_candy" (becsc)
@@ -609,10 +692,15 @@ blueprint! {
             amnt_in: Decimal, 
             addr_in: Address, 
             addr_out: Address
-        ) -> Decimal {
+        ) -> SwapQuote {
             let amount_in = CandyDex::adjust_fee(self, amnt_in);
             
-            CandyDex::candy_sum(self, amount_in, addr_in, addr_out, 1)
+            let amount_out = CandyDex::candy_sum(self, amount_in, addr_in, addr_out, 1);
+
+            let price_in = CandyDex::candyprice(self, addr_in);
+            let price_out = CandyDex::candyprice(self, addr_out);
+            
+            SwapQuote::new(amount_out, amnt_in, amount_in, amount_out*price_out/price_in, true)
         }
 
             // Get candy buy amount. Use with function "buy_candy_sell_exact_candy"(bcsec)
@@ -621,12 +709,21 @@ blueprint! {
             addr_in: Address, 
             amnt_out: Decimal, 
             addr_out: Address
-        ) -> Decimal {
+        ) -> SwapQuote {
             let amount_out = CandyDex::adjust_fee(self, amnt_out);
             
             let amount = CandyDex::candy_sum(self, amount_out, addr_out, addr_in, 1);
+
+            let price_in = CandyDex::candyprice(self, addr_in);
+            let price_out = CandyDex::candyprice(self, addr_out);
             
-            amount-amount*self.fee/100
+            SwapQuote::new(
+                amnt_out, 
+                amount-amount*self.fee/100, 
+                amount, 
+                amnt_out*price_out/price_in, 
+                true
+            )
         }
 
             // Obtain a minimum candy amount in exchange of an exact XRD amount. 
@@ -731,6 +828,67 @@ blueprint! {
             )
         }
         
+            // Obtain a minimum candy amount in exchange of an exact candy amount before a deadline 
+            // epoch. Function swap exact candy for candy with slippage limit.
+        pub fn swap_exact_in(
+            &mut self, 
+            candy_in: Bucket, 
+            candy_out_addr: Address, 
+            min_out: Decimal, 
+            deadline_epoch: u64
+        ) -> Bucket {
+            let addr_in = candy_in.resource_address();
+            CandyDex::check_candy_swap(self, addr_in, candy_out_addr, deadline_epoch);
+            
+            let (_nmbr,amount_out) = 
+                CandyDex::candyput_pri(self, dec!(0), dec!(1), candy_out_addr, candy_in);
+            assert!( 
+                amount_out <= self.candy_vaults.get(&candy_out_addr).unwrap().amount(), 
+                " Not enough candy in stock "
+            );
+            
+            let candy_out = CandyDex::candytake(self, amount_out, candy_out_addr);
+            assert!( 
+                candy_out.amount() >= min_out, 
+                " Output amount {} below minimum {} ", candy_out.amount(), min_out
+            );
+            
+            candy_out
+        }
+
+            // Obtain an exact candy amount in exchange of a maximum candy amount before a deadline 
+            // epoch. Function swap candy for exact candy with slippage limit. Unspent input candy 
+            // is returned back.
+        pub fn swap_exact_out(
+            &mut self, 
+            candy_in: Bucket, 
+            candy_out_addr: Address, 
+            amount_out: Decimal, 
+            max_in: Decimal, 
+            deadline_epoch: u64
+        ) -> (Bucket,Bucket) {
+            let addr_in = candy_in.resource_address();
+            CandyDex::check_candy_swap(self, addr_in, candy_out_addr, deadline_epoch);
+            assert!(amount_out > dec!(0), " Output amount must be positive ");
+            
+            let amount = CandyDex::adjust_fee(self, amount_out);
+            assert!( 
+                amount <= self.candy_vaults.get(&candy_out_addr).unwrap().amount(), 
+                " Not enough candy in stock "
+            );
+            
+            let amount_before = candy_in.amount();
+            let change = 
+                CandyDex::candyput_sec(self, amount, candy_out_addr, dec!(0), dec!(1), candy_in);
+            let amount_in = amount_before-change.amount();
+            assert!( 
+                amount_in <= max_in, 
+                " Input amount {} above maximum {} ", amount_in, max_in
+            );
+            
+            (CandyDex::candytake(self, amount, candy_out_addr),change)
+        }
+
             // Obtain an exact XRD amount in exchange of a maximum candy amount. 
             // Function swap candy for exact XRD.
         pub fn buy_exact_xrd_sell_candy(
@@ -751,7 +909,7 @@ blueprint! {
             
             match self.candy_map.get_mut(&addr) {
                 Some((_a,_b,price)) => *price = new_price,
-                None => std::process::abort()                  
+                None => panic!(" Candy {} not in stock! ", addr)
             };
 
             self.xrd_fee = self.xrd_fee+xrd_input*self.fee/100;
@@ -804,8 +962,7 @@ blueprint! {
                 price_in = CandyDex::candyprice(self, addr_in);
                 price_out = CandyDex::candyprice(self, bckt_addr);
             } else { 
-                info!(" Check out addresses! "); 
-                std::process::abort(); 
+                panic!(" Check out addresses! ");
             }
             
             let args = vec![scrypto_encode(&token_bucket),scrypto_encode(&bckt_addr)];
@@ -826,10 +983,7 @@ blueprint! {
                 token_output = self.collected_xrd.take(*&(amount-nmbr)); 
             }
 
-            if token_output.amount() < dec!(0) {
-               info!(" Sorry mate, ain't nothin' to scrape! ");
-               std::process::abort();
-            }
+            assert!(token_output.amount() >= dec!(0), " Sorry mate, ain't nothin' to scrape! ");
 
             token_output
         }
@@ -844,6 +998,7 @@ pub struct MetaToken {
     candy_symbol: String,
     candy_address: Address,
     meta_res_def: ResourceDef,
+    position_res_def: ResourceDef,
 }
 
 impl MetaToken {
@@ -852,12 +1007,238 @@ impl MetaToken {
         candy_symbol: String,
         candy_address: Address,
         meta_res_def: ResourceDef,
+        position_res_def: ResourceDef,
     ) -> Self {
         Self {
             candy_name,
             candy_symbol,
             candy_address,
             meta_res_def,
+            position_res_def,
+        }
+    }
+}
+
+// Build a structure to record a liquidity provider position as non fungible data. Positions on the 
+// same candy share a single resource, they're transferable and mergeable.
+#[derive(TypeId, Encode, Decode, Describe, NonFungibleData)]
+pub struct CandyPosition {
+    // Candy accrued fee level at position entry.
+    entry_fee: Decimal,
+    // metaCandy amount related to position.
+    meta_amount: Decimal,
+}
+
+// Build a structure and implement it to return a swap quote with amounts, price impact and fee 
+// breakdown. Amounts are seen from end user side: "amount_in" is sold, "amount_out" is bought.
+#[derive(Debug, Clone, TypeId, Encode, Decode, Describe, PartialEq, Eq)]
+pub struct SwapQuote {
+    // Token amount end user sells to protocol.
+    pub amount_in: Decimal,
+    // Token amount end user receives, protocol fee excluded.
+    pub amount_out: Decimal,
+    // Percentage shortfall of bought amount, fee excluded, versus an amount at spot price.
+    // Negative whenever swap is executed at better than spot price.
+    pub price_impact: Decimal,
+    // Fee in bought candy accrued to liquidity providers.
+    pub lp_fee: Decimal,
+    // Fee in bought XRD accrued to protocol owner.
+    pub protocol_fee: Decimal,
+}
+
+impl SwapQuote {
+    pub fn new(
+        amount_in: Decimal,
+        amount_out: Decimal,
+        gross_amount_out: Decimal,
+        spot_amount_out: Decimal,
+        candy_out: bool,
+    ) -> Self {
+        let fee = gross_amount_out - amount_out;
+        let price_impact = if spot_amount_out == dec!(0) {
+            dec!(0)
+        } else {
+            (spot_amount_out - gross_amount_out) * dec!(100) / spot_amount_out
+        };
+
+        Self {
+            amount_in,
+            amount_out,
+            price_impact,
+            lp_fee: if candy_out { fee } else { dec!(0) },
+            protocol_fee: if candy_out { dec!(0) } else { fee },
+        }
+    }
+}
+
+// Build a structure to return a liquidity provider position relative to a position unit.
+#[derive(Debug, Clone, TypeId, Encode, Decode, Describe, PartialEq, Eq)]
+pub struct LpPosition {
+    // Stocked candy address.
+    pub candy_address: Address,
+    // metaCandy amount related to position.
+    pub meta_amount: Decimal,
+    // Candy accrued fee level at position entry.
+    pub entry_fee: Decimal,
+    // Candy accrued fee level now.
+    pub accrued_fee: Decimal,
+    // Candy fee accrued to position since entry.
+    pub fee_share: Decimal,
+    // Candy amount returned unstocking the whole position.
+    pub candy_amount: Decimal,
+    // XRD amount returned unstocking the whole position, whenever candy vault falls short.
+    pub xrd_amount: Decimal,
+}
+
+// Build a structure and implement it to keep a cumulative price accumulator of a candy, updated on 
+// every swap, and to calculate candy's time weighted average price over a window of epochs.
+#[derive(Debug, Clone, TypeId, Encode, Decode, Describe, PartialEq, Eq)]
+pub struct PriceOracle {
+    // Candy price since last update.
+    last_price: Decimal,
+    // Epoch of last update.
+    last_epoch: u64,
+    // Epoch candy has been stocked, first checkpoint until it gets pruned.
+    first_epoch: u64,
+    // Sum of candy prices times epochs they've lasted, up to last update.
+    cumulative: Decimal,
+    // Accumulator checkpoints as (epoch, cumulative), one per epoch with swaps, oldest first.
+    observations: Vec<(u64, Decimal)>,
+}
+
+impl PriceOracle {
+    pub fn new(price: Decimal, epoch: u64) -> Self {
+        Self {
+            last_price: price,
+            last_epoch: epoch,
+            first_epoch: epoch,
+            cumulative: dec!(0),
+            observations: vec![(epoch, dec!(0))],
+        }
+    }
+
+    // Accumulate last price over epochs elapsed since last update, then record the new price.
+    // Only the last price of each epoch is accumulated so a price moved and restored within an 
+    // epoch doesn't affect the average.
+    pub fn update(&mut self, price: Decimal, epoch: u64, window: u64) {
+        if epoch > self.last_epoch {
+            self.cumulative = self.cumulative_at(epoch);
+            self.last_epoch = epoch;
+            self.observations.push((epoch, self.cumulative));
+
+            // Keep the newest checkpoint preceding window start, drop older ones.
+            while self.observations.len() > 1 && self.observations[1].0 + window <= epoch {
+                self.observations.remove(0);
+            }
+        }
+        self.last_price = price;
+    }
+
+    // Time weighted average price over the epochs preceding the given one. If candy has been 
+    // stocked for less than the given epochs the average covers its whole history. Checkpoints 
+    // pruned with a shorter window can't be averaged over, so after a window raise a longer TWAP 
+    // is refused until enough history has accumulated again.
+    pub fn twap(&self, epoch: u64, epochs: u64) -> Decimal {
+        let oldest = self.observations[0].0;
+        assert!(
+            oldest == self.first_epoch || epoch.saturating_sub(epochs) >= oldest,
+            " Observations only cover {} epochs ", epoch - oldest
+        );
+        let start = std::cmp::max(epoch.saturating_sub(epochs), oldest);
+        
+        if start >= epoch {
+            return self.last_price;
         }
+
+        (self.cumulative_at(epoch) - self.cumulative_at(start)) / Decimal::from(epoch - start)
+    }
+
+    // Accumulator value at a given epoch not older than the first checkpoint. Price is constant 
+    // between consecutive checkpoints so accumulator grows linearly in between.
+    fn cumulative_at(&self, epoch: u64) -> Decimal {
+        if epoch >= self.last_epoch {
+            return self.cumulative + self.last_price * Decimal::from(epoch - self.last_epoch);
+        }
+
+        let next = self.observations.iter().position(|(e, _)| *e > epoch).unwrap();
+        let (e0, c0) = self.observations[next - 1];
+        let (e1, c1) = self.observations[next];
+
+        c0 + (c1 - c0) * Decimal::from(epoch - e0) / Decimal::from(e1 - e0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_twap_over_several_prices() {
+        let mut oracle = PriceOracle::new(dec!(10), 0);
+        oracle.update(dec!(20), 2, 10);
+        oracle.update(dec!(30), 5, 10);
+        // 10 for 2 epochs, 20 for 3 epochs, 30 for 2 epochs
+        assert_eq!(oracle.twap(7, 7), dec!(20));
+        // 20 for 1 epoch, 30 for 2 epochs
+        assert_eq!(oracle.twap(7, 3), dec!("26.666666666666666666"));
+    }
+
+    #[test]
+    fn test_twap_only_accumulates_last_price_of_an_epoch() {
+        let mut oracle = PriceOracle::new(dec!(10), 0);
+        oracle.update(dec!(50), 3, 10);
+        oracle.update(dec!(1), 3, 10);
+        oracle.update(dec!(20), 3, 10);
+        assert_eq!(oracle.observations.len(), 2);
+        // 10 for 3 epochs, 20 for 2 epochs
+        assert_eq!(oracle.twap(5, 5), dec!(14));
+    }
+
+    #[test]
+    fn test_twap_interpolates_between_checkpoints() {
+        let mut oracle = PriceOracle::new(dec!(10), 0);
+        oracle.update(dec!(20), 4, 10);
+        oracle.update(dec!(30), 8, 10);
+        // window starts at epoch 3, between checkpoints 0 and 4: 10 for 1 epoch, 20 for 4 epochs
+        assert_eq!(oracle.cumulative_at(3), dec!(30));
+        assert_eq!(oracle.twap(8, 5), dec!(18));
+        // last price lasts after last checkpoint: 20 for 2 epochs, 30 for 2 epochs
+        assert_eq!(oracle.twap(10, 4), dec!(25));
+    }
+
+    #[test]
+    fn test_twap_prunes_checkpoints_at_window_boundary() {
+        let mut oracle = PriceOracle::new(dec!(10), 0);
+        oracle.update(dec!(20), 2, 4);
+        oracle.update(dec!(30), 4, 4);
+        // checkpoint 2 starts the window ending at epoch 6, checkpoint 0 isn't needed anymore
+        oracle.update(dec!(40), 6, 4);
+        assert_eq!(oracle.observations, vec![(2, dec!(20)), (4, dec!(60)), (6, dec!(120))]);
+        assert_eq!(oracle.twap(6, 4), dec!(25));
+        // checkpoint 2 still precedes the window ending at epoch 7
+        oracle.update(dec!(50), 7, 4);
+        assert_eq!(oracle.observations[0].0, 2);
+        assert_eq!(oracle.twap(7, 4), dec!(30));
+    }
+
+    #[test]
+    #[should_panic(expected = "Observations only cover 5 epochs")]
+    fn test_twap_refuses_history_pruned_by_a_shorter_window() {
+        let mut oracle = PriceOracle::new(dec!(10), 0);
+        oracle.update(dec!(20), 2, 4);
+        oracle.update(dec!(30), 4, 4);
+        oracle.update(dec!(40), 6, 4);
+        // the window is raised to 8 epochs but checkpoint 0 is gone
+        oracle.update(dec!(50), 7, 8);
+        oracle.twap(7, 8);
+    }
+
+    #[test]
+    fn test_twap_over_history_shorter_than_window() {
+        let mut oracle = PriceOracle::new(dec!(10), 3);
+        assert_eq!(oracle.twap(3, 10), dec!(10));
+        oracle.update(dec!(20), 5, 10);
+        // 10 for 2 epochs, 20 for 2 epochs since candy has been stocked
+        assert_eq!(oracle.twap(7, 10), dec!(15));
     }
 }
@@ -0,0 +1,566 @@
+use radix_engine::ledger::*;
+use radix_engine::transaction::*;
+use sbor::*;
+use scrypto::prelude::*;
+
+use candydex::{LpPosition, SwapQuote};
+
+// Amounts computed along different paths may differ in the last decimals
+fn eps() -> Decimal {
+    dec!("0.000000001")
+}
+
+#[test]
+fn test_bcsex_quote_matches_executed_swap() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test = TestCandyDex::new(&mut ledger);
+    let user = test.user;
+
+    let price: Decimal = test.query("get_price", vec![test.candy_a.to_string()]);
+    let quote: SwapQuote = test.query("get_candy_buy_amount_bcsex", vec![test.candy_a.to_string(), "500".to_owned()]);
+    let before: LpPosition = test.query_as(user, "stock_position", vec![format!("1,{}", test.position_a)]);
+
+    test.assert_output(
+        user,
+        "buy_candy_sell_exact_xrd",
+        vec!["0".to_owned(), test.candy_a.to_string(), format!("500,{}", RADIX_TOKEN)],
+        test.candy_a,
+        quote.amount_out,
+    );
+
+    // the fee is left to THG liquidity providers
+    let after: LpPosition = test.query_as(user, "stock_position", vec![format!("1,{}", test.position_a)]);
+    assert_eq!(quote.amount_in, dec!(500));
+    assert_eq!(after.accrued_fee - before.accrued_fee, quote.lp_fee);
+    assert_eq!(quote.protocol_fee, dec!(0));
+
+    // 500 XRD buy 250 THG at spot price
+    let spot_amount = dec!(500) / price;
+    let gross_amount = quote.amount_out + quote.lp_fee;
+    assert_eq!(quote.price_impact, (spot_amount - gross_amount) * dec!(100) / spot_amount);
+    assert!(quote.price_impact > dec!(0));
+}
+
+#[test]
+fn test_bxsec_quote_matches_executed_swap() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test = TestCandyDex::new(&mut ledger);
+    let user = test.user;
+
+    // fill the XRD vault first
+    test.assert_ok(user, "buy_candy_sell_exact_xrd", vec!["0".to_owned(), test.candy_a.to_string(), format!("500,{}", RADIX_TOKEN)]);
+
+    let price: Decimal = test.query("get_price", vec![test.candy_a.to_string()]);
+    let quote: SwapQuote = test.query("get_xrd_buy_amount_bxsec", vec![test.candy_a.to_string(), "10".to_owned()]);
+
+    test.assert_output(
+        user,
+        "buy_xrd_sell_exact_candy",
+        vec!["0".to_owned(), format!("10,{}", test.candy_a)],
+        RADIX_TOKEN,
+        quote.amount_out,
+    );
+
+    // the fee is left to the protocol owner
+    test.assert_output(user, "claim_xrd_fee", vec![format!("1,{}", test.owner_badge)], RADIX_TOKEN, quote.protocol_fee);
+    assert_eq!(quote.lp_fee, dec!(0));
+    assert!(quote.protocol_fee > dec!(0));
+
+    let spot_amount = dec!(10) * price;
+    let gross_amount = quote.amount_out + quote.protocol_fee;
+    assert_eq!(quote.price_impact, (spot_amount - gross_amount) * dec!(100) / spot_amount);
+    assert!(quote.price_impact > dec!(0));
+}
+
+#[test]
+fn test_bcsec_quote_matches_executed_swap() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test = TestCandyDex::new(&mut ledger);
+    let user = test.user;
+
+    // sell 10 THG for OMG
+    let quote: SwapQuote = test.query(
+        "get_candy_buy_amount_bcsec",
+        vec![test.candy_b.to_string(), "10".to_owned(), test.candy_a.to_string()],
+    );
+    let before: LpPosition = test.query_as(user, "stock_position", vec![format!("1,{}", test.position_b)]);
+
+    test.assert_output(
+        user,
+        "buy_candy_sell_exact_candy",
+        vec!["0".to_owned(), test.candy_b.to_string(), format!("10,{}", test.candy_a)],
+        test.candy_b,
+        quote.amount_out,
+    );
+
+    let after: LpPosition = test.query_as(user, "stock_position", vec![format!("1,{}", test.position_b)]);
+    assert_eq!(quote.amount_in, dec!(10));
+    assert_eq!(after.accrued_fee - before.accrued_fee, quote.lp_fee);
+    assert_eq!(quote.protocol_fee, dec!(0));
+    assert!(quote.price_impact > dec!(0));
+}
+
+#[test]
+fn test_stock_position_matches_unstock() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test = TestCandyDex::new(&mut ledger);
+    let user = test.user;
+
+    // a second provider doubles THG liquidity, then a fee accrues to both
+    let user2 = test.new_user();
+    test.transfer(user, user2, "1000", test.candy_a);
+    test.assert_ok(user2, "restock_candy", vec![format!("1000,{}", test.candy_a)]);
+    test.assert_ok(user, "buy_candy_sell_exact_xrd", vec!["0".to_owned(), test.candy_a.to_string(), format!("500,{}", RADIX_TOKEN)]);
+
+    let position: LpPosition = test.query_as(user, "stock_position", vec![format!("1,{}", test.position_a)]);
+    assert_eq!(position.candy_address, test.candy_a);
+    assert_eq!(position.meta_amount, dec!(1000));
+    assert_eq!(position.entry_fee, dec!(0));
+    assert!(position.accrued_fee > dec!(0));
+    assert_eq!(position.fee_share, position.accrued_fee * dec!(1000) / dec!(2000));
+    assert_eq!(position.candy_amount, position.meta_amount + position.fee_share);
+    assert_eq!(position.xrd_amount, dec!(0));
+
+    test.assert_output(
+        user,
+        "unstock_candy",
+        vec![test.candy_a.to_string(), format!("1000,{}", test.meta_a), format!("1,{}", test.position_a)],
+        test.candy_a,
+        position.candy_amount,
+    );
+}
+
+#[test]
+fn test_descriptive_panics() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test = TestCandyDex::new(&mut ledger);
+    let user = test.user;
+
+    let candy_c = test.new_candy("KAPPAGUM", "KPG");
+    let receipt = test.call(user, "get_price", vec![candy_c.to_string()]);
+    assert_panicked(&receipt, "Could not find candy in stock!");
+
+    // 500 XRD buy less than 190 THG
+    let receipt = test.call(
+        user,
+        "buy_candy_sell_exact_xrd",
+        vec!["190".to_owned(), test.candy_a.to_string(), format!("500,{}", RADIX_TOKEN)],
+    );
+    assert_panicked(&receipt, "Not enough candies output amount");
+
+    let receipt = test.call(user, "stock_candy", vec![format!("1000,{}", test.candy_a), "2".to_owned()]);
+    assert_panicked(&receipt, "Candy already in Vault. Please use restock_candy function");
+}
+
+#[test]
+fn test_swap_exact_in_returns_candy_sum_amount() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test = TestCandyDex::new(&mut ledger);
+    let user = test.user;
+
+    // the quote of selling 10 THG for OMG comes from candy_sum
+    let quote: SwapQuote = test.query(
+        "get_candy_buy_amount_bcsec",
+        vec![test.candy_b.to_string(), "10".to_owned(), test.candy_a.to_string()],
+    );
+    let (candy_a, candy_b) = (test.candy_a, test.candy_b);
+    let swap_args = move |min_out: Decimal, deadline: &str| vec![
+        format!("10,{}", candy_a),
+        candy_b.to_string(),
+        min_out.to_string(),
+        deadline.to_owned(),
+    ];
+
+    let receipt = test.call(user, "swap_exact_in", swap_args(quote.amount_out + eps(), "100"));
+    assert_panicked(&receipt, "below minimum");
+
+    test.assert_output(user, "swap_exact_in", swap_args(quote.amount_out - eps(), "100"), test.candy_b, quote.amount_out);
+    let reserve: Decimal = test.query("get_reserve", vec![test.candy_a.to_string()]);
+    assert_eq!(reserve, dec!(1010));
+}
+
+#[test]
+fn test_swap_exact_out_returns_change() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test = TestCandyDex::new(&mut ledger);
+    let user = test.user;
+
+    // THG needed to buy 4 OMG
+    let quote: SwapQuote = test.query(
+        "get_candy_sell_amount_becsc",
+        vec!["4".to_owned(), test.candy_b.to_string(), test.candy_a.to_string()],
+    );
+    let (candy_a, candy_b) = (test.candy_a, test.candy_b);
+    let swap_args = move |max_in: Decimal| vec![
+        format!("100,{}", candy_a),
+        candy_b.to_string(),
+        "4".to_owned(),
+        max_in.to_string(),
+        "100".to_owned(),
+    ];
+
+    let receipt = test.call(user, "swap_exact_out", swap_args(quote.amount_in - eps()));
+    assert_panicked(&receipt, "above maximum");
+
+    // the 4 OMG bought and the THG change
+    test.assert_outputs(
+        user,
+        "swap_exact_out",
+        swap_args(quote.amount_in + eps()),
+        vec![(test.candy_b, dec!(4)), (test.candy_a, dec!(100) - quote.amount_in)],
+    );
+}
+
+#[test]
+fn test_candy_swap_checks() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    ledger.set_epoch(10);
+    let mut test = TestCandyDex::new(&mut ledger);
+    let user = test.user;
+
+    let receipt = test.call(
+        user,
+        "swap_exact_in",
+        vec![format!("10,{}", test.candy_a), test.candy_b.to_string(), "0".to_owned(), "9".to_owned()],
+    );
+    assert_panicked(&receipt, "Swap deadline epoch 9 expired");
+    let receipt = test.call(
+        user,
+        "swap_exact_out",
+        vec![format!("10,{}", test.candy_a), test.candy_b.to_string(), "1".to_owned(), "10".to_owned(), "9".to_owned()],
+    );
+    assert_panicked(&receipt, "Swap deadline epoch 9 expired");
+
+    let receipt = test.call(
+        user,
+        "swap_exact_in",
+        vec![format!("10,{}", test.candy_a), test.candy_a.to_string(), "0".to_owned(), "10".to_owned()],
+    );
+    assert_panicked(&receipt, "Same candy's address detect!");
+
+    // the deadline epoch itself is fine
+    test.assert_ok(
+        user,
+        "swap_exact_in",
+        vec![format!("10,{}", test.candy_a), test.candy_b.to_string(), "0".to_owned(), "10".to_owned()],
+    );
+}
+
+#[test]
+fn test_merge_positions_keeps_claimable_fee() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test = TestCandyDex::new(&mut ledger);
+    let user = test.user;
+    let user2 = test.new_user();
+    test.transfer(user, user2, "1000", test.candy_a);
+
+    // user2 enters at a higher fee level than user, then more fee accrues to both
+    test.assert_ok(user, "buy_candy_sell_exact_xrd", vec!["0".to_owned(), test.candy_a.to_string(), format!("500,{}", RADIX_TOKEN)]);
+    test.assert_ok(user2, "restock_candy", vec![format!("500,{}", test.candy_a)]);
+    test.assert_ok(user, "buy_candy_sell_exact_xrd", vec!["0".to_owned(), test.candy_a.to_string(), format!("500,{}", RADIX_TOKEN)]);
+
+    let position1: LpPosition = test.query_as(user, "stock_position", vec![format!("1,{}", test.position_a)]);
+    let position2: LpPosition = test.query_as(user2, "stock_position", vec![format!("1,{}", test.position_a)]);
+    assert!(position2.entry_fee > dec!(0));
+    assert!(position1.fee_share > position2.fee_share);
+
+    test.transfer(user2, user, "1", test.position_a);
+    test.assert_ok(user, "merge_positions", vec![format!("2,{}", test.position_a)]);
+
+    let merged: LpPosition = test.query_as(user, "stock_position", vec![format!("1,{}", test.position_a)]);
+    assert_eq!(merged.meta_amount, dec!(1500));
+    assert_eq!(merged.entry_fee, position2.entry_fee * dec!(500) / dec!(1500));
+    assert_close(merged.fee_share, position1.fee_share + position2.fee_share);
+}
+
+#[test]
+fn test_unstock_from_a_transferred_position() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test = TestCandyDex::new(&mut ledger);
+    let user = test.user;
+    let user2 = test.new_user();
+
+    // the position of 1000 mTHG goes to user2 with part of the mTHG
+    test.transfer(user, user2, "1", test.position_a);
+    test.transfer(user, user2, "600", test.meta_a);
+
+    // partial unstock updates the position
+    test.assert_output(
+        user2,
+        "unstock_candy",
+        vec![test.candy_a.to_string(), format!("200,{}", test.meta_a), format!("1,{}", test.position_a)],
+        test.candy_a,
+        dec!(200),
+    );
+    let position: LpPosition = test.query_as(user2, "stock_position", vec![format!("1,{}", test.position_a)]);
+    assert_eq!(position.meta_amount, dec!(800));
+
+    // user kept mTHG but can't unstock without the position
+    let receipt = test.call(
+        user,
+        "unstock_candy",
+        vec![test.candy_a.to_string(), format!("400,{}", test.meta_a), format!("1,{}", test.position_a)],
+    );
+    assert!(receipt.result.is_err());
+
+    // full unstock burns the position
+    test.transfer(user, user2, "400", test.meta_a);
+    test.assert_output(
+        user2,
+        "unstock_candy",
+        vec![test.candy_a.to_string(), format!("800,{}", test.meta_a), format!("1,{}", test.position_a)],
+        test.candy_a,
+        dec!(800),
+    );
+    let receipt = test.call(user2, "stock_position", vec![format!("1,{}", test.position_a)]);
+    assert!(receipt.result.is_err());
+    let reserve: Decimal = test.query("get_reserve", vec![test.candy_a.to_string()]);
+    assert_eq!(reserve, dec!(0));
+}
+
+#[test]
+fn test_position_of_another_candy_is_rejected() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test = TestCandyDex::new(&mut ledger);
+    let user = test.user;
+
+    let receipt = test.call(
+        user,
+        "unstock_candy",
+        vec![test.candy_a.to_string(), format!("100,{}", test.meta_a), format!("1,{}", test.position_b)],
+    );
+    assert_panicked(&receipt, "Position resource unrecognized!");
+    let reserve: Decimal = test.query("get_reserve", vec![test.candy_a.to_string()]);
+    assert_eq!(reserve, dec!(1000));
+}
+
+#[test]
+fn test_twap_follows_swaps_across_epochs() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test = TestCandyDex::new(&mut ledger);
+    let user = test.user;
+
+    // THG is stocked at epoch 0, bought at epoch 2 and sold at epoch 5
+    let price_0: Decimal = test.query("get_price", vec![test.candy_a.to_string()]);
+    test.set_epoch(2);
+    test.assert_ok(user, "buy_candy_sell_exact_xrd", vec!["0".to_owned(), test.candy_a.to_string(), format!("500,{}", RADIX_TOKEN)]);
+    let price_2: Decimal = test.query("get_price", vec![test.candy_a.to_string()]);
+    test.set_epoch(5);
+    test.assert_ok(user, "buy_xrd_sell_exact_candy", vec!["0".to_owned(), format!("10,{}", test.candy_a)]);
+    let price_5: Decimal = test.query("get_price", vec![test.candy_a.to_string()]);
+    assert_eq!(price_0, dec!(2));
+    assert!(price_2 > price_0);
+    assert!(price_5 < price_2);
+
+    // the oracle holds the price each swap left behind
+    test.set_epoch(7);
+    let expected = (price_0 * dec!(2) + price_2 * dec!(3) + price_5 * dec!(2)) / dec!(7);
+    let twap: Decimal = test.query("get_twap", vec![test.candy_a.to_string()]);
+    assert_eq!(twap, expected);
+    let twap: Decimal = test.query("get_twap_over", vec![test.candy_a.to_string(), "2".to_owned()]);
+    assert_eq!(twap, price_5);
+
+    let receipt = test.call(user, "get_twap_over", vec![test.candy_a.to_string(), "11".to_owned()]);
+    assert_panicked(&receipt, "Observations only cover 10 epochs");
+
+    let receipt = test.call(user, "set_twap_window", vec!["20".to_owned(), format!("1,{}", test.position_a)]);
+    assert!(receipt.result.is_err());
+    test.assert_ok(user, "set_twap_window", vec!["20".to_owned(), format!("1,{}", test.owner_badge)]);
+
+    // a longer TWAP still covers the whole history
+    let twap: Decimal = test.query("get_twap_over", vec![test.candy_a.to_string(), "11".to_owned()]);
+    assert_eq!(twap, expected);
+}
+
+fn assert_close(actual: Decimal, expected: Decimal) {
+    assert!(
+        actual <= expected + eps() && actual + eps() >= expected,
+        "{} isn't close to {}", actual, expected
+    );
+}
+
+fn assert_panicked(receipt: &Receipt, message: &str) {
+    assert!(receipt.result.is_err());
+    assert!(
+        receipt.logs.iter().any(|(_, log)| log.contains(message)),
+        "No log contains \"{}\"", message
+    );
+}
+
+#[derive(Clone, Copy)]
+struct User {
+    key: EcdsaPublicKey,
+    account: Address,
+}
+
+// CandyDex with a 1% fee, stocked with 1000 THG @2 XRD and 1000 OMG @5 XRD by `user`
+struct TestCandyDex<'a> {
+    executor: TransactionExecutor<'a, InMemorySubstateStore>,
+    user: User,
+    dex: Address,
+    owner_badge: Address,
+    candy_a: Address,
+    meta_a: Address,
+    position_a: Address,
+    candy_b: Address,
+    position_b: Address,
+}
+
+impl<'a> TestCandyDex<'a> {
+    fn new(ledger: &'a mut InMemorySubstateStore) -> Self {
+        let mut executor = TransactionExecutor::new(ledger, false);
+        let key = executor.new_public_key();
+        let account = executor.new_account(key);
+        let package = executor.publish_package(include_code!("candydex")).unwrap();
+
+        let transaction = TransactionBuilder::new(&executor)
+            .call_function(package, "CandyDex", "new", vec!["1".to_owned()], Some(account))
+            .call_method_with_all_resources(account, "deposit_batch")
+            .build(vec![key])
+            .unwrap();
+        let receipt = executor.run(transaction).unwrap();
+        println!("{:?}\n", receipt);
+        assert!(receipt.result.is_ok());
+
+        let mut test = Self {
+            executor,
+            user: User { key, account },
+            dex: receipt.component(0).unwrap(),
+            owner_badge: receipt.resource_def(1).unwrap(),
+            candy_a: RADIX_TOKEN,
+            meta_a: RADIX_TOKEN,
+            position_a: RADIX_TOKEN,
+            candy_b: RADIX_TOKEN,
+            position_b: RADIX_TOKEN,
+        };
+
+        test.candy_a = test.new_candy("THETAGUM", "THG");
+        let (meta_a, position_a) = test.stock(test.user, test.candy_a, "2");
+        test.meta_a = meta_a;
+        test.position_a = position_a;
+
+        test.candy_b = test.new_candy("OMICRONGUM", "OMG");
+        let (_meta_b, position_b) = test.stock(test.user, test.candy_b, "5");
+        test.position_b = position_b;
+
+        test
+    }
+
+    fn set_epoch(&mut self, epoch: u64) {
+        self.executor.ledger_mut().set_epoch(epoch);
+    }
+
+    fn new_user(&mut self) -> User {
+        let key = self.executor.new_public_key();
+        let account = self.executor.new_account(key);
+        User { key, account }
+    }
+
+    // 100000 candies with a name and a symbol, as stock_candy needs
+    fn new_candy(&mut self, name: &str, symbol: &str) -> Address {
+        let mut metadata = HashMap::new();
+        metadata.insert("name".to_owned(), name.to_owned());
+        metadata.insert("symbol".to_owned(), symbol.to_owned());
+        let transaction = TransactionBuilder::new(&self.executor)
+            .new_token_fixed(metadata, dec!(100000))
+            .call_method_with_all_resources(self.user.account, "deposit_batch")
+            .build(vec![self.user.key])
+            .unwrap();
+        let receipt = self.executor.run(transaction).unwrap();
+        println!("{:?}\n", receipt);
+        assert!(receipt.result.is_ok());
+        receipt.resource_def(0).unwrap()
+    }
+
+    fn transfer(&mut self, from: User, to: User, amount: &str, resource: Address) {
+        let transaction = TransactionBuilder::new(&self.executor)
+            .call_method(to.account, "deposit", vec![format!("{},{}", amount, resource)], Some(from.account))
+            .call_method_with_all_resources(from.account, "deposit_batch")
+            .build(vec![from.key])
+            .unwrap();
+        let receipt = self.executor.run(transaction).unwrap();
+        println!("{:?}\n", receipt);
+        assert!(receipt.result.is_ok());
+    }
+
+    // Stock 1000 candies, returns the metaCandy and position resources
+    fn stock(&mut self, user: User, candy: Address, price: &str) -> (Address, Address) {
+        let receipt = self.assert_ok(user, "stock_candy", vec![format!("1000,{}", candy), price.to_owned()]);
+        (receipt.resource_def(0).unwrap(), receipt.resource_def(1).unwrap())
+    }
+
+    fn call(&mut self, user: User, method: &str, args: Vec<String>) -> Receipt {
+        let transaction = TransactionBuilder::new(&self.executor)
+            .call_method(self.dex, method, args, Some(user.account))
+            .call_method_with_all_resources(user.account, "deposit_batch")
+            .build(vec![user.key])
+            .unwrap();
+        let receipt = self.executor.run(transaction).unwrap();
+        println!("{:?}\n", receipt);
+        receipt
+    }
+
+    fn assert_ok(&mut self, user: User, method: &str, args: Vec<String>) -> Receipt {
+        let receipt = self.call(user, method, args);
+        assert!(receipt.result.is_ok());
+        receipt
+    }
+
+    // Call a method returning `expected` of `resource` up to eps
+    fn assert_output(&mut self, user: User, method: &str, args: Vec<String>, resource: Address, expected: Decimal) {
+        self.assert_outputs(user, method, args, vec![(resource, expected)]);
+    }
+
+    // Same with one or two returned resources, each one is checked by a failing call asking for more
+    fn assert_outputs(&mut self, user: User, method: &str, args: Vec<String>, expected: Vec<(Address, Decimal)>) {
+        let lower: Vec<(Address, Decimal)> = expected.iter().map(|(resource, amount)| (*resource, *amount - eps())).collect();
+        for i in 0..expected.len() {
+            let mut higher = lower.clone();
+            higher[i].1 = expected[i].1 + eps();
+            assert!(!self.call_expecting(user, method, args.clone(), &higher));
+        }
+        assert!(self.call_expecting(user, method, args, &lower));
+    }
+
+    fn call_expecting(&mut self, user: User, method: &str, args: Vec<String>, amounts: &[(Address, Decimal)]) -> bool {
+        // a single amount is asserted twice
+        let (first_resource, first_amount) = amounts[0];
+        let (last_resource, last_amount) = amounts[amounts.len() - 1];
+        let transaction = TransactionBuilder::new(&self.executor)
+            .call_method(self.dex, method, args, Some(user.account))
+            .assert_worktop_contains(first_amount, first_resource)
+            .assert_worktop_contains(last_amount, last_resource)
+            .call_method_with_all_resources(user.account, "deposit_batch")
+            .build(vec![user.key])
+            .unwrap();
+        let receipt = self.executor.run(transaction).unwrap();
+        println!("{:?}\n", receipt);
+        receipt.result.is_ok()
+    }
+
+    fn query<T: Decode>(&mut self, method: &str, args: Vec<String>) -> T {
+        let transaction = TransactionBuilder::new(&self.executor)
+            .call_method(self.dex, method, args, None)
+            .build(vec![])
+            .unwrap();
+        let receipt = self.executor.run(transaction).unwrap();
+        println!("{:?}\n", receipt);
+        assert!(receipt.result.is_ok());
+        decode_output(&receipt)
+    }
+
+    // Query with resources of the user's account, ie. a position as reference
+    fn query_as<T: Decode>(&mut self, user: User, method: &str, args: Vec<String>) -> T {
+        let receipt = self.assert_ok(user, method, args);
+        decode_output(&receipt)
+    }
+}
+
+// The outputs also hold the buckets withdrawn from the account, take the first one of type T
+fn decode_output<T: Decode>(receipt: &Receipt) -> T {
+    receipt
+        .outputs
+        .iter()
+        .find_map(|output| scrypto_decode::<T>(&output.raw).ok())
+        .unwrap()
+}
@@ -1,5 +1,75 @@
 use scrypto::prelude::*;
 
+// LP tokens locked forever when the pool is created, so the LP token supply never goes back to zero and
+// inflating the value of an LP token (ie. with add_fees) costs at least a thousand times the gain
+fn minimum_liquidity() -> Decimal {
+    Decimal(1000) // 1e-15
+}
+
+// LP tokens to mint for `amount` deposited in a pool worth `value` with `supply` LP tokens.
+// Rounded down so a deposit never gets more than its share.
+fn shares_for_deposit(amount: Decimal, supply: Decimal, value: Decimal) -> Decimal {
+    amount * supply / value
+}
+
+// Part of `amount` returned for `shares` LP tokens out of `supply`.
+// Rounded down so a withdrawal never takes more than its share.
+fn amount_for_shares(shares: Decimal, supply: Decimal, amount: Decimal) -> Decimal {
+    shares * amount / supply
+}
+
+// Amounts held by the pool, the LP token supply and the amount lent.
+// add_liquidity and remove_liquidity move the vaults and LP tokens as computed here.
+#[derive(Clone, Copy, Debug)]
+struct PoolState {
+    pool: Decimal,
+    fees: Decimal,
+    supply: Decimal,
+    lent: Decimal
+}
+
+impl PoolState {
+    // fees belong to the LP token holders too
+    fn value(&self) -> Decimal {
+        self.pool + self.fees
+    }
+
+    // State after depositing `amount` and the LP tokens to mint for it
+    fn add_liquidity(self, amount: Decimal, min_shares_out: Decimal) -> Result<(PoolState, Decimal), String> {
+        // while a loan is out the pool is worth more than it holds
+        if self.lent != Decimal::zero() {
+            return Err("Liquidity can't change during a loan".to_owned());
+        }
+        let shares = shares_for_deposit(amount, self.supply, self.value());
+        if shares == Decimal::zero() {
+            return Err("Deposit too small to get any LP token".to_owned());
+        }
+        if shares < min_shares_out {
+            return Err(format!("Deposit would only get {} LP tokens", shares));
+        }
+        Ok((PoolState { pool: self.pool + amount, supply: self.supply + shares, ..self }, shares))
+    }
+
+    // State after burning `shares` LP tokens and the amounts to return from the fees and the pool
+    fn remove_liquidity(self, shares: Decimal, min_amount_out: Decimal) -> Result<(PoolState, Decimal, Decimal), String> {
+        if self.lent != Decimal::zero() {
+            return Err("Liquidity can't change during a loan".to_owned());
+        }
+        let from_fees = amount_for_shares(shares, self.supply, self.fees);
+        let from_pool = amount_for_shares(shares, self.supply, self.pool);
+        if from_fees + from_pool < min_amount_out {
+            return Err(format!("LP tokens would only return {}", from_fees + from_pool));
+        }
+        let state = PoolState {
+            pool: self.pool - from_pool,
+            fees: self.fees - from_fees,
+            supply: self.supply - shares,
+            lent: self.lent
+        };
+        Ok((state, from_fees, from_pool))
+    }
+}
+
 /*
  * Liquidity pool with a single token and fees collection mechanism.
  * This component is not meant to be used by itself. 
@@ -10,11 +80,41 @@ blueprint! {
         pool: Vault,
         fees: Vault,
         lp_minter_badge: Vault,
-        lp_token: ResourceDef
+        lp_token: ResourceDef,
+        // Minimum liquidity, never withdrawn
+        locked_lp: Vault,
+        // Badge allowed to borrow from the pool, ie. a flash loan component
+        lender_badge: ResourceDef,
+        // Amount borrowed and not repaid yet
+        lent: Decimal
     }
 
     impl SingleTokenLiquidityPool {
-        pub fn new(initial_funds: Bucket, lp_initial_supply: Decimal) -> Component {
+        // Returns the LP tokens for the initial funds, one per token minus the locked minimum liquidity
+        pub fn new(initial_funds: Bucket) -> (Component, Bucket) {
+            // Nobody can borrow from this pool
+            let lender_badge = ResourceBuilder::new_fungible(DIVISIBILITY_NONE)
+                .metadata("name", "Pool Lender Badge")
+                .no_initial_supply();
+
+            Self::instantiate_pool(initial_funds, lender_badge)
+        }
+
+        /*
+         * Same as new but also returns a badge allowed to borrow the pool liquidity with `borrow` and `repay`,
+         * to be given to a flash loan component.
+         */
+        pub fn new_with_lender(initial_funds: Bucket) -> (Component, Bucket, Bucket) {
+            let lender_badge: Bucket = ResourceBuilder::new_fungible(DIVISIBILITY_NONE)
+                .metadata("name", "Pool Lender Badge")
+                .initial_supply_fungible(1);
+
+            let (component, lp_tokens) = Self::instantiate_pool(initial_funds, lender_badge.resource_def());
+            (component, lp_tokens, lender_badge)
+        }
+
+        fn instantiate_pool(initial_funds: Bucket, lender_badge: ResourceDef) -> (Component, Bucket) {
+            assert!(initial_funds.amount() > minimum_liquidity(), "Initial funds must be more than {}", minimum_liquidity());
             let funds_resource_def = initial_funds.resource_def();
 
             // Create badge that will be used to mint and burn LP tokens
@@ -29,17 +129,22 @@ blueprint! {
                 .badge(lp_minter.resource_def(), MAY_MINT | MAY_BURN)
                 .no_initial_supply();
 
-            // Mint initial LP tokens
-            lp_token.mint(lp_initial_supply, lp_minter.present());
+            // Mint initial LP tokens, one per token of the initial funds
+            let lp_tokens = lp_token.mint(initial_funds.amount(), lp_minter.present());
+            let locked_lp = Vault::with_bucket(lp_tokens.take(minimum_liquidity()));
 
-            Self {
+            let component = Self {
                 pool: Vault::with_bucket(initial_funds),
                 fees: Vault::new(funds_resource_def),
                 lp_minter_badge: Vault::with_bucket(lp_minter),
-                lp_token: lp_token
-
+                lp_token: lp_token,
+                locked_lp: locked_lp,
+                lender_badge: lender_badge,
+                lent: Decimal::zero()
             }
-            .instantiate()
+            .instantiate();
+
+            (component, lp_tokens)
         }
 
         // Will be called by other components
@@ -47,23 +152,58 @@ blueprint! {
             self.fees.put(fees);
         }
 
-        // Contribute tokens to the pool in exchange of an LP token
-        pub fn add_liquidity(&self, liquidity: Bucket) -> Bucket {
-            let pool_share = liquidity.amount() / self.pool.amount();
+        // Lend tokens of the pool, the lender is responsible for getting them back with `repay`
+        #[auth(lender_badge)]
+        pub fn borrow(&mut self, amount: Decimal) -> Bucket {
+            assert!(amount <= self.pool.amount(), "Not enough liquidity in the pool");
+            self.lent = self.lent + amount;
+            self.pool.take(amount)
+        }
+
+        // Give back borrowed tokens, interest should go through add_fees
+        #[auth(lender_badge)]
+        pub fn repay(&mut self, principal: Bucket) {
+            assert!(principal.amount() <= self.lent, "Repaying more than was lent");
+            self.lent = self.lent - principal.amount();
+            self.pool.put(principal);
+        }
+
+        pub fn get_liquidity(&self) -> Decimal {
+            self.pool.amount()
+        }
+
+        pub fn get_fees(&self) -> Decimal {
+            self.fees.amount()
+        }
+
+        fn state(&self) -> PoolState {
+            PoolState {
+                pool: self.pool.amount(),
+                fees: self.fees.amount(),
+                supply: self.lp_token.total_supply(),
+                lent: self.lent
+            }
+        }
+
+        // Contribute tokens to the pool in exchange of at least min_shares_out LP tokens
+        pub fn add_liquidity(&self, liquidity: Bucket, min_shares_out: Decimal) -> Bucket {
+            assert!(liquidity.resource_def() == self.pool.resource_def(), "Wrong token !");
+            let (_, shares) = self.state().add_liquidity(liquidity.amount(), min_shares_out)
+                .unwrap_or_else(|err| panic!("{}", err));
+
             self.pool.put(liquidity);
 
             // Return newly minted lp_tokens
             self.lp_minter_badge.authorize(|badge| {
-                self.lp_token
-                    .mint(self.lp_token.total_supply() * pool_share, badge)
+                self.lp_token.mint(shares, badge)
             })
         }
 
-        // Give LP token back to get portion of the pool and fees
-        pub fn remove_liquidity(&self, lp_tokens: Bucket) -> Bucket {
+        // Give LP token back to get portion of the pool and fees, at least min_amount_out in total
+        pub fn remove_liquidity(&self, lp_tokens: Bucket, min_amount_out: Decimal) -> Bucket {
             assert!(lp_tokens.resource_def() == self.lp_token, "Wrong LP token !");
-
-            let share = lp_tokens.amount() / self.lp_token.total_supply();
+            let (_, from_fees, from_pool) = self.state().remove_liquidity(lp_tokens.amount(), min_amount_out)
+                .unwrap_or_else(|err| panic!("{}", err));
             
             // Burn the provided LP token
             self.lp_minter_badge.authorize(|badge| {
@@ -71,9 +211,130 @@ blueprint! {
             });
 
             // Return the share of fees and pool
-            let return_bucket = self.fees.take(self.fees.amount() * share);
-            return_bucket.put(self.pool.take(self.pool.amount() * share));
+            let return_bucket = self.fees.take(from_fees);
+            return_bucket.put(self.pool.take(from_pool));
             return_bucket
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // small deterministic generator so the fuzz tests are repeatable without extra dependencies
+    struct Lcg(u64);
+    impl Lcg {
+        fn next(&mut self, max: u64) -> u64 {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (self.0 >> 33) % max
+        }
+
+        // amounts from 1e-18 up to about 1e6 so rounding matters at every scale
+        fn next_amount(&mut self) -> Decimal {
+            let digits = self.next(24) as u32;
+            Decimal(self.next(1_000_000) as i128 * 10i128.pow(digits) / 1_000_000 + 1)
+        }
+    }
+
+    // a * b as a 256 bits number (high, low), to compare share prices exactly
+    fn mul_wide(a: Decimal, b: Decimal) -> (u128, u128) {
+        let (a, b) = (a.0 as u128, b.0 as u128);
+        let mask = u64::MAX as u128;
+        let (a_hi, a_lo, b_hi, b_lo) = (a >> 64, a & mask, b >> 64, b & mask);
+        let lo_lo = a_lo * b_lo;
+        let cross = (lo_lo >> 64) + (a_hi * b_lo & mask) + (a_lo * b_hi & mask);
+        let high = a_hi * b_hi + (a_hi * b_lo >> 64) + (a_lo * b_hi >> 64) + (cross >> 64);
+        (high, (cross << 64) | (lo_lo & mask))
+    }
+
+    // the state of a pool created with initial_funds, with its locked minimum liquidity
+    fn new_pool(initial_funds: Decimal) -> PoolState {
+        PoolState { pool: initial_funds, fees: Decimal::zero(), supply: initial_funds, lent: Decimal::zero() }
+    }
+
+    fn deposit(pool: &mut PoolState, amount: Decimal) -> Option<Decimal> {
+        let (state, shares) = pool.add_liquidity(amount, Decimal::zero()).ok()?;
+        *pool = state;
+        Some(shares)
+    }
+
+    fn withdraw(pool: &mut PoolState, shares: Decimal) -> Decimal {
+        let (state, from_fees, from_pool) = pool.remove_liquidity(shares, Decimal::zero()).unwrap();
+        *pool = state;
+        from_fees + from_pool
+    }
+
+    #[test]
+    fn test_fuzz_no_lp_extracts_more_than_its_share() {
+        let mut rng = Lcg(24);
+        for _ in 0..200 {
+            let mut pool = new_pool(minimum_liquidity() + rng.next_amount());
+            let mut holders = vec![pool.supply - minimum_liquidity()];
+            for _ in 0..100 {
+                let (value_before, supply_before) = (pool.value(), pool.supply);
+                match rng.next(4) {
+                    0 => {
+                        if let Some(shares) = deposit(&mut pool, rng.next_amount()) {
+                            holders.push(shares);
+                        }
+                    }
+                    1 => {
+                        let i = rng.next(holders.len() as u64) as usize;
+                        let shares = holders[i] * Decimal::from(rng.next(101)) / Decimal::from(100);
+                        let returned = withdraw(&mut pool, shares);
+                        // returned / shares <= value / supply
+                        assert!(mul_wide(returned, supply_before) <= mul_wide(shares, value_before),
+                            "{} LP tokens out of {} returned {} from a pool worth {}", shares, supply_before, returned, value_before);
+                        holders[i] = holders[i] - shares;
+                    }
+                    2 => pool.fees = pool.fees + rng.next_amount(),
+                    _ => {
+                        let amount = rng.next_amount();
+                        if let Some(shares) = deposit(&mut pool, amount) {
+                            let returned = withdraw(&mut pool, shares);
+                            assert!(returned <= amount, "deposit {} then withdraw returned {}", amount, returned);
+                        }
+                    }
+                }
+                // the value of a LP token never goes down, so no LP is paid with the others' share
+                assert!(mul_wide(pool.value(), supply_before) >= mul_wide(value_before, pool.supply),
+                    "LP token value went from {}/{} to {}/{}", value_before, supply_before, pool.value(), pool.supply);
+                assert!(pool.supply >= minimum_liquidity());
+            }
+        }
+    }
+
+    #[test]
+    fn test_liquidity_checks() {
+        let pool = new_pool(Decimal::from(100));
+        let err = pool.add_liquidity(Decimal::from(10), Decimal::from(11)).unwrap_err();
+        assert!(err.starts_with("Deposit would only get"), "{}", err);
+        let err = pool.remove_liquidity(Decimal::from(10), Decimal::from(11)).unwrap_err();
+        assert!(err.starts_with("LP tokens would only return"), "{}", err);
+
+        // nothing changes while a loan is out, even with the right amounts
+        let lent = PoolState { pool: Decimal::from(90), lent: Decimal::from(10), ..pool };
+        assert_eq!(lent.add_liquidity(Decimal::from(10), Decimal::zero()).unwrap_err(), "Liquidity can't change during a loan");
+        assert_eq!(lent.remove_liquidity(Decimal::from(10), Decimal::zero()).unwrap_err(), "Liquidity can't change during a loan");
+    }
+
+    #[test]
+    fn test_inflating_lp_token_costs_more_than_it_takes() {
+        // creator puts the bare minimum then donates fees to make one LP token unit worth about 1 token
+        let donation = Decimal::from(1000);
+        let mut pool = new_pool(minimum_liquidity() + Decimal(1));
+        pool.fees = donation;
+        // small deposits get no LP token, add_liquidity rejects them instead of taking the tokens
+        assert_eq!(deposit(&mut pool, Decimal::from_str("0.1").unwrap()), None);
+        // bigger ones lose less than the value of one LP token unit
+        let amount = Decimal::from(10);
+        let unit_value = pool.value() / pool.supply * Decimal(1);
+        let shares = deposit(&mut pool, amount).unwrap();
+        let returned = withdraw(&mut pool, shares);
+        assert!(amount - returned < unit_value, "lost {}", amount - returned);
+        // but most of the donation stays with the locked minimum liquidity
+        let creator_returned = withdraw(&mut pool, Decimal(1));
+        assert!(creator_returned + amount - returned < donation / Decimal::from(100), "creator got {}", creator_returned);
+    }
+}
@@ -0,0 +1,84 @@
+use radix_engine::ledger::*;
+use radix_engine::transaction::*;
+use scrypto::prelude::*;
+
+struct TestPool {
+    key: Address,
+    account: Address,
+    component: Address,
+    lender_badge: Address,
+    lp_token: Address,
+}
+
+// pool of 10000 XRD with a lender badge
+fn setup<'a, L: Ledger>(executor: &mut TransactionExecutor<'a, L>) -> TestPool {
+    let key = executor.new_public_key();
+    let account = executor.new_account(key);
+    let package = executor.publish_package(include_code!("single_token_liquidity_pool"));
+
+    let transaction = TransactionBuilder::new(executor)
+        .call_function(package, "SingleTokenLiquidityPool", "new_with_lender", vec![format!("10000,{}", RADIX_TOKEN)], Some(account))
+        .drop_all_bucket_refs()
+        .deposit_all_buckets(account)
+        .build(vec![key])
+        .unwrap();
+    let receipt = executor.run(transaction, false).unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.success);
+
+    TestPool {
+        key,
+        account,
+        component: receipt.component(0).unwrap(),
+        lender_badge: receipt.resource_def(0).unwrap(),
+        lp_token: receipt.resource_def(2).unwrap(),
+    }
+}
+
+fn call<'a, L: Ledger>(executor: &mut TransactionExecutor<'a, L>, pool: &TestPool, method: &str, args: Vec<String>) -> bool {
+    let transaction = TransactionBuilder::new(executor)
+        .call_method(pool.component, method, args, Some(pool.account))
+        .drop_all_bucket_refs()
+        .deposit_all_buckets(pool.account)
+        .build(vec![pool.key])
+        .unwrap();
+    let receipt = executor.run(transaction, false).unwrap();
+    println!("{:?}\n", receipt);
+    receipt.success
+}
+
+fn get_decimal<'a, L: Ledger>(executor: &mut TransactionExecutor<'a, L>, pool: &TestPool, method: &str) -> Decimal {
+    let transaction = TransactionBuilder::new(executor)
+        .call_method(pool.component, method, vec![], Some(pool.account))
+        .build(vec![pool.key])
+        .unwrap();
+    let mut receipt = executor.run(transaction, false).unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.success);
+    scrypto_decode(&receipt.results.swap_remove(0).unwrap().unwrap().encoded).unwrap()
+}
+
+#[test]
+fn test_liquidity_cant_change_while_lent() {
+    let mut ledger = InMemoryLedger::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, 0, 0);
+    let pool = setup(&mut executor);
+
+    // only the lender can borrow
+    assert!(!call(&mut executor, &pool, "borrow", vec!["1000".to_owned()]));
+    assert!(call(&mut executor, &pool, "borrow", vec!["1000".to_owned(), format!("1,{}", pool.lender_badge)]));
+    assert_eq!(get_decimal(&mut executor, &pool, "get_liquidity"), 9000.into());
+
+    // the pool is worth more than it holds until the loan is repaid
+    assert!(!call(&mut executor, &pool, "add_liquidity", vec![format!("500,{}", RADIX_TOKEN), "0".to_owned()]));
+    assert!(!call(&mut executor, &pool, "remove_liquidity", vec![format!("100,{}", pool.lp_token), "0".to_owned()]));
+    assert_eq!(get_decimal(&mut executor, &pool, "get_liquidity"), 9000.into());
+
+    // no more than the loan can be repaid
+    assert!(!call(&mut executor, &pool, "repay", vec![format!("1001,{}", RADIX_TOKEN), format!("1,{}", pool.lender_badge)]));
+    assert!(call(&mut executor, &pool, "repay", vec![format!("1000,{}", RADIX_TOKEN), format!("1,{}", pool.lender_badge)]));
+
+    assert!(call(&mut executor, &pool, "add_liquidity", vec![format!("500,{}", RADIX_TOKEN), "500".to_owned()]));
+    assert!(call(&mut executor, &pool, "remove_liquidity", vec![format!("100,{}", pool.lp_token), "100".to_owned()]));
+    assert_eq!(get_decimal(&mut executor, &pool, "get_liquidity"), 10400.into());
+}
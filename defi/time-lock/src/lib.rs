@@ -1,13 +1,54 @@
 use scrypto::prelude::*;
 
+/// Vesting schedule of a TL badge: nothing can be released until the cliff is over,
+/// then the locked amount vests linearly from the lock epoch to the end epoch.
+#[derive(Debug, Clone, TypeId, Encode, Decode, Describe)]
+pub struct VestingSchedule {
+    // Locked amount, after fees
+    pub total: Decimal,
+    // Amount not released yet
+    pub remaining: Decimal,
+    pub start_epoch: u64,
+    pub cliff_epoch: u64,
+    pub end_epoch: u64,
+}
+
+impl VestingSchedule {
+    pub fn new(total: Decimal, start_epoch: u64, cliff_epochs: u64, vesting_epochs: u64) -> Self {
+        Self {
+            total: total,
+            remaining: total,
+            start_epoch: start_epoch,
+            cliff_epoch: start_epoch + cliff_epochs,
+            end_epoch: start_epoch + vesting_epochs,
+        }
+    }
+
+    /// Amount vested at `epoch`, released or not.
+    pub fn vested(&self, epoch: u64) -> Decimal {
+        if epoch <= self.cliff_epoch {
+            Decimal::zero()
+        } else if epoch >= self.end_epoch {
+            self.total
+        } else {
+            // rounded down, the rest is released at the end
+            self.total * Decimal::from(epoch - self.start_epoch) / Decimal::from(self.end_epoch - self.start_epoch)
+        }
+    }
+
+    /// Amount that can be released at `epoch`.
+    pub fn releasable(&self, epoch: u64) -> Decimal {
+        self.vested(epoch) - (self.total - self.remaining)
+    }
+}
 
 blueprint! {
     struct TimeLock {
         /// Mint authorization to TL badges.
         tl_minter_vault: Vault,
         tl_minter_badge: ResourceDef,
-        // Minted badges
-        minted: HashMap<Address, (Decimal, u64)>,
+        // Vesting schedule of each minted badge
+        minted: HashMap<Address, VestingSchedule>,
 
         // Collected fees in XRD.
         collected_fees: Vault,
@@ -45,16 +86,21 @@ blueprint! {
 
         
         /// Lock XRD for a certain time.
-        pub fn lock(&mut self, lock_tokens: Bucket, duration: u64) -> Bucket{
+        pub fn lock(&mut self, lock_tokens: Bucket, duration: u64) -> Bucket {
+            assert!(duration != 0, "You cannot lock with a zero duration");
+
+            // Everything vests at once after the duration
+            self.lock_vesting(lock_tokens, duration, duration)
+        }
+
+        /// Lock XRD released linearly over vesting_epochs, nothing can be released before cliff_epochs.
+        pub fn lock_vesting(&mut self, lock_tokens: Bucket, cliff_epochs: u64, vesting_epochs: u64) -> Bucket {
 
             let amount = lock_tokens.amount();
 
             assert!(amount != Decimal::zero(), "You cannot lock zero amount");
-            assert!(duration != 0, "You cannot lock with a zero duration");
-
-            
-            // Setup the end time.
-            let end_time = Context::current_epoch() + duration;
+            assert!(vesting_epochs != 0, "You cannot lock with a zero vesting duration");
+            assert!(cliff_epochs <= vesting_epochs, "The cliff cannot be after the end of the vesting");
 
             // fees calculation
             let fee_amount = amount * self.fee_percent/100;
@@ -66,11 +112,15 @@ blueprint! {
             // Put fees in collected XRD.
             self.collected_fees.put(fee_tokens);
 
-            // Mint TL badge with locked amount and end epoch as metadata
+            // Setup the schedule.
+            let schedule = VestingSchedule::new(available, Context::current_epoch(), cliff_epochs, vesting_epochs);
+
+            // Mint TL badge with locked amount, cliff and end epochs as metadata
             let tl_resource_def = ResourceBuilder::new_fungible(DIVISIBILITY_MAXIMUM)
                 .metadata("name", "Time lock badge")
                 .metadata("amount", available.to_string())
-                .metadata("ends", end_time.to_string())
+                .metadata("cliff", schedule.cliff_epoch.to_string())
+                .metadata("ends", schedule.end_epoch.to_string())
                 .flags(MINTABLE | BURNABLE)
                 .badge(self.tl_minter_vault.resource_def(), MAY_MINT | MAY_BURN)
                 .no_initial_supply();
@@ -81,40 +131,52 @@ blueprint! {
             });
 
             // store new badge address
-            self.minted.insert(tl_resource_def.address(), (available, end_time));
+            self.minted.insert(tl_resource_def.address(), schedule);
 
             // put the rest amount of tokens to the locked vault
             self.locked_xrd.put(lock_tokens);
             tl_badge
         }
 
-        pub fn release(&mut self, tl_badge: Bucket) -> Bucket {
+        /// Release the XRD vested so far, returns them with the TL badge.
+        /// The badge is burned once everything is released and an empty bucket is returned instead.
+        pub fn release(&mut self, tl_badge: Bucket) -> (Bucket, Bucket) {
             let resource_def = tl_badge.resource_def();
-    
-            let returns = Bucket::new(RADIX_TOKEN);
-            match self.minted.get(&resource_def.address()) {
-                Some(&value) => {
-                info!("current epoch {}", Context::current_epoch());
-                assert!(Context::current_epoch() > value.1, "Release time not yet over, wait for a bit longer"); 
-                assert!(value.0 > Decimal::zero(), "Release amount is zero");
+            assert!(tl_badge.amount() == Decimal::one(), "A whole TL badge is needed to release");
+
+            let mut schedule = match self.minted.get(&resource_def.address()) {
+                Some(schedule) => schedule.clone(),
+                None => panic!("no mints found with provided badge")
+            };
+            info!("current epoch {}", Context::current_epoch());
+            assert!(Context::current_epoch() > schedule.cliff_epoch, "Release time not yet over, wait for a bit longer");
 
+            let amount = schedule.releasable(Context::current_epoch());
+            assert!(amount > Decimal::zero(), "Release amount is zero");
+            schedule.remaining = schedule.remaining - amount;
+
+            let returns = self.locked_xrd.take(amount);
+            if schedule.remaining == Decimal::zero() {
                 // Burn the TL badge
                 self.tl_minter_vault.authorize(|badge| {
                     tl_badge.burn_with_auth(badge);
                 });
                 // update mapping
                 self.minted.remove(&resource_def.address());
-
-                returns.put(self.locked_xrd.take(value.0));
-                },
-                _ => {
-                    info!("no mints found with provided badge");
-                    std::process::abort();
-                }
+                return (returns, Bucket::new(resource_def));
             }
-            
+
+            self.minted.insert(resource_def.address(), schedule);
             // Return the withdrawn tokens
-            returns
+            (returns, tl_badge)
+        }
+
+        /// Schedule of a TL badge.
+        pub fn get_schedule(&self, tl_badge: Address) -> VestingSchedule {
+            match self.minted.get(&tl_badge) {
+                Some(schedule) => schedule.clone(),
+                None => panic!("no mints found with provided badge")
+            }
         }
 
         // #[auth(tl_minter_badge)]
@@ -122,4 +184,41 @@ blueprint! {
             self.collected_fees.take_all()
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_lock_vests_everything_after_the_duration() {
+        let schedule = VestingSchedule::new(100.into(), 10, 100, 100);
+        assert_eq!(schedule.vested(110), Decimal::zero());
+        assert_eq!(schedule.vested(111), 100.into());
+    }
+
+    #[test]
+    fn test_cliff_then_linear_vesting() {
+        let mut schedule = VestingSchedule::new(1000.into(), 0, 25, 100);
+        assert_eq!(schedule.releasable(25), Decimal::zero());
+        assert_eq!(schedule.releasable(26), 260.into());
+        schedule.remaining = schedule.remaining - schedule.releasable(26);
+        assert_eq!(schedule.releasable(26), Decimal::zero());
+        assert_eq!(schedule.releasable(50), 240.into());
+        assert_eq!(schedule.releasable(1000), 740.into());
+    }
+
+    #[test]
+    fn test_rounding_is_released_at_the_end() {
+        let mut schedule = VestingSchedule::new(Decimal(10), 0, 0, 3);
+        let mut released = Decimal::zero();
+        for epoch in 1..=3 {
+            let amount = schedule.releasable(epoch);
+            schedule.remaining = schedule.remaining - amount;
+            released = released + amount;
+            assert!(released <= schedule.vested(epoch));
+        }
+        assert_eq!(released, Decimal(10));
+        assert_eq!(schedule.remaining, Decimal::zero());
+    }
+}
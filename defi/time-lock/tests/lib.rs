@@ -0,0 +1,126 @@
+use radix_engine::ledger::*;
+use radix_engine::transaction::*;
+use scrypto::prelude::*;
+use time_lock::VestingSchedule;
+
+struct TestTimeLock {
+    key: Address,
+    account: Address,
+    component: Address,
+}
+
+// time lock with a 0.5% fee
+fn setup<'a, L: Ledger>(executor: &mut TransactionExecutor<'a, L>) -> TestTimeLock {
+    let key = executor.new_public_key();
+    let account = executor.new_account(key);
+    let package = executor.publish_package(include_code!("time_lock"));
+
+    executor.set_current_epoch(0);
+
+    let transaction = TransactionBuilder::new(executor)
+        .call_function(package, "TimeLock", "new", vec!["0.5".to_owned()], Some(account))
+        .drop_all_bucket_refs()
+        .deposit_all_buckets(account)
+        .build(vec![key])
+        .unwrap();
+    let receipt = executor.run(transaction, false).unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.success);
+
+    TestTimeLock {
+        key,
+        account,
+        component: receipt.component(0).unwrap(),
+    }
+}
+
+fn run<'a, L: Ledger>(executor: &mut TransactionExecutor<'a, L>, time_lock: &TestTimeLock, method: &str, args: Vec<String>) -> Receipt {
+    let transaction = TransactionBuilder::new(executor)
+        .call_method(time_lock.component, method, args, Some(time_lock.account))
+        .drop_all_bucket_refs()
+        .deposit_all_buckets(time_lock.account)
+        .build(vec![time_lock.key])
+        .unwrap();
+    let receipt = executor.run(transaction, false).unwrap();
+    println!("{:?}\n", receipt);
+    receipt
+}
+
+fn call<'a, L: Ledger>(executor: &mut TransactionExecutor<'a, L>, time_lock: &TestTimeLock, method: &str, args: Vec<String>) -> bool {
+    run(executor, time_lock, method, args).success
+}
+
+// locks XRD and returns the TL badge
+fn lock<'a, L: Ledger>(executor: &mut TransactionExecutor<'a, L>, time_lock: &TestTimeLock, method: &str, args: Vec<String>) -> Address {
+    let receipt = run(executor, time_lock, method, args);
+    assert!(receipt.success);
+    receipt.resource_def(0).unwrap()
+}
+
+fn get_schedule<'a, L: Ledger>(executor: &mut TransactionExecutor<'a, L>, time_lock: &TestTimeLock, tl_badge: Address) -> Option<VestingSchedule> {
+    let mut receipt = run(executor, time_lock, "get_schedule", vec![tl_badge.to_string()]);
+    if !receipt.success {
+        return None;
+    }
+    Some(scrypto_decode(&receipt.results.swap_remove(0).unwrap().unwrap().encoded).unwrap())
+}
+
+fn remaining<'a, L: Ledger>(executor: &mut TransactionExecutor<'a, L>, time_lock: &TestTimeLock, tl_badge: Address) -> Decimal {
+    get_schedule(executor, time_lock, tl_badge).unwrap().remaining
+}
+
+#[test]
+fn test_vesting_release() {
+    let mut ledger = InMemoryLedger::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, 0, 0);
+    let time_lock = setup(&mut executor);
+
+    // 1000 XRD vesting over 100 epochs with a 25 epochs cliff, 995 XRD after the fee
+    let tl_badge = lock(&mut executor, &time_lock, "lock_vesting", vec![format!("1000,{}", RADIX_TOKEN), "25".to_owned(), "100".to_owned()]);
+    let schedule = get_schedule(&mut executor, &time_lock, tl_badge).unwrap();
+    assert_eq!((schedule.total, schedule.remaining), (995.into(), 995.into()));
+    assert_eq!((schedule.start_epoch, schedule.cliff_epoch, schedule.end_epoch), (0, 25, 100));
+
+    // nothing before the end of the cliff
+    executor.set_current_epoch(25);
+    assert!(!call(&mut executor, &time_lock, "release", vec![format!("1,{}", tl_badge)]));
+    assert_eq!(remaining(&mut executor, &time_lock, tl_badge), 995.into());
+
+    // half of the XRD at half of the vesting, only with the whole badge
+    executor.set_current_epoch(50);
+    assert!(!call(&mut executor, &time_lock, "release", vec![format!("0.5,{}", tl_badge)]));
+    assert!(call(&mut executor, &time_lock, "release", vec![format!("1,{}", tl_badge)]));
+    assert_eq!(remaining(&mut executor, &time_lock, tl_badge), Decimal::from_str("497.5").unwrap());
+
+    // the badge came back but nothing more vested in the same epoch
+    assert!(!call(&mut executor, &time_lock, "release", vec![format!("1,{}", tl_badge)]));
+    assert_eq!(remaining(&mut executor, &time_lock, tl_badge), Decimal::from_str("497.5").unwrap());
+
+    // the rest at the end, the badge is burned
+    executor.set_current_epoch(100);
+    assert!(call(&mut executor, &time_lock, "release", vec![format!("1,{}", tl_badge)]));
+    assert!(get_schedule(&mut executor, &time_lock, tl_badge).is_none());
+    assert!(!call(&mut executor, &time_lock, "release", vec![format!("1,{}", tl_badge)]));
+
+    // the fees of the lock
+    assert!(call(&mut executor, &time_lock, "claim", vec![]));
+}
+
+#[test]
+fn test_lock_releases_everything_after_the_duration() {
+    let mut ledger = InMemoryLedger::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, 0, 0);
+    let time_lock = setup(&mut executor);
+
+    assert!(!call(&mut executor, &time_lock, "lock", vec![format!("100,{}", RADIX_TOKEN), "0".to_owned()]));
+    let tl_badge = lock(&mut executor, &time_lock, "lock", vec![format!("100,{}", RADIX_TOKEN), "100".to_owned()]);
+    assert_eq!(remaining(&mut executor, &time_lock, tl_badge), Decimal::from_str("99.5").unwrap());
+
+    executor.set_current_epoch(100);
+    assert!(!call(&mut executor, &time_lock, "release", vec![format!("1,{}", tl_badge)]));
+
+    // everything at once, so the badge is burned by the first release
+    executor.set_current_epoch(101);
+    assert!(call(&mut executor, &time_lock, "release", vec![format!("1,{}", tl_badge)]));
+    assert!(get_schedule(&mut executor, &time_lock, tl_badge).is_none());
+}
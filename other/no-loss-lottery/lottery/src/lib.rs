@@ -1,76 +1,7 @@
 use scrypto::prelude::*;
 
-import! { r#"
-{
-  "package": "013fa22e238526e9c82376d2b4679a845364243bf970e5f783d13f",
-  "name": "Staking",
-  "functions": [
-    {
-      "name": "new",
-      "inputs": [
-        {
-          "type": "Custom",
-          "name": "scrypto::resource::Bucket",
-          "generics": []
-        }
-      ],
-      "output": {
-        "type": "Custom",
-        "name": "scrypto::core::Component",
-        "generics": []
-      }
-    }
-  ],
-  "methods": [
-    {
-      "name": "new_user",
-      "mutability": "Mutable",
-      "inputs": [],
-      "output": {
-        "type": "Custom",
-        "name": "scrypto::resource::Bucket",
-        "generics": []
-      }
-    },
-    {
-      "name": "stake",
-      "mutability": "Mutable",
-      "inputs": [
-        {
-          "type": "Custom",
-          "name": "scrypto::resource::Bucket",
-          "generics": []
-        },
-        {
-          "type": "Custom",
-          "name": "scrypto::resource::BucketRef",
-          "generics": []
-        }
-      ],
-      "output": {
-        "type": "Unit"
-      }
-    },
-    {
-      "name": "withdraw",
-      "mutability": "Mutable",
-      "inputs": [
-        {
-          "type": "Custom",
-          "name": "scrypto::resource::BucketRef",
-          "generics": []
-        }
-      ],
-      "output": {
-        "type": "Custom",
-        "name": "scrypto::resource::Bucket",
-        "generics": []
-      }
-    }
-  ]
-}
-"# }
-
+mod staking; // imported Staking blueprint for cross-blueprint calls to the Staking component
+pub use staking::Staking;
 
 #[derive(NftData)]
 pub struct LotteryTicketData {
@@ -102,7 +33,14 @@ pub struct LotteryData {
     reward: Decimal,
     // winner ticket id after lottery end
     #[scrypto(mutable)]
-    winner: Option<u128>
+    winner: Option<u128>,
+    // sha256 hash of the operator secret, revealed when the lottery ends
+    commitment: String,
+    // last epoch when the operator can reveal the secret
+    reveal_epoch: u64,
+    // entropy collected from the ticket buyers
+    #[scrypto(mutable)]
+    entropy: String
 }
 
 blueprint! {
@@ -115,10 +53,8 @@ blueprint! {
         lottery_minter: Vault,
         // address of staking contract
         staking_address: Address,
-        // staking user NFT
-        staking_token: Vault,
-        // track staking amount
-        current_staking: Decimal,
+        // staking user NFT per lottery, each lottery has its own staking position
+        staking_tokens: HashMap<u128, Vault>,
         // vault that will be used for withdrawals and reward
         assets_vault: Vault,
         // resource def for minting lottery tickets
@@ -164,16 +100,13 @@ blueprint! {
 
             // staking setup
             let staking: Staking = staking_address.into();
-            // register new user and take the staking user nft (STT)
-            let staking_nft = staking.new_user();
 
             let component = Self {
                 staking,
                 admin_badge: admin_resource_def, 
                 lottery_minter: Vault::with_bucket(lottery_minter),
-                staking_token: Vault::with_bucket(staking_nft),
+                staking_tokens: HashMap::new(),
                 staking_address,
-                current_staking: Decimal::zero(),
                 assets_vault: Vault::new(RADIX_TOKEN),
                 ticket_id_counter: 0,
                 lottery_id_counter: 0,
@@ -186,10 +119,13 @@ blueprint! {
         }
 
         // initiate new lottery
+        // commitment is the sha256 hash of the secret that has to be revealed in the end_lottery call before the reveal epoch
         #[auth(admin_badge)]
-        pub fn start_lottery(&mut self, name: String, epoch: u64, price: Decimal) -> Bucket {
+        pub fn start_lottery(&mut self, name: String, epoch: u64, price: Decimal, commitment: String, reveal_epoch: u64) -> Bucket {
             assert!(epoch > 0, "Epoch cannot be zero");
             assert!(!name.is_empty(), "Name cannot be empty");
+            assert!(reveal_epoch > epoch, "Reveal epoch should be after the lottery epoch");
+            assert!(commitment.len() == 64, "Commitment should be a sha256 hash");
 
             // mint lottery
             let data = LotteryData {
@@ -199,90 +135,118 @@ blueprint! {
               minted: Vec::new(),
               ticket_price:price,
               reward: Decimal::zero(),
-              winner: None
+              winner: None,
+              entropy: commitment.clone(),
+              commitment,
+              reveal_epoch
           };
 
           let lottery = self.lottery_minter.authorize(|auth| {
               self.lottery_resource_def.mint_nft(self.lottery_id_counter, data, auth)
           });
 
+          // register new staking user for this lottery and take the staking user nft (STT)
+          let staking_nft = self.staking.new_user();
+          self.staking_tokens.insert(self.lottery_id_counter, Vault::with_bucket(staking_nft));
+
           // increase lottery count
           self.lottery_id_counter += 1;
 
           lottery
         }
 
-        // End lottery by specific ID
+        // End lottery by specific ID revealing the secret for the start_lottery commitment
         #[auth(admin_badge)]
-        pub fn end_lottery(&mut self, lottery_id: u128) {
+        pub fn end_lottery(&mut self, lottery_id: u128, secret: String) {
 
             // find this lottery
             let mut lottery_data: LotteryData = self.lottery_resource_def.get_nft_data(lottery_id);
+            assert!(!lottery_data.ended, "Lottery is already ended");
             assert!(Context::current_epoch() > lottery_data.lottery_epoch, "Lottery epoch is not ended yet");
+            assert!(Context::current_epoch() <= lottery_data.reveal_epoch, "Reveal epoch is passed, lottery can only be cancelled");
+
+            // check the secret
+            assert!(sha256(secret.as_bytes()).to_string() == lottery_data.commitment, "Secret doesn't match the commitment");
 
             debug!("++++++Lottery Finished++++++");
 
             // pick winner
-            // take hash
-            let hash = Context::transaction_hash().to_string();
+            // take hash of the secret and the entropy of ticket buyers
+            let hash = sha256(format!("{}{}", secret, lottery_data.entropy).as_bytes()).to_string();
             //take first 5 bits of result hash
             let seed = &hash[0..5];
             //convert 10 hex bits to usize
             let result = usize::from_str_radix(&seed, 16).unwrap();
-            // get winner index
-            let index = result % lottery_data.minted.iter().count();
-            // get winner ticket id
-            let winner = lottery_data.minted[index];
-            debug!("Winner ID: {}", winner);
-
-            // save winner
-            lottery_data.winner = Some(winner);
+            if !lottery_data.minted.is_empty() {
+                // get winner index
+                let index = result % lottery_data.minted.iter().count();
+                // get winner ticket id
+                let winner = lottery_data.minted[index];
+                debug!("Winner ID: {}", winner);
+
+                // save winner
+                lottery_data.winner = Some(winner);
+            }
+
+            self.close_lottery(lottery_id, lottery_data);
+
+            debug!("=======================");
+        }
+
+        // Cancel the lottery if the secret wasn't revealed before the reveal epoch
+        // anyone can call it, tickets are refunded together with equal part of the reward
+        pub fn cancel_lottery(&mut self, lottery_id: u128) {
+            let lottery_data: LotteryData = self.lottery_resource_def.get_nft_data(lottery_id);
+            assert!(!lottery_data.ended, "Lottery is already ended");
+            assert!(Context::current_epoch() > lottery_data.reveal_epoch, "Reveal epoch is not passed yet");
+
+            debug!("Lottery cancelled: {}", lottery_id);
+
+            self.close_lottery(lottery_id, lottery_data);
+        }
+
+        // withdraw staking and mark lottery as ended
+        fn close_lottery(&mut self, lottery_id: u128, mut lottery_data: LotteryData) {
             lottery_data.ended = true;
 
             // withdraw staking
-            // get current staking amount
-            let staking_amount = self.current_staking;            
-            self.staking_token.authorize(|auth| {
-                let withdraw = self.staking.withdraw(auth);
-
-                // take the reward from the withdraw amount
-                let reward = withdraw.amount() - staking_amount;
-                debug!("Reward: {}", reward);
-
-                // update lottery data reward params
-                lottery_data.reward = reward;
-
-                // clear staking value
-                self.current_staking = Decimal::zero();
-                self.assets_vault.put(withdraw);
-            });            
+            // get staking amount of this lottery
+            let count = lottery_data.minted.len() as i64;
+            let staking_amount = lottery_data.ticket_price * Decimal::from(count);
+            let staking_token = self.staking_tokens.get(&lottery_id).unwrap();
+            let withdraw = staking_token.authorize(|auth| self.staking.withdraw(auth));
+
+            // take the reward from the withdraw amount
+            let reward = withdraw.amount() - staking_amount;
+            debug!("Reward: {}", reward);
+
+            // update lottery data reward params
+            lottery_data.reward = reward;
+            self.assets_vault.put(withdraw);
                         
             // update lottery NFT
             self.lottery_minter.authorize(|auth| {
                 self.lottery_resource_def.update_nft_data(lottery_id, lottery_data, auth);
             });
-
-            // clear current staking
-            debug!("=======================");
         }
 
         // Buy a ticket for a specific lottery
-        pub fn buy_ticket(&mut self, lottery_id: u128, payment: Bucket) -> (Bucket, Bucket) {
+        // entropy is mixed into the winner selection together with the operator secret
+        pub fn buy_ticket(&mut self, lottery_id: u128, payment: Bucket, entropy: String) -> (Bucket, Bucket) {
           // find this lottery
           let mut lottery_data: LotteryData = self.lottery_resource_def.get_nft_data(lottery_id);
           assert!(!lottery_data.ended, "Lottery is ended");
+          assert!(Context::current_epoch() <= lottery_data.lottery_epoch, "Ticket sales are closed");
 
           assert!(payment.resource_address() == RADIX_TOKEN, "You can only use radix");
           assert!(payment.amount() >= lottery_data.ticket_price, "Not enough amount");
 
-          // send to stake
-          self.staking_token.authorize(|auth| {
+          // send to stake of this lottery
+          let staking_token = self.staking_tokens.get(&lottery_id).unwrap();
+          staking_token.authorize(|auth| {
               self.staking.stake(payment.take(lottery_data.ticket_price), auth);
           });
 
-          // update staking amount
-          self.current_staking += lottery_data.ticket_price;
-
           // mint ticket
           let data = LotteryTicketData {
               lottery_id,
@@ -299,6 +263,7 @@ blueprint! {
           // save minted ticket id for the lottery
           let id = ticket.get_nft_id();
           lottery_data.minted.push(id);
+          lottery_data.entropy = sha256(format!("{}{}", lottery_data.entropy, entropy).as_bytes()).to_string();
           debug!("Minted new ticket: {}", id);
 
           // update lottery NFT
@@ -342,7 +307,13 @@ blueprint! {
                 withdraw.put(self.assets_vault.take(lottery_data.reward));
               }
             },
-            _ => (),
+            None => {
+              // lottery was cancelled, split the reward between all tickets
+              if !lottery_data.minted.is_empty() {
+                let count = lottery_data.minted.len() as i64;
+                withdraw.put(self.assets_vault.take(lottery_data.reward / Decimal::from(count)));
+              }
+            },
           }
 
           // update ticket data
@@ -352,5 +323,16 @@ blueprint! {
 
           withdraw
         }
+
+        // reward and winner ticket of a lottery, the reward is known once the lottery is ended
+        pub fn get_lottery_result(&self, lottery_id: u128) -> (Decimal, Option<u128>) {
+          let lottery_data: LotteryData = self.lottery_resource_def.get_nft_data(lottery_id);
+          (lottery_data.reward, lottery_data.winner)
+        }
+
+        // stake and reward of the ended lotteries that are not withdrawn yet
+        pub fn get_assets(&self) -> Decimal {
+          self.assets_vault.amount()
+        }
     }
 }
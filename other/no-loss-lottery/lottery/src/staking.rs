@@ -0,0 +1,21 @@
+use scrypto::prelude::*;
+
+use bonding_macros::blueprint_import; // generates the ABI JSON for import! from the trait
+
+// the Staking blueprint of staking/src/lib.rs, in the same order
+// methods with #[auth] take the auth BucketRef as the last argument
+// the integration tests compare the generated ABI with the published Staking package
+#[blueprint_import(package = "013fa22e238526e9c82376d2b4679a845364243bf970e5f783d13f")]
+pub trait Staking {
+    fn new(rewards: Bucket, rate: Decimal, end_epoch: u64) -> (Component, Bucket);
+    fn top_up(&mut self, rewards: Bucket, auth: BucketRef);
+    fn extend(&mut self, end_epoch: u64, auth: BucketRef);
+    fn new_user(&mut self) -> Bucket;
+    fn stake(&mut self, staking: Bucket, auth: BucketRef);
+    fn withdraw(&mut self, auth: BucketRef) -> Bucket;
+    fn unstake(&mut self, amount: Decimal, auth: BucketRef) -> Bucket;
+    fn claim_rewards(&mut self, auth: BucketRef) -> Bucket;
+    fn compound(&mut self, auth: BucketRef);
+    fn get_pools(&self) -> (Decimal, Decimal);
+    fn get_staking_data(&self, id: u128) -> (Decimal, Decimal, Decimal);
+}
@@ -0,0 +1,208 @@
+use radix_engine::ledger::*;
+use radix_engine::transaction::*;
+use scrypto::prelude::*;
+
+// Staking package address used by the lottery `import!`
+const STAKING_PACKAGE: &str = "013fa22e238526e9c82376d2b4679a845364243bf970e5f783d13f";
+const SECRET: &str = "lottery secret";
+
+struct TestLottery {
+    staking: Address,
+    key: Address,
+    account: Address,
+    component: Address,
+    admin_badge: Address,
+    ticket_def: Address,
+}
+
+fn setup<'a, L: Ledger>(executor: &mut TransactionExecutor<'a, L>) -> TestLottery {
+    let key = executor.new_public_key();
+    let account = executor.new_account(key);
+
+    // the staking package has to be published at the imported address
+    let staking_package = Address::from_str(STAKING_PACKAGE).unwrap();
+    executor.overwrite_package(
+        staking_package,
+        include_bytes!("../../staking/target/wasm32-unknown-unknown/release/staking.wasm"),
+    );
+    let package = executor.publish_package(include_code!("lottery"));
+
+    executor.set_current_epoch(0);
+
+    let transaction1 = TransactionBuilder::new(executor)
+        .call_function(staking_package, "Staking", "new", vec![format!("1000,{}", RADIX_TOKEN), "2".to_owned(), "100000".to_owned()], Some(account))
+        .drop_all_bucket_refs()
+        .deposit_all_buckets(account)
+        .build(vec![key])
+        .unwrap();
+    let receipt1 = executor.run(transaction1, false).unwrap();
+    println!("{:?}\n", receipt1);
+    assert!(receipt1.success);
+    let staking = receipt1.component(0).unwrap();
+
+    let transaction2 = TransactionBuilder::new(executor)
+        .call_function(package, "Lottery", "new", vec![staking.to_string()], Some(account))
+        .drop_all_bucket_refs()
+        .deposit_all_buckets(account)
+        .build(vec![key])
+        .unwrap();
+    let receipt2 = executor.run(transaction2, false).unwrap();
+    println!("{:?}\n", receipt2);
+    assert!(receipt2.success);
+
+    TestLottery {
+        staking,
+        key,
+        account,
+        component: receipt2.component(0).unwrap(),
+        admin_badge: receipt2.resource_def(0).unwrap(),
+        ticket_def: receipt2.resource_def(3).unwrap(),
+    }
+}
+
+fn call<'a, L: Ledger>(executor: &mut TransactionExecutor<'a, L>, lottery: &TestLottery, method: &str, args: Vec<String>) -> bool {
+    let transaction = TransactionBuilder::new(executor)
+        .call_method(lottery.component, method, args, Some(lottery.account))
+        .drop_all_bucket_refs()
+        .deposit_all_buckets(lottery.account)
+        .build(vec![lottery.key])
+        .unwrap();
+    let receipt = executor.run(transaction, false).unwrap();
+    println!("{:?}\n", receipt);
+    receipt.success
+}
+
+fn query<'a, L: Ledger, T: Decode>(executor: &mut TransactionExecutor<'a, L>, lottery: &TestLottery, component: Address, method: &str, args: Vec<String>) -> T {
+    let transaction = TransactionBuilder::new(executor)
+        .call_method(component, method, args, Some(lottery.account))
+        .build(vec![lottery.key])
+        .unwrap();
+    let mut receipt = executor.run(transaction, false).unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.success);
+    scrypto_decode(&receipt.results.swap_remove(0).unwrap().unwrap().encoded).unwrap()
+}
+
+fn get_assets<'a, L: Ledger>(executor: &mut TransactionExecutor<'a, L>, lottery: &TestLottery) -> Decimal {
+    query(executor, lottery, lottery.component, "get_assets", vec![])
+}
+
+// starts lottery #0 ending at epoch 10 with the reveal deadline at epoch 20 and buys two tickets
+fn start_and_buy<'a, L: Ledger>(executor: &mut TransactionExecutor<'a, L>, lottery: &TestLottery) {
+    let commitment = sha256(SECRET.as_bytes()).to_string();
+    assert!(call(executor, lottery, "start_lottery", vec![
+        "Lottery#1".to_owned(), "10".to_owned(), "100".to_owned(), commitment, "20".to_owned(), format!("1,{}", lottery.admin_badge)
+    ]));
+
+    assert!(call(executor, lottery, "buy_ticket", vec!["0".to_owned(), format!("100,{}", RADIX_TOKEN), "first".to_owned()]));
+    assert!(call(executor, lottery, "buy_ticket", vec!["0".to_owned(), format!("100,{}", RADIX_TOKEN), "second".to_owned()]));
+}
+
+#[test]
+fn test_wrong_reveal_is_rejected() {
+    let mut ledger = InMemoryLedger::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, 0, 0);
+    let lottery = setup(&mut executor);
+    start_and_buy(&mut executor, &lottery);
+
+    // the lottery can't end before its epoch
+    assert!(!call(&mut executor, &lottery, "end_lottery", vec!["0".to_owned(), SECRET.to_owned(), format!("1,{}", lottery.admin_badge)]));
+
+    executor.set_current_epoch(11);
+
+    // tickets can't be bought after the lottery epoch
+    assert!(!call(&mut executor, &lottery, "buy_ticket", vec!["0".to_owned(), format!("100,{}", RADIX_TOKEN), "late".to_owned()]));
+
+    // wrong secret
+    assert!(!call(&mut executor, &lottery, "end_lottery", vec!["0".to_owned(), "wrong secret".to_owned(), format!("1,{}", lottery.admin_badge)]));
+
+    // the lottery can't be cancelled before the reveal deadline
+    assert!(!call(&mut executor, &lottery, "cancel_lottery", vec!["0".to_owned()]));
+
+    // correct secret
+    assert!(call(&mut executor, &lottery, "end_lottery", vec!["0".to_owned(), SECRET.to_owned(), format!("1,{}", lottery.admin_badge)]));
+
+    // the lottery can only end once
+    assert!(!call(&mut executor, &lottery, "end_lottery", vec!["0".to_owned(), SECRET.to_owned(), format!("1,{}", lottery.admin_badge)]));
+}
+
+#[test]
+fn test_missed_reveal_allows_refund() {
+    let mut ledger = InMemoryLedger::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, 0, 0);
+    let lottery = setup(&mut executor);
+    start_and_buy(&mut executor, &lottery);
+
+    executor.set_current_epoch(21);
+
+    // the reveal deadline is passed
+    assert!(!call(&mut executor, &lottery, "end_lottery", vec!["0".to_owned(), SECRET.to_owned(), format!("1,{}", lottery.admin_badge)]));
+
+    // anyone can cancel the lottery now
+    assert!(call(&mut executor, &lottery, "cancel_lottery", vec!["0".to_owned()]));
+
+    // both tickets are refunded once
+    assert!(call(&mut executor, &lottery, "withdraw", vec!["0".to_owned(), format!("#0,{}", lottery.ticket_def)]));
+    assert!(call(&mut executor, &lottery, "withdraw", vec!["0".to_owned(), format!("#1,{}", lottery.ticket_def)]));
+    assert!(!call(&mut executor, &lottery, "withdraw", vec!["0".to_owned(), format!("#0,{}", lottery.ticket_def)]));
+}
+
+#[test]
+fn test_concurrent_lotteries() {
+    let mut ledger = InMemoryLedger::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, 0, 0);
+    let lottery = setup(&mut executor);
+    start_and_buy(&mut executor, &lottery);
+
+    // second lottery runs at the same time with its own staking position
+    let commitment = sha256(SECRET.as_bytes()).to_string();
+    assert!(call(&mut executor, &lottery, "start_lottery", vec![
+        "Lottery#2".to_owned(), "30".to_owned(), "50".to_owned(), commitment, "40".to_owned(), format!("1,{}", lottery.admin_badge)
+    ]));
+    assert!(call(&mut executor, &lottery, "buy_ticket", vec!["1".to_owned(), format!("50,{}", RADIX_TOKEN), "third".to_owned()]));
+
+    // ending the first lottery doesn't touch the stake of the second one
+    // 11 epochs of 0.0002 XRD are split 200:50 between the two lotteries
+    executor.set_current_epoch(11);
+    assert!(call(&mut executor, &lottery, "end_lottery", vec!["0".to_owned(), SECRET.to_owned(), format!("1,{}", lottery.admin_badge)]));
+    let reward1 = Decimal::from_str("0.00176").unwrap();
+    let (reward, winner): (Decimal, Option<u128>) = query(&mut executor, &lottery, lottery.component, "get_lottery_result", vec!["0".to_owned()]);
+    assert_eq!(reward, reward1);
+    assert_eq!(get_assets(&mut executor, &lottery), Decimal::from(200) + reward1);
+    let (balance, _reward, _paid): (Decimal, Decimal, Decimal) = query(&mut executor, &lottery, lottery.staking, "get_staking_data", vec!["1".to_owned()]);
+    assert_eq!(balance, 50.into());
+
+    // the winner ticket gets the reward on top of its price
+    let winner = winner.unwrap();
+    for ticket in 0..2u128 {
+        let assets = get_assets(&mut executor, &lottery);
+        assert!(call(&mut executor, &lottery, "withdraw", vec!["0".to_owned(), format!("#{},{}", ticket, lottery.ticket_def)]));
+        let withdrawn = assets - get_assets(&mut executor, &lottery);
+        let expected = if ticket == winner { Decimal::from(100) + reward1 } else { Decimal::from(100) };
+        assert_eq!(withdrawn, expected);
+    }
+
+    // the ticket of the second lottery can't be used for the first one
+    assert!(!call(&mut executor, &lottery, "withdraw", vec!["0".to_owned(), format!("#2,{}", lottery.ticket_def)]));
+
+    // the second lottery gets its share of the first 11 epochs and the whole 20 epochs after
+    executor.set_current_epoch(31);
+    assert!(call(&mut executor, &lottery, "end_lottery", vec!["1".to_owned(), SECRET.to_owned(), format!("1,{}", lottery.admin_badge)]));
+    let reward2 = Decimal::from_str("0.00444").unwrap();
+    let (reward, winner): (Decimal, Option<u128>) = query(&mut executor, &lottery, lottery.component, "get_lottery_result", vec!["1".to_owned()]);
+    assert_eq!((reward, winner), (reward2, Some(2)));
+    assert!(call(&mut executor, &lottery, "withdraw", vec!["1".to_owned(), format!("#2,{}", lottery.ticket_def)]));
+    assert_eq!(get_assets(&mut executor, &lottery), Decimal::zero());
+}
+
+#[test]
+fn test_staking_import_matches_staking_abi() {
+    let mut ledger = InMemoryLedger::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, 0, 0);
+    setup(&mut executor);
+
+    // the ABI imported by the lottery is generated from the Staking trait in src/staking.rs,
+    // it has to be the ABI exported by the Staking package, functions and methods included
+    let staking_abi = executor.export_abi(Address::from_str(STAKING_PACKAGE).unwrap(), "Staking", false).unwrap();
+    assert_eq!(serde_json::to_string_pretty(&staking_abi).unwrap(), lottery::Staking::ABI);
+}
@@ -24,6 +24,12 @@ blueprint! {
         last_update: u64,
         // reward rate per staking
         rate: Decimal,
+        // last epoch of the reward period
+        end_epoch: u64,
+        // rewards accrued by the stakers but not withdrawn yet
+        owed: Decimal,
+        // admin badge to top up the rewards pool and extend the reward period
+        admin_badge: ResourceDef,
         // collects rewards per account
         // rewards: HashMap<Address, Decimal>,
         // collects balance per account
@@ -35,10 +41,17 @@ blueprint! {
     impl Staking {
         
         // initiate component with some rewards pool
-        pub fn new(rewards: Bucket) -> Component {
+        // rate is the reward amount distributed per 10k epochs, rewards are distributed until the end epoch
+        pub fn new(rewards: Bucket, rate: Decimal, end_epoch: u64) -> (Component, Bucket) {
+            assert!(rate > Decimal::zero(), "Rate should be positive");
+            assert!(end_epoch > Context::current_epoch(), "End epoch should be in the future");
             
             let rewards_ref = rewards.resource_def();
 
+            let admin_badge = ResourceBuilder::new_fungible(DIVISIBILITY_NONE)
+            .metadata("name", "Staking Admin Badge")
+            .initial_supply_fungible(1);
+
             let token_minter = ResourceBuilder::new_fungible(DIVISIBILITY_NONE)
             .metadata("name", "Staking Token Minter")
             .initial_supply_fungible(1);
@@ -50,7 +63,7 @@ blueprint! {
             .badge(token_minter.resource_def(), MAY_MINT | MAY_CHANGE_INDIVIDUAL_METADATA)
             .no_initial_supply();
 
-            Self {
+            let component = Self {
                 token_minter: Vault::with_bucket(token_minter),
                 token_id_counter: 0,
                 token_resource_def: token,
@@ -58,12 +71,38 @@ blueprint! {
                 rewards_pool: Vault::with_bucket(rewards),
                 reward_value: Decimal::zero(),
                 last_update: Context::current_epoch(),
-                rate: 2.into(),
+                rate,
+                end_epoch,
+                owed: Decimal::zero(),
+                admin_badge: admin_badge.resource_def(),
                 // rewards: HashMap::new(),
                 // balances: HashMap::new(),
                 // paid: HashMap::new()
             }
-            .instantiate()
+            .instantiate();
+
+            (component, admin_badge)
+        }
+
+        // Calculates rewards distributed since the last update
+        // never more than the rewards pool can cover on top of the already owed rewards
+        fn get_emission(&self) -> Decimal {
+            let current_epoch = Context::current_epoch();
+            let end = if current_epoch < self.end_epoch { current_epoch } else { self.end_epoch };
+            if end <= self.last_update {
+                return Decimal::zero();
+            }
+
+            // 10k epochs as possible way to calculate the yearly rate to get the rate per epoch
+            let epoch_rate = self.rate / 10_000;
+            let emission = Decimal::from(end - self.last_update) * epoch_rate;
+
+            let available = self.rewards_pool.amount() - self.owed;
+            if emission > available {
+                debug!("Rewards pool is dry, emission is limited to: {}", available);
+                return available;
+            }
+            emission
         }
 
         // Calculates reward per token in supply
@@ -71,16 +110,13 @@ blueprint! {
 
             if self.staking_pool.is_empty() {
                 debug!("Pool is empty");
-                return Decimal::zero();
+                return self.reward_value;
             }
             
-            // calculate per token reward depending on the time passed since last epoch
-            let time = Context::current_epoch()-self.last_update;
+            // calculate per token reward depending on the rewards distributed since last epoch
             let exp = 1e18 as i64;
             let exp_rate = Decimal::from(exp) / self.staking_pool.amount();
-            // 10k epochs as possible way to calculate the yearly rate to get the rate per epoch
-            let epoch_rate = self.rate / 10_000;
-            let calculation = Decimal::from(time) * epoch_rate * exp_rate;
+            let calculation = self.get_emission() * exp_rate;
             let result = self.reward_value + calculation;
             result
         }
@@ -114,14 +150,40 @@ blueprint! {
         // private method that is used to update rewards during the epoch
         // will be called on each public request
         fn update_reward(&mut self, data: &StakingData) -> Decimal{
-            // update per token reward
+            self.update_reward_value();
+
+            let earned = self.earned(&data);
+
+            earned
+        }
+
+        // update per token reward and owed rewards up to the current epoch
+        fn update_reward_value(&mut self) {
+            // update per token reward before the owed rewards, both have to account the same emission
             self.reward_value = self.get_reward_value();
+            if !self.staking_pool.is_empty() {
+                self.owed += self.get_emission();
+            }
             // update epoch
             self.last_update = Context::current_epoch();
+        }
 
-            let earned = self.earned(&data);
+        // add rewards to the rewards pool
+        #[auth(admin_badge)]
+        pub fn top_up(&mut self, rewards: Bucket) {
+            // distribute rewards before the top up, so the dry period is not paid retroactively
+            self.update_reward_value();
+            self.rewards_pool.put(rewards);
+        }
 
-            earned
+        // extend the reward period
+        #[auth(admin_badge)]
+        pub fn extend(&mut self, end_epoch: u64) {
+            assert!(end_epoch > self.end_epoch, "New end epoch should be after the current one");
+            assert!(end_epoch > Context::current_epoch(), "End epoch should be in the future");
+            // distribute rewards until the previous end epoch
+            self.update_reward_value();
+            self.end_epoch = end_epoch;
         }
 
         /// Registers a new user
@@ -182,6 +244,7 @@ blueprint! {
             let bucket = self.staking_pool.take(data.balance);
             // add reward from the reward pool
             bucket.put(self.rewards_pool.take(data.reward));
+            self.owed -= data.reward;
             // reset balance and reward
             data.balance = Decimal::zero();
             data.reward = Decimal::zero();
@@ -193,5 +256,85 @@ blueprint! {
             bucket
         }
 
+
+        // unstake part of the staking balance, the reward stays in the account
+        #[auth(token_resource_def)]
+        pub fn unstake(&mut self, amount: Decimal) -> Bucket {
+            assert!(amount > Decimal::zero(), "Amount should be positive");
+
+            let id = auth.get_nft_id();
+            let mut data: StakingData = self.token_resource_def.get_nft_data(id);
+            assert!(amount <= data.balance, "Not enough staking balance");
+
+            let earned = self.update_reward(&data);
+            data.reward = earned;
+            data.paid = self.reward_value;
+            // take staking amount
+            let bucket = self.staking_pool.take(amount);
+            data.balance -= amount;
+
+            debug!("Account staking balance: {}", data.balance);
+
+            // update NFT
+            self.token_minter
+            .authorize(|auth| self.token_resource_def.update_nft_data(id, data, auth));
+
+            bucket
+        }
+
+        // withdraw the reward, the staking balance stays staked
+        #[auth(token_resource_def)]
+        pub fn claim_rewards(&mut self) -> Bucket {
+            let id = auth.get_nft_id();
+            let mut data: StakingData = self.token_resource_def.get_nft_data(id);
+
+            let earned = self.update_reward(&data);
+            data.paid = self.reward_value;
+            // take the reward from the reward pool
+            let bucket = self.rewards_pool.take(earned);
+            self.owed -= earned;
+            data.reward = Decimal::zero();
+
+            debug!("Claimed reward: {}", earned);
+
+            // update NFT
+            self.token_minter
+            .authorize(|auth| self.token_resource_def.update_nft_data(id, data, auth));
+
+            bucket
+        }
+
+        // stake the reward, adding it to the staking balance
+        #[auth(token_resource_def)]
+        pub fn compound(&mut self) {
+            let id = auth.get_nft_id();
+            let mut data: StakingData = self.token_resource_def.get_nft_data(id);
+
+            let earned = self.update_reward(&data);
+            data.paid = self.reward_value;
+            // move the reward from the reward pool to the staking pool
+            self.staking_pool.put(self.rewards_pool.take(earned));
+            self.owed -= earned;
+            data.balance += earned;
+            data.reward = Decimal::zero();
+
+            debug!("Account staking balance: {}", data.balance);
+
+            // update NFT
+            self.token_minter
+            .authorize(|auth| self.token_resource_def.update_nft_data(id, data, auth));
+        }
+
+        // staking and rewards pool amounts
+        pub fn get_pools(&self) -> (Decimal, Decimal) {
+            (self.staking_pool.amount(), self.rewards_pool.amount())
+        }
+
+        // balance, reward and paid reward value of a staking NFT
+        pub fn get_staking_data(&self, id: u128) -> (Decimal, Decimal, Decimal) {
+            let data: StakingData = self.token_resource_def.get_nft_data(id);
+            (data.balance, data.reward, data.paid)
+        }
+
     }
 }
@@ -0,0 +1,176 @@
+use radix_engine::ledger::*;
+use radix_engine::transaction::*;
+use scrypto::prelude::*;
+
+struct TestStaking {
+    key: Address,
+    account: Address,
+    component: Address,
+    admin_badge: Address,
+    token_def: Address,
+}
+
+// creates staking with 1 XRD of rewards, distributing 1 XRD per epoch until the epoch 100
+fn setup<'a, L: Ledger>(executor: &mut TransactionExecutor<'a, L>) -> TestStaking {
+    let key = executor.new_public_key();
+    let account = executor.new_account(key);
+    let package = executor.publish_package(include_code!("staking"));
+
+    executor.set_current_epoch(0);
+
+    let transaction = TransactionBuilder::new(executor)
+        .call_function(package, "Staking", "new", vec![format!("1,{}", RADIX_TOKEN), "10000".to_owned(), "100".to_owned()], Some(account))
+        .drop_all_bucket_refs()
+        .deposit_all_buckets(account)
+        .build(vec![key])
+        .unwrap();
+    let receipt = executor.run(transaction, false).unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.success);
+
+    TestStaking {
+        key,
+        account,
+        component: receipt.component(0).unwrap(),
+        admin_badge: receipt.resource_def(0).unwrap(),
+        token_def: receipt.resource_def(2).unwrap(),
+    }
+}
+
+fn call<'a, L: Ledger>(executor: &mut TransactionExecutor<'a, L>, staking: &TestStaking, method: &str, args: Vec<String>) -> bool {
+    let transaction = TransactionBuilder::new(executor)
+        .call_method(staking.component, method, args, Some(staking.account))
+        .drop_all_bucket_refs()
+        .deposit_all_buckets(staking.account)
+        .build(vec![staking.key])
+        .unwrap();
+    let receipt = executor.run(transaction, false).unwrap();
+    println!("{:?}\n", receipt);
+    receipt.success
+}
+
+fn query<'a, L: Ledger, T: Decode>(executor: &mut TransactionExecutor<'a, L>, staking: &TestStaking, method: &str, args: Vec<String>) -> T {
+    let transaction = TransactionBuilder::new(executor)
+        .call_method(staking.component, method, args, Some(staking.account))
+        .build(vec![staking.key])
+        .unwrap();
+    let mut receipt = executor.run(transaction, false).unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.success);
+    scrypto_decode(&receipt.results.swap_remove(0).unwrap().unwrap().encoded).unwrap()
+}
+
+// rewards pool amount
+fn rewards_pool<'a, L: Ledger>(executor: &mut TransactionExecutor<'a, L>, staking: &TestStaking) -> Decimal {
+    let (_staked, rewards): (Decimal, Decimal) = query(executor, staking, "get_pools", vec![]);
+    rewards
+}
+
+// balance, reward and paid reward value of the staking NFT
+fn staking_data<'a, L: Ledger>(executor: &mut TransactionExecutor<'a, L>, staking: &TestStaking, id: u128) -> (Decimal, Decimal, Decimal) {
+    query(executor, staking, "get_staking_data", vec![id.to_string()])
+}
+
+#[test]
+fn test_pool_runs_dry_in_the_middle_of_period() {
+    let mut ledger = InMemoryLedger::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, 0, 0);
+    let staking = setup(&mut executor);
+
+    // two stakers
+    assert!(call(&mut executor, &staking, "new_user", vec![]));
+    assert!(call(&mut executor, &staking, "new_user", vec![]));
+    assert!(call(&mut executor, &staking, "stake", vec![format!("100,{}", RADIX_TOKEN), format!("#0,{}", staking.token_def)]));
+    assert!(call(&mut executor, &staking, "stake", vec![format!("300,{}", RADIX_TOKEN), format!("#1,{}", staking.token_def)]));
+
+    // 50 XRD of rewards are accrued by the rate but the pool only has 1 XRD
+    executor.set_current_epoch(50);
+
+    // both stakers can withdraw, the rewards are limited by the pool and split by the staked amounts
+    assert!(call(&mut executor, &staking, "withdraw", vec![format!("#0,{}", staking.token_def)]));
+    let reward0 = Decimal::from(1) - rewards_pool(&mut executor, &staking);
+    assert!(call(&mut executor, &staking, "withdraw", vec![format!("#1,{}", staking.token_def)]));
+    let reward1 = Decimal::from(1) - reward0 - rewards_pool(&mut executor, &staking);
+
+    assert_eq!(reward0, Decimal::from_str("0.25").unwrap());
+    assert_eq!(reward1, Decimal::from_str("0.75").unwrap());
+    assert!(reward0 + reward1 <= Decimal::from(1));
+    let pools: (Decimal, Decimal) = query(&mut executor, &staking, "get_pools", vec![]);
+    assert_eq!(pools, (Decimal::zero(), Decimal::zero()));
+    assert_eq!(staking_data(&mut executor, &staking, 0).0, Decimal::zero());
+    assert_eq!(staking_data(&mut executor, &staking, 1).0, Decimal::zero());
+}
+
+#[test]
+fn test_top_up_and_extend() {
+    let mut ledger = InMemoryLedger::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, 0, 0);
+    let staking = setup(&mut executor);
+
+    assert!(call(&mut executor, &staking, "new_user", vec![]));
+    assert!(call(&mut executor, &staking, "stake", vec![format!("100,{}", RADIX_TOKEN), format!("#0,{}", staking.token_def)]));
+
+    // the pool runs dry, then gets topped up
+    executor.set_current_epoch(10);
+    assert!(!call(&mut executor, &staking, "top_up", vec![format!("100,{}", RADIX_TOKEN)]));
+    assert!(call(&mut executor, &staking, "top_up", vec![format!("1000,{}", RADIX_TOKEN), format!("1,{}", staking.admin_badge)]));
+    // the 10 epochs before the top up are only owed the 1 XRD the pool had
+    assert_eq!(rewards_pool(&mut executor, &staking), 1001.into());
+
+    // the period can only be extended by the admin and into the future
+    assert!(!call(&mut executor, &staking, "extend", vec!["200".to_owned()]));
+    assert!(!call(&mut executor, &staking, "extend", vec!["50".to_owned(), format!("1,{}", staking.admin_badge)]));
+    assert!(call(&mut executor, &staking, "extend", vec!["200".to_owned(), format!("1,{}", staking.admin_badge)]));
+
+    // rewards stop at the end of the period, 190 epochs are paid after the top up instead of 290
+    executor.set_current_epoch(300);
+    assert!(call(&mut executor, &staking, "withdraw", vec![format!("#0,{}", staking.token_def)]));
+    assert_eq!(rewards_pool(&mut executor, &staking), 810.into());
+    let (balance, reward, _paid) = staking_data(&mut executor, &staking, 0);
+    assert_eq!((balance, reward), (Decimal::zero(), Decimal::zero()));
+}
+
+#[test]
+fn test_partial_unstake_claim_and_compound() {
+    let mut ledger = InMemoryLedger::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, 0, 0);
+    let staking = setup(&mut executor);
+    assert!(call(&mut executor, &staking, "top_up", vec![format!("1000,{}", RADIX_TOKEN), format!("1,{}", staking.admin_badge)]));
+
+    assert!(call(&mut executor, &staking, "new_user", vec![]));
+    assert!(call(&mut executor, &staking, "stake", vec![format!("100,{}", RADIX_TOKEN), format!("#0,{}", staking.token_def)]));
+
+    executor.set_current_epoch(10);
+
+    // only the staked balance can be unstaked
+    assert!(!call(&mut executor, &staking, "unstake", vec!["101".to_owned(), format!("#0,{}", staking.token_def)]));
+    assert!(!call(&mut executor, &staking, "unstake", vec!["0".to_owned(), format!("#0,{}", staking.token_def)]));
+    assert!(call(&mut executor, &staking, "unstake", vec!["40".to_owned(), format!("#0,{}", staking.token_def)]));
+    // 10 epochs at 1 XRD for 100 XRD staked, the reward per token is scaled by 1e18
+    let paid = Decimal::from(100_000_000_000_000_000i64);
+    assert_eq!(staking_data(&mut executor, &staking, 0), (60.into(), 10.into(), paid));
+
+    // rewards can be claimed and compounded while the principal stays staked
+    assert!(call(&mut executor, &staking, "claim_rewards", vec![format!("#0,{}", staking.token_def)]));
+    assert_eq!(staking_data(&mut executor, &staking, 0), (60.into(), Decimal::zero(), paid));
+    assert_eq!(rewards_pool(&mut executor, &staking), 991.into());
+
+    executor.set_current_epoch(20);
+    assert!(call(&mut executor, &staking, "compound", vec![format!("#0,{}", staking.token_def)]));
+    // about 10 XRD of rewards are added to the balance, rounded down
+    let (balance, reward, compound_paid) = staking_data(&mut executor, &staking, 0);
+    assert!(balance > Decimal::from_str("69.99").unwrap() && balance <= 70.into(), "balance is {}", balance);
+    assert_eq!(reward, Decimal::zero());
+    assert!(compound_paid > paid);
+    let (staked, rewards): (Decimal, Decimal) = query(&mut executor, &staking, "get_pools", vec![]);
+    assert_eq!(staked, balance);
+    assert_eq!(rewards + balance, Decimal::from(1051));
+
+    // the remaining 60 XRD plus the compounded rewards can be unstaked
+    assert!(!call(&mut executor, &staking, "unstake", vec!["71".to_owned(), format!("#0,{}", staking.token_def)]));
+    assert!(call(&mut executor, &staking, "unstake", vec![balance.to_string(), format!("#0,{}", staking.token_def)]));
+    assert_eq!(staking_data(&mut executor, &staking, 0), (Decimal::zero(), Decimal::zero(), compound_paid));
+
+    executor.set_current_epoch(30);
+    assert!(call(&mut executor, &staking, "withdraw", vec![format!("#0,{}", staking.token_def)]));
+}